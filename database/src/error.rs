@@ -3,11 +3,25 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum DatabaseError {
     #[error("Sqlx error: {0}")]
-    Sqlx(#[from] sqlx::Error),
+    Sqlx(sqlx::Error),
 
     #[error("Migration error: {0}")]
     Migrate(#[from] sqlx::migrate::MigrateError),
 
     #[error("Query returned no rows")]
     NotFound,
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+}
+
+impl From<sqlx::Error> for DatabaseError {
+    fn from(value: sqlx::Error) -> Self {
+        match value {
+            sqlx::Error::Database(err) if err.is_unique_violation() => {
+                Self::Conflict(err.message().to_string())
+            }
+            other => Self::Sqlx(other),
+        }
+    }
 }
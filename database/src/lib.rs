@@ -2,20 +2,41 @@ use sqlx::{SqlitePool, migrate::MigrateDatabase, sqlite::SqlitePoolOptions};
 
 use crate::{
     error::DatabaseError,
-    repos::{achievement::AchievementRepo, service::ServiceRepo, tag::TagRepo, user::UserRepo},
+    models::{service::Namespace, user::OfUser},
+    repos::{
+        achievement::AchievementRepo, follow::FollowRepo, impersonation::ImpersonationRepo,
+        kudos::KudosRepo, profile_change::ProfileChangeRepo, profile_field::ProfileFieldRepo,
+        service::ServiceRepo, service_data::ServiceDataRepo, service_usage::ServiceUsageRepo,
+        tag::TagRepo, unlock::UnlockRepo, user::UserRepo,
+    },
 };
 
 pub mod models {
     pub mod achievement;
+    pub mod follow;
+    pub mod impersonation;
+    pub mod kudos;
+    pub mod profile_change;
+    pub mod profile_field;
     pub mod service;
+    pub mod service_data;
+    pub mod service_usage;
     pub mod tag;
     pub mod user;
 }
 
 pub mod repos {
     pub mod achievement;
+    pub mod follow;
+    pub mod impersonation;
+    pub mod kudos;
+    pub mod profile_change;
+    pub mod profile_field;
     pub mod service;
+    pub mod service_data;
+    pub mod service_usage;
     pub mod tag;
+    pub mod unlock;
     pub mod user;
 }
 
@@ -59,7 +80,49 @@ impl Database {
         ServiceRepo::new(&self.db)
     }
 
+    pub fn service_data<'a>(&'a self, namespace: Namespace) -> ServiceDataRepo<'a> {
+        ServiceDataRepo::new(&self.db, namespace)
+    }
+
+    pub fn unlocks<'a>(&'a self, namespace: Namespace) -> UnlockRepo<'a> {
+        UnlockRepo::new(&self.db, namespace)
+    }
+
+    pub fn service_usage<'a>(&'a self) -> ServiceUsageRepo<'a> {
+        ServiceUsageRepo::new(&self.db)
+    }
+
     pub fn achievements<'a>(&'a self) -> AchievementRepo<'a> {
         AchievementRepo::new(&self.db)
     }
+
+    pub fn profile_fields<'a>(&'a self) -> ProfileFieldRepo<'a> {
+        ProfileFieldRepo::new(&self.db)
+    }
+
+    pub fn impersonations<'a>(&'a self) -> ImpersonationRepo<'a> {
+        ImpersonationRepo::new(&self.db)
+    }
+
+    pub fn profile_changes<'a>(&'a self) -> ProfileChangeRepo<'a> {
+        ProfileChangeRepo::new(&self.db)
+    }
+
+    pub fn follows<'a>(&'a self) -> FollowRepo<'a> {
+        FollowRepo::new(&self.db)
+    }
+
+    pub fn kudos<'a>(&'a self) -> KudosRepo<'a> {
+        KudosRepo::new(&self.db)
+    }
+}
+
+/// `?` placeholders for a list of values in an `IN (...)`, the list can't be empty
+pub(crate) fn placeholders(count: usize) -> String {
+    vec!["?"; count].join(", ")
+}
+
+/// the rows of a lookup of several users, for a lookup of a single user
+pub(crate) fn items<T>(rows: Vec<OfUser<T>>) -> Vec<T> {
+    rows.into_iter().map(|row| row.item).collect()
 }
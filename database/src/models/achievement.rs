@@ -49,3 +49,12 @@ pub struct AchievementCreate {
     pub name: String,
     pub goals: Vec<GoalCreate>,
 }
+
+/// a goal a user has reached
+#[derive(Debug, Serialize, Deserialize, FromRow, PartialEq)]
+pub struct Unlock {
+    pub user_id: u32,
+    pub goal_id: u32,
+    pub service_id: u32,
+    pub time: DateTime<Local>,
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// a user someone follows
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct Follow {
+    pub user_id: u32,
+    pub username: String,
+    pub followed_at: DateTime<Utc>,
+}
+
+/// amount of users that follow and are followed by a user
+#[derive(Debug, Clone, Copy, Default, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct FollowCounts {
+    pub followers: u32,
+    pub following: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum FeedKind {
+    Unlock,
+    ProfileUpdate,
+}
+
+/// something a followed user did, `kind` decides which of the optional fields are set
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct FeedEntry {
+    pub kind: FeedKind,
+    pub user_id: u32,
+    pub username: String,
+    pub time: DateTime<Utc>,
+    pub goal_id: Option<u32>,
+    pub achievement_id: Option<u32>,
+    pub service_id: Option<u32>,
+    /// the part of the profile that was updated
+    pub field: Option<String>,
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// an admin acting as another user, as kept in the audit log
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct Impersonation {
+    pub id: u32,
+    pub admin_id: u32,
+    pub admin_username: String,
+    pub user_id: u32,
+    pub username: String,
+    /// whether the admin could make changes as the user
+    pub allow_writes: bool,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the impersonation is still going on
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct ImpersonationCreate {
+    pub admin_id: u32,
+    pub admin_username: String,
+    pub user_id: u32,
+    pub username: String,
+    pub allow_writes: bool,
+    /// the impersonation ends with the session of the admin
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImpersonationFilter {
+    pub admin_id: Option<u32>,
+    pub user_id: Option<u32>,
+}
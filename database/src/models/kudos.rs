@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct Kudos {
+    pub id: u32,
+    pub giver_id: u32,
+    pub giver_username: String,
+    pub recipient_id: u32,
+    pub message: String,
+    /// `None` if the kudos is for a free skill, or the tag was deleted
+    pub tag_id: Option<u32>,
+    pub tag_name: Option<String>,
+    pub skill: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// when the recipient hid it, only they and admins see hidden kudos
+    pub hidden_at: Option<DateTime<Utc>>,
+    /// when an admin removed it, only admins see removed kudos
+    pub removed_at: Option<DateTime<Utc>>,
+    /// when the giver took it back, only admins see withdrawn kudos
+    pub withdrawn_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct KudosCreate {
+    pub giver_id: u32,
+    pub recipient_id: u32,
+    pub message: String,
+    pub tag_id: Option<u32>,
+    pub skill: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct KudosFilter {
+    /// kudos given or received by this user
+    pub user_id: Option<u32>,
+    pub removed: Option<bool>,
+}
+
+/// which of the kudos a user got are shown to a viewer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KudosScope {
+    /// kudos the recipient hid, only for the recipient themselves
+    pub hidden: bool,
+    /// kudos from givers whose profile is only shown to members
+    pub members_only_givers: bool,
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// one field of a profile that changed
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct ProfileChange {
+    pub id: u32,
+    pub user_id: u32,
+    /// e.g. `about`, `tags` or `fields.pronouns`
+    pub field: String,
+    /// `None` if the field was not set
+    pub old_value: Option<String>,
+    /// `None` if the field was cleared
+    pub new_value: Option<String>,
+    pub actor_user_id: Option<u32>,
+    /// username of the actor, if they still exist
+    pub actor_username: Option<String>,
+    pub actor_service_id: Option<u32>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// who made a change to a profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Actor {
+    User(u32),
+    Service(u32),
+    /// zpi itself, e.g. syncing tags from zauth roles
+    System,
+}
+
+impl Actor {
+    pub(crate) fn user_id(&self) -> Option<u32> {
+        match self {
+            Actor::User(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn service_id(&self) -> Option<u32> {
+        match self {
+            Actor::Service(id) => Some(*id),
+            _ => None,
+        }
+    }
+}
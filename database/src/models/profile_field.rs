@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::models::user::Visibility;
+
+/// an extra profile field defined by admins
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct ProfileField {
+    pub id: u32,
+    /// key used in the api, like `pronouns`
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    /// json encoded array of the allowed values of an enum field
+    pub options: String,
+    /// maximum length of a text field, `None` for the default
+    pub max_length: Option<u32>,
+    /// who can see the values of this field
+    pub visibility: Visibility,
+    pub position: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum FieldKind {
+    Text,
+    /// one of a fixed list of options
+    Enum,
+    /// a date like `2025-09-22`
+    Date,
+    Url,
+}
+
+/// value of a profile field for a user
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct UserFieldValue {
+    pub field_id: u32,
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    pub visibility: Visibility,
+    pub value: String,
+}
+
+pub struct ProfileFieldCreate {
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    /// json encoded array
+    pub options: String,
+    pub max_length: Option<u32>,
+    pub visibility: Visibility,
+    pub position: u32,
+}
+
+/// fields that are `None` are left unchanged
+pub struct ProfileFieldPatch {
+    pub label: Option<String>,
+    /// json encoded array
+    pub options: Option<String>,
+    /// `Some(None)` goes back to the default maximum
+    pub max_length: Option<Option<u32>>,
+    pub visibility: Option<Visibility>,
+    pub position: Option<u32>,
+}
@@ -6,6 +6,10 @@ pub struct Service {
     pub id: u32,
     pub name: String,
     pub api_key: String,
+    pub test_api_key: Option<String>,
+    pub owner_id: Option<u32>,
+    pub rate_limit_burst: u32,
+    pub rate_limit_per_minute: u32,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -16,4 +20,39 @@ pub struct ServiceCreate {
 #[derive(Serialize, Deserialize)]
 pub struct ServicePatch {
     pub name: String,
+    /// `None` keeps the current owner, `Some(None)` removes it
+    pub owner_id: Option<Option<u32>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ServiceRateLimitPatch {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+/// where the data a service writes ends up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum Namespace {
+    /// real data, shown on profiles
+    Production,
+    /// written with a test api key, only visible to the service owner and the user
+    Sandbox,
+}
+
+impl Namespace {
+    pub(crate) fn unlock_table(self) -> &'static str {
+        match self {
+            Self::Production => "unlock",
+            Self::Sandbox => "sandbox_unlock",
+        }
+    }
+
+    pub(crate) fn service_data_table(self) -> &'static str {
+        match self {
+            Self::Production => "service_data",
+            Self::Sandbox => "sandbox_service_data",
+        }
+    }
 }
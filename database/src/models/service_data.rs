@@ -0,0 +1,24 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+/// a json value a service stores about a user
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct ServiceData {
+    pub service_id: u32,
+    pub user_id: u32,
+    pub key: String,
+    /// json encoded value
+    pub value: String,
+    pub version: u32,
+    pub updated_at: DateTime<Local>,
+}
+
+pub struct ServiceDataPut {
+    pub key: String,
+    pub value: String,
+    /// version the service last saw, `None` if the key should not exist yet
+    pub version: Option<u32>,
+    /// most keys the service can store for the user, checked when a key is created
+    pub max_keys: u32,
+}
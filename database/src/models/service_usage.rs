@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+use crate::models::service::Namespace;
+
+/// requests to one endpoint with one status code, summed over a time range
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct EndpointUsage {
+    pub endpoint: String,
+    pub status: u16,
+    pub count: u32,
+    pub last_called_at: DateTime<Utc>,
+}
+
+/// all requests of a service in one hour
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct HourlyUsage {
+    pub hour: DateTime<Utc>,
+    pub requests: u32,
+    pub errors: u32,
+}
+
+/// requests counted since the last write, for one bucket of `service_usage`
+#[derive(Debug)]
+pub struct UsageCount {
+    pub service_id: u32,
+    pub namespace: Namespace,
+    /// start of the hour the requests were made in
+    pub hour: DateTime<Utc>,
+    pub endpoint: String,
+    pub status: u16,
+    pub count: u32,
+    pub last_called_at: DateTime<Utc>,
+}
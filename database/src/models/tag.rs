@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
@@ -7,4 +8,139 @@ pub struct Tag {
     pub name: String,
     pub category: String,
     pub description: Option<String>,
+    /// css hex color, the one of the category if the tag has none
+    pub color: String,
+    /// whether the tag or its category has an icon
+    pub icon: bool,
+}
+
+/// a tag as held by a user, with the period they held it
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq)]
+pub struct UserTag {
+    /// id of the tag, not of the assignment
+    #[serde(rename = "id")]
+    pub tag_id: u32,
+    pub name: String,
+    pub category: String,
+    pub description: Option<String>,
+    pub color: String,
+    pub icon: bool,
+    /// `None` if it's unknown since when the user has the tag
+    pub valid_from: Option<DateTime<Utc>>,
+    /// `None` if the user keeps the tag until further notice
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+/// one period in which a user held a tag
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq)]
+pub struct TagAssignment {
+    pub id: u32,
+    pub user_id: u32,
+    pub tag_id: u32,
+    pub source: TagSource,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+/// how a user got a tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum TagSource {
+    /// by an admin, the user themselves or a service
+    Manual,
+    /// synced from a zauth role on login
+    Zauth,
+}
+
+pub struct TagAssignmentCreate {
+    pub tag_id: u32,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+/// fields that are `None` are left unchanged, `Some(None)` clears them
+pub struct TagAssignmentPatch {
+    pub valid_from: Option<Option<DateTime<Utc>>>,
+    pub valid_until: Option<Option<DateTime<Utc>>>,
+}
+
+/// a tag with the amount of members holding it
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq)]
+pub struct TagOverview {
+    pub id: u32,
+    pub name: String,
+    pub category: String,
+    pub description: Option<String>,
+    pub color: String,
+    pub icon: bool,
+    /// private profiles are not counted
+    pub members: u32,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, PartialEq)]
+pub struct TagCategory {
+    pub id: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub policy: TagPolicy,
+    /// how many tags of this category a user can have, `None` for no limit
+    pub max_per_user: Option<u32>,
+    /// the service that can hand out the tags of a `ServiceAssignable` category
+    pub service_id: Option<u32>,
+    /// css hex color, used by tags without their own color
+    pub color: String,
+    pub icon: bool,
+}
+
+/// who can give the tags of a category to a user
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "TEXT", rename_all = "snake_case")]
+pub enum TagPolicy {
+    /// only admins
+    #[default]
+    AdminOnly,
+    /// users can pick these tags themselves
+    SelfAssignable,
+    /// services can give these tags with their api key
+    ServiceAssignable,
+}
+
+pub struct TagCreate {
+    pub name: String,
+    pub description: Option<String>,
+    pub category_id: u32,
+    /// `None` uses the color of the category
+    pub color: Option<String>,
+}
+
+/// fields that are `None` are left unchanged
+pub struct TagPatch {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub category_id: Option<u32>,
+    /// `Some(None)` goes back to the color of the category
+    pub color: Option<Option<String>>,
+}
+
+pub struct TagCategoryCreate {
+    pub name: String,
+    pub description: Option<String>,
+    pub policy: TagPolicy,
+    pub max_per_user: Option<u32>,
+    pub service_id: Option<u32>,
+    pub color: String,
+}
+
+/// fields that are `None` are left unchanged
+pub struct TagCategoryPatch {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub policy: Option<TagPolicy>,
+    /// `Some(None)` removes the maximum
+    pub max_per_user: Option<Option<u32>>,
+    /// `Some(None)` removes the service
+    pub service_id: Option<Option<u32>>,
+    pub color: Option<String>,
 }
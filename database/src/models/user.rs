@@ -1,11 +1,79 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
 pub struct User {
     pub id: u32,
     pub username: String,
     pub about: String,
+    pub visibility: Visibility,
+    #[sqlx(flatten)]
+    pub field_visibility: FieldVisibility,
+}
+
+/// a row of one of the users that were looked up at once
+#[derive(Debug, FromRow)]
+pub struct OfUser<T> {
+    pub user_id: u32,
+    #[sqlx(flatten)]
+    pub item: T,
+}
+
+/// a user found by a current or former username
+#[derive(Debug, FromRow)]
+pub struct UsernameMatch {
+    /// the username that was looked up
+    pub name: String,
+    #[sqlx(flatten)]
+    pub user: User,
+}
+
+impl User {
+    /// visibility of a part of the profile, an override can only make it stricter
+    pub fn visibility_of(&self, part: Option<Visibility>) -> Visibility {
+        part.map_or(self.visibility, |part| part.max(self.visibility))
+    }
+}
+
+/// who is allowed to see a profile, from least to most strict
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum Visibility {
+    /// everyone, also people who aren't logged in
+    Public,
+    /// logged in members and services
+    #[default]
+    Members,
+    /// only the user themselves and admins
+    Private,
+}
+
+/// overrides of the profile visibility for parts of a profile, `None` follows the profile
+#[derive(Debug, Clone, Copy, Default, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct FieldVisibility {
+    #[sqlx(rename = "about_visibility")]
+    pub about: Option<Visibility>,
+    /// current and former tags
+    #[sqlx(rename = "tags_visibility")]
+    pub tags: Option<Visibility>,
+    #[sqlx(rename = "achievements_visibility")]
+    pub achievements: Option<Visibility>,
+    #[sqlx(rename = "image_visibility")]
+    pub image: Option<Visibility>,
+}
+
+/// a username a user had before zauth renamed them
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct FormerUsername {
+    pub username: String,
+    /// when the user stopped having this name
+    pub changed_at: DateTime<Utc>,
 }
 
 pub struct UserCreate {
@@ -13,7 +81,144 @@ pub struct UserCreate {
     pub username: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct UserPatch {
-    pub about: String,
+    pub about: Option<String>,
+    pub visibility: Option<Visibility>,
+    /// replaces all overrides of the profile visibility
+    pub field_visibility: Option<FieldVisibility>,
+    /// replaces all links of the user, in this order
+    pub links: Option<Vec<SocialLink>>,
+    /// values of profile fields by field name, `None` removes the value
+    ///
+    /// fields that are not given are left unchanged
+    pub fields: Option<BTreeMap<String, Option<String>>>,
+}
+
+/// order of the member directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserSort {
+    /// alphabetically
+    #[default]
+    Username,
+    /// newest members first
+    Joined,
+    /// most recently active first
+    Active,
+}
+
+impl UserSort {
+    /// column the users are sorted on
+    pub(crate) fn column(self) -> &'static str {
+        match self {
+            Self::Username => "user.username",
+            Self::Joined => "user.created_at",
+            Self::Active => "user.last_active_at",
+        }
+    }
+
+    pub(crate) fn direction(self) -> &'static str {
+        match self {
+            Self::Username => "ASC",
+            Self::Joined | Self::Active => "DESC",
+        }
+    }
+
+    /// comparison that selects the users after a cursor
+    pub(crate) fn after(self) -> &'static str {
+        match self {
+            Self::Username => ">",
+            Self::Joined | Self::Active => "<",
+        }
+    }
+}
+
+/// conditions users in a listing have to meet, `None` doesn't filter
+#[derive(Debug, Default)]
+pub struct UserFilter {
+    /// fts5 match expression on the username, about and tag names
+    pub search: Option<String>,
+    /// name of a tag the user currently holds
+    pub tag: Option<String>,
+    /// achievement of which the user unlocked at least one goal
+    pub achievement_id: Option<u32>,
+    pub has_image: Option<bool>,
+}
+
+/// a user in a listing, with the value it's sorted on
+#[derive(Debug, FromRow)]
+pub struct UserListEntry {
+    #[sqlx(flatten)]
+    pub user: User,
+    pub sort_key: String,
+}
+
+/// everything admins can see about a user
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct UserRecord {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub user: User,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
+    /// `None` if the user didn't log in since logins are tracked
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub has_image: bool,
+    /// `None` if the user can log in
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub disabled_reason: Option<String>,
+}
+
+/// conditions users in the admin listing have to meet, `None` doesn't filter
+#[derive(Debug, Default)]
+pub struct UserRecordFilter {
+    /// part of the username, or the exact id
+    pub search: Option<String>,
+    pub disabled: Option<bool>,
+}
+
+/// changes an admin makes to a user, `None` leaves it unchanged
+#[derive(Debug, Default)]
+pub struct UserModeration {
+    pub about: Option<String>,
+    /// disabling a user also ends their sessions
+    pub disabled: Option<bool>,
+    /// only kept while the user is disabled
+    pub disabled_reason: Option<String>,
+}
+
+/// a session a user logged in with
+#[derive(Debug, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct UserSession {
+    pub logged_in_at: DateTime<Utc>,
+    /// the session can't be used after this, unless the user is active before it
+    pub expires_at: DateTime<Utc>,
+}
+
+/// what decides whether a session of a user is still valid
+#[derive(Debug, FromRow)]
+pub struct SessionState {
+    /// sessions that started before this were ended
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub disabled: bool,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, PartialEq)]
+pub struct SocialLink {
+    pub kind: LinkKind,
+    /// username, address or url, depending on the kind
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+pub enum LinkKind {
+    Github,
+    Gitlab,
+    Mattermost,
+    Matrix,
+    Website,
+    Email,
 }
@@ -0,0 +1,205 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    error::DatabaseError,
+    items,
+    models::{
+        follow::{FeedEntry, Follow, FollowCounts},
+        user::{OfUser, User},
+    },
+    placeholders,
+};
+
+/// unlocks and profile updates of everyone a user follows, bound with the follower id twice
+///
+/// like in the member directory, private profiles and private parts of profiles are left out
+/// updates only count when the user made them themselves, not an admin or service
+static FEED: &str = "
+    SELECT 'unlock' AS kind, user.id AS user_id, user.username, unlock.time AS time,
+        unlock.goal_id, goal.achievement_id, achievement.service_id, NULL AS field
+    FROM follow
+        INNER JOIN user ON follow.followed_id = user.id
+        INNER JOIN unlock ON unlock.user_id = user.id
+        INNER JOIN goal ON unlock.goal_id = goal.id
+        INNER JOIN achievement ON goal.achievement_id = achievement.id
+    WHERE follow.follower_id = ? AND user.visibility != 'private'
+        AND COALESCE(user.achievements_visibility, '') != 'private'
+    UNION ALL
+    SELECT 'profile_update', user.id, user.username, profile_change.changed_at,
+        NULL, NULL, NULL, profile_change.field
+    FROM follow
+        INNER JOIN user ON follow.followed_id = user.id
+        INNER JOIN profile_change ON profile_change.user_id = user.id
+    WHERE follow.follower_id = ? AND user.visibility != 'private'
+        AND profile_change.actor_user_id = user.id
+        AND CASE profile_change.field
+            WHEN 'about' THEN COALESCE(user.about_visibility, '') != 'private'
+            WHEN 'tags' THEN COALESCE(user.tags_visibility, '') != 'private'
+            WHEN 'image' THEN COALESCE(user.image_visibility, '') != 'private'
+            WHEN 'links' THEN TRUE
+            ELSE FALSE
+        END";
+
+pub struct FollowRepo<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> FollowRepo<'a> {
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// does nothing if the user is already followed
+    pub async fn follow(&self, follower_id: u32, followed_id: u32) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "INSERT INTO follow (follower_id, followed_id) VALUES (?, ?)
+            ON CONFLICT DO NOTHING;",
+        )
+        .bind(follower_id)
+        .bind(followed_id)
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// does nothing if the user isn't followed
+    pub async fn unfollow(&self, follower_id: u32, followed_id: u32) -> Result<(), DatabaseError> {
+        sqlx::query("DELETE FROM follow WHERE follower_id = ? AND followed_id = ?;")
+            .bind(follower_id)
+            .bind(followed_id)
+            .execute(self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// private profiles are not counted, like they are not listed
+    pub async fn counts(&self, user_id: u32) -> Result<FollowCounts, DatabaseError> {
+        Ok(items(self.counts_for_users(&[user_id]).await?)
+            .pop()
+            .unwrap_or_default())
+    }
+
+    /// counts of each of the users, users that don't exist are left out
+    pub async fn counts_for_users(
+        &self,
+        user_ids: &[u32],
+    ) -> Result<Vec<OfUser<FollowCounts>>, DatabaseError> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT user.id AS user_id,
+                (SELECT COUNT(*) FROM follow INNER JOIN user AS follower
+                    ON follow.follower_id = follower.id
+                WHERE follow.followed_id = user.id AND follower.visibility != 'private') AS followers,
+                (SELECT COUNT(*) FROM follow INNER JOIN user AS followed
+                    ON follow.followed_id = followed.id
+                WHERE follow.follower_id = user.id AND followed.visibility != 'private') AS following
+            FROM user WHERE user.id IN ({});
+            ",
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for user_id in user_ids {
+            query = query.bind(user_id);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+
+    /// users that follow a user, most recent first, without private profiles
+    pub async fn followers(
+        &self,
+        user_id: u32,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<User>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT user.id, user.username, user.about, user.visibility,
+                user.about_visibility, user.tags_visibility, user.achievements_visibility,
+                user.image_visibility
+            FROM follow
+                INNER JOIN user ON follow.follower_id = user.id
+            WHERE follow.followed_id = ? AND user.visibility != 'private'
+            ORDER BY follow.followed_at DESC, user.id
+            LIMIT ? OFFSET ?;
+            ",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// users a user follows, most recent first, without private profiles
+    pub async fn following(
+        &self,
+        user_id: u32,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<User>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT user.id, user.username, user.about, user.visibility,
+                user.about_visibility, user.tags_visibility, user.achievements_visibility,
+                user.image_visibility
+            FROM follow
+                INNER JOIN user ON follow.followed_id = user.id
+            WHERE follow.follower_id = ? AND user.visibility != 'private'
+            ORDER BY follow.followed_at DESC, user.id
+            LIMIT ? OFFSET ?;
+            ",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// every user a user follows, private profiles included, for their own data export
+    pub async fn all_following(&self, user_id: u32) -> Result<Vec<Follow>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT user.id AS user_id, user.username, follow.followed_at
+            FROM follow
+                INNER JOIN user ON follow.followed_id = user.id
+            WHERE follow.follower_id = ?
+            ORDER BY follow.followed_at DESC, user.id;
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// most recent first
+    pub async fn feed(
+        &self,
+        user_id: u32,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<FeedEntry>, DatabaseError> {
+        let sql = format!(
+            "SELECT kind, user_id, username, time, goal_id, achievement_id, service_id, field
+            FROM ({FEED})
+            ORDER BY datetime(time) DESC, user_id, kind, goal_id, field
+            LIMIT ? OFFSET ?;"
+        );
+        Ok(sqlx::query_as(&sql)
+            .bind(user_id)
+            .bind(user_id)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    pub async fn count_feed(&self, user_id: u32) -> Result<u32, DatabaseError> {
+        let sql = format!("SELECT COUNT(*) FROM ({FEED});");
+        let (count,) = sqlx::query_as(&sql)
+            .bind(user_id)
+            .bind(user_id)
+            .fetch_one(self.db)
+            .await?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::Utc;
+use sqlx::{Sqlite, SqlitePool, query::QueryAs, sqlite::SqliteArguments};
+
+use crate::{
+    error::DatabaseError,
+    models::impersonation::{Impersonation, ImpersonationCreate, ImpersonationFilter},
+};
+
+/// conditions of an `ImpersonationFilter`, bound with `bind_filter`
+static FILTER: &str = "(? IS NULL OR admin_id = ?) AND (? IS NULL OR user_id = ?)";
+
+pub struct ImpersonationRepo<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> ImpersonationRepo<'a> {
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    pub async fn create(
+        &self,
+        impersonation: ImpersonationCreate,
+    ) -> Result<Impersonation, DatabaseError> {
+        Ok(sqlx::query_as(
+            "
+            INSERT INTO impersonation
+                (admin_id, admin_username, user_id, username, allow_writes, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id, admin_id, admin_username, user_id, username, allow_writes,
+                started_at, ended_at;
+            ",
+        )
+        .bind(impersonation.admin_id)
+        .bind(impersonation.admin_username)
+        .bind(impersonation.user_id)
+        .bind(impersonation.username)
+        .bind(impersonation.allow_writes)
+        .bind(impersonation.expires_at)
+        .fetch_one(self.db)
+        .await?)
+    }
+
+    /// does nothing if the impersonation already ended
+    pub async fn end(&self, id: u32) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE impersonation SET ended_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND ended_at IS NULL;",
+        )
+        .bind(id)
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// sessions don't survive a restart, so neither do impersonations
+    pub async fn end_all(&self) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE impersonation SET ended_at = CURRENT_TIMESTAMP WHERE ended_at IS NULL;",
+        )
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// end the impersonations of sessions that expired without stopping them
+    pub async fn end_expired(&self) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE impersonation SET ended_at = expires_at
+            WHERE ended_at IS NULL AND expires_at <= ?;",
+        )
+        .bind(Utc::now())
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// most recent first
+    pub async fn list(
+        &self,
+        filter: &ImpersonationFilter,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Impersonation>, DatabaseError> {
+        let sql = format!(
+            "SELECT id, admin_id, admin_username, user_id, username, allow_writes,
+                started_at, ended_at
+            FROM impersonation
+            WHERE {FILTER}
+            ORDER BY started_at DESC, id DESC
+            LIMIT ? OFFSET ?;"
+        );
+        Ok(bind_filter(sqlx::query_as(&sql), filter)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    pub async fn count(&self, filter: &ImpersonationFilter) -> Result<u32, DatabaseError> {
+        let sql = format!("SELECT COUNT(*) FROM impersonation WHERE {FILTER};");
+        let (count,) = bind_filter(sqlx::query_as(&sql), filter)
+            .fetch_one(self.db)
+            .await?;
+        Ok(count)
+    }
+}
+
+fn bind_filter<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    filter: &ImpersonationFilter,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    query
+        .bind(filter.admin_id)
+        .bind(filter.admin_id)
+        .bind(filter.user_id)
+        .bind(filter.user_id)
+}
@@ -0,0 +1,344 @@
+use sqlx::{Sqlite, SqlitePool, query::QueryAs, sqlite::SqliteArguments};
+
+use crate::{
+    error::DatabaseError,
+    models::kudos::{Kudos, KudosCreate, KudosFilter, KudosScope},
+    placeholders,
+};
+
+/// kudos someone who can see the recipient sees, bound with a `KudosScope`
+///
+/// removed and withdrawn kudos and kudos from private profiles are left out
+static VISIBLE: &str = "
+    kudos.removed_at IS NULL AND kudos.withdrawn_at IS NULL AND giver.visibility != 'private'
+    AND (? OR kudos.hidden_at IS NULL)
+    AND (? OR giver.visibility = 'public')";
+
+/// kudos given or received by a user, and whether they are removed
+static FILTER: &str = "(? IS NULL OR kudos.giver_id = ? OR kudos.recipient_id = ?)
+    AND (? IS NULL OR (kudos.removed_at IS NOT NULL) = ?)";
+
+pub struct KudosRepo<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> KudosRepo<'a> {
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    pub async fn by_id(&self, id: u32) -> Result<Kudos, DatabaseError> {
+        sqlx::query_as(
+            "SELECT kudos.id, kudos.giver_id, giver.username AS giver_username,
+                kudos.recipient_id, kudos.message, kudos.tag_id, tag.name AS tag_name,
+                kudos.skill, kudos.created_at, kudos.hidden_at, kudos.removed_at,
+                kudos.withdrawn_at
+            FROM kudos
+                INNER JOIN user AS giver ON kudos.giver_id = giver.id
+                LEFT JOIN tag ON kudos.tag_id = tag.id
+            WHERE kudos.id = ?;
+            ",
+        )
+        .bind(id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// a giver can give a recipient kudos only once, removed and withdrawn kudos included
+    /// kudos the giver withdrew are given again, kudos removed by an admin can't be
+    pub async fn create(&self, kudos: KudosCreate) -> Result<Kudos, DatabaseError> {
+        let id: Option<(u32,)> = sqlx::query_as(
+            "INSERT INTO kudos (giver_id, recipient_id, message, tag_id, skill)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT (giver_id, recipient_id) DO UPDATE SET
+                message = excluded.message, tag_id = excluded.tag_id, skill = excluded.skill,
+                created_at = CURRENT_TIMESTAMP, hidden_at = NULL, withdrawn_at = NULL
+            WHERE kudos.withdrawn_at IS NOT NULL AND kudos.removed_at IS NULL
+            RETURNING id;
+            ",
+        )
+        .bind(kudos.giver_id)
+        .bind(kudos.recipient_id)
+        .bind(kudos.message)
+        .bind(kudos.tag_id)
+        .bind(kudos.skill)
+        .fetch_optional(self.db)
+        .await?;
+
+        let (id,) = id.ok_or_else(already_given)?;
+        self.by_id(id).await
+    }
+
+    /// fails with a conflict if the giver has kudos for the recipient they can't give again
+    pub async fn check_can_give(
+        &self,
+        giver_id: u32,
+        recipient_id: u32,
+    ) -> Result<(), DatabaseError> {
+        let given: bool = sqlx::query_scalar(
+            "SELECT EXISTS (
+                SELECT 1 FROM kudos WHERE giver_id = ? AND recipient_id = ?
+                    AND (withdrawn_at IS NULL OR removed_at IS NOT NULL)
+            );",
+        )
+        .bind(giver_id)
+        .bind(recipient_id)
+        .fetch_one(self.db)
+        .await?;
+
+        if given {
+            return Err(already_given());
+        }
+        Ok(())
+    }
+
+    /// the giver takes back their kudos, kudos removed by an admin stay
+    ///
+    /// the giver can give the recipient kudos again afterwards
+    pub async fn withdraw(&self, giver_id: u32, recipient_id: u32) -> Result<(), DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE kudos SET withdrawn_at = CURRENT_TIMESTAMP
+            WHERE giver_id = ? AND recipient_id = ?
+                AND removed_at IS NULL AND withdrawn_at IS NULL;",
+        )
+        .bind(giver_id)
+        .bind(recipient_id)
+        .execute(self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// only the recipient can hide or show kudos they got
+    pub async fn set_hidden(
+        &self,
+        id: u32,
+        recipient_id: u32,
+        hidden: bool,
+    ) -> Result<Kudos, DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE kudos
+            SET hidden_at = CASE WHEN ? THEN COALESCE(hidden_at, CURRENT_TIMESTAMP) END
+            WHERE id = ? AND recipient_id = ? AND removed_at IS NULL AND withdrawn_at IS NULL;",
+        )
+        .bind(hidden)
+        .bind(id)
+        .bind(recipient_id)
+        .execute(self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        self.by_id(id).await
+    }
+
+    /// the kudos is kept so the giver can't give it again
+    pub async fn remove(&self, id: u32, admin_id: u32) -> Result<Kudos, DatabaseError> {
+        let result = sqlx::query(
+            "UPDATE kudos SET removed_at = CURRENT_TIMESTAMP, removed_by = ?
+            WHERE id = ? AND removed_at IS NULL;",
+        )
+        .bind(admin_id)
+        .bind(id)
+        .execute(self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        self.by_id(id).await
+    }
+
+    /// visible kudos a user got, most recent first
+    pub async fn for_recipient(
+        &self,
+        recipient_id: u32,
+        scope: &KudosScope,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Kudos>, DatabaseError> {
+        let sql = format!(
+            "SELECT kudos.id, kudos.giver_id, giver.username AS giver_username,
+                kudos.recipient_id, kudos.message, kudos.tag_id, tag.name AS tag_name,
+                kudos.skill, kudos.created_at, kudos.hidden_at, kudos.removed_at,
+                kudos.withdrawn_at
+            FROM kudos
+                INNER JOIN user AS giver ON kudos.giver_id = giver.id
+                LEFT JOIN tag ON kudos.tag_id = tag.id
+            WHERE kudos.recipient_id = ? AND {VISIBLE}
+            ORDER BY kudos.created_at DESC, kudos.id DESC
+            LIMIT ? OFFSET ?;
+            "
+        );
+        Ok(bind_scope(sqlx::query_as(&sql).bind(recipient_id), scope)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    pub async fn count_for_recipient(
+        &self,
+        recipient_id: u32,
+        scope: &KudosScope,
+    ) -> Result<u32, DatabaseError> {
+        let sql = format!(
+            "SELECT COUNT(*)
+            FROM kudos
+                INNER JOIN user AS giver ON kudos.giver_id = giver.id
+            WHERE kudos.recipient_id = ? AND {VISIBLE};"
+        );
+        let (count,) = bind_scope(sqlx::query_as(&sql).bind(recipient_id), scope)
+            .fetch_one(self.db)
+            .await?;
+        Ok(count)
+    }
+
+    /// the most recent visible kudos of each of the recipients
+    pub async fn recent_for_recipients(
+        &self,
+        recipient_ids: &[u32],
+        scope: &KudosScope,
+        per_recipient: u32,
+    ) -> Result<Vec<Kudos>, DatabaseError> {
+        if recipient_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT id, giver_id, giver_username, recipient_id, message, tag_id, tag_name,
+                skill, created_at, hidden_at, removed_at, withdrawn_at
+            FROM (
+                SELECT kudos.id, kudos.giver_id, giver.username AS giver_username,
+                    kudos.recipient_id, kudos.message, kudos.tag_id, tag.name AS tag_name,
+                    kudos.skill, kudos.created_at, kudos.hidden_at, kudos.removed_at,
+                    kudos.withdrawn_at,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY kudos.recipient_id
+                        ORDER BY kudos.created_at DESC, kudos.id DESC
+                    ) AS position
+                FROM kudos
+                    INNER JOIN user AS giver ON kudos.giver_id = giver.id
+                    LEFT JOIN tag ON kudos.tag_id = tag.id
+                WHERE kudos.recipient_id IN ({}) AND {VISIBLE}
+            )
+            WHERE position <= ?
+            ORDER BY created_at DESC, id DESC;
+            ",
+            placeholders(recipient_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for recipient_id in recipient_ids {
+            query = query.bind(recipient_id);
+        }
+        Ok(bind_scope(query, scope)
+            .bind(per_recipient)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    /// amount of visible kudos of each of the recipients, recipients without any are left out
+    pub async fn count_for_recipients(
+        &self,
+        recipient_ids: &[u32],
+        scope: &KudosScope,
+    ) -> Result<Vec<(u32, u32)>, DatabaseError> {
+        if recipient_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT kudos.recipient_id, COUNT(*)
+            FROM kudos
+                INNER JOIN user AS giver ON kudos.giver_id = giver.id
+            WHERE kudos.recipient_id IN ({}) AND {VISIBLE}
+            GROUP BY kudos.recipient_id;",
+            placeholders(recipient_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for recipient_id in recipient_ids {
+            query = query.bind(recipient_id);
+        }
+        Ok(bind_scope(query, scope).fetch_all(self.db).await?)
+    }
+
+    /// every kudos a user gave, removed ones included, for their own data export
+    pub async fn given_by(&self, giver_id: u32) -> Result<Vec<Kudos>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT kudos.id, kudos.giver_id, giver.username AS giver_username,
+                kudos.recipient_id, kudos.message, kudos.tag_id, tag.name AS tag_name,
+                kudos.skill, kudos.created_at, kudos.hidden_at, kudos.removed_at,
+                kudos.withdrawn_at
+            FROM kudos
+                INNER JOIN user AS giver ON kudos.giver_id = giver.id
+                LEFT JOIN tag ON kudos.tag_id = tag.id
+            WHERE kudos.giver_id = ?
+            ORDER BY kudos.created_at DESC, kudos.id DESC;
+            ",
+        )
+        .bind(giver_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// all kudos, hidden and removed ones included, most recent first
+    pub async fn list(
+        &self,
+        filter: &KudosFilter,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<Kudos>, DatabaseError> {
+        let sql = format!(
+            "SELECT kudos.id, kudos.giver_id, giver.username AS giver_username,
+                kudos.recipient_id, kudos.message, kudos.tag_id, tag.name AS tag_name,
+                kudos.skill, kudos.created_at, kudos.hidden_at, kudos.removed_at,
+                kudos.withdrawn_at
+            FROM kudos
+                INNER JOIN user AS giver ON kudos.giver_id = giver.id
+                LEFT JOIN tag ON kudos.tag_id = tag.id
+            WHERE {FILTER}
+            ORDER BY kudos.created_at DESC, kudos.id DESC
+            LIMIT ? OFFSET ?;
+            "
+        );
+        Ok(bind_filter(sqlx::query_as(&sql), filter)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    pub async fn count(&self, filter: &KudosFilter) -> Result<u32, DatabaseError> {
+        let sql = format!("SELECT COUNT(*) FROM kudos WHERE {FILTER};");
+        let (count,) = bind_filter(sqlx::query_as(&sql), filter)
+            .fetch_one(self.db)
+            .await?;
+        Ok(count)
+    }
+}
+
+fn bind_filter<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    filter: &KudosFilter,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    query
+        .bind(filter.user_id)
+        .bind(filter.user_id)
+        .bind(filter.user_id)
+        .bind(filter.removed)
+        .bind(filter.removed)
+}
+
+fn bind_scope<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    scope: &KudosScope,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    query.bind(scope.hidden).bind(scope.members_only_givers)
+}
+
+fn already_given() -> DatabaseError {
+    DatabaseError::Conflict("You already gave this user kudos".into())
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{
+    error::DatabaseError,
+    models::profile_change::{Actor, ProfileChange},
+};
+
+/// current value of every field of a profile that is kept in the history
+///
+/// fields that are not set are left out
+static SNAPSHOT: &str = "
+    SELECT field, value FROM (
+        SELECT 'about' AS field, about AS value FROM user WHERE id = ?1
+        UNION ALL SELECT 'visibility', visibility FROM user WHERE id = ?1
+        UNION ALL SELECT 'about_visibility', about_visibility FROM user WHERE id = ?1
+        UNION ALL SELECT 'tags_visibility', tags_visibility FROM user WHERE id = ?1
+        UNION ALL SELECT 'achievements_visibility', achievements_visibility FROM user WHERE id = ?1
+        UNION ALL SELECT 'image_visibility', image_visibility FROM user WHERE id = ?1
+        UNION ALL SELECT 'links',
+            CASE WHEN COUNT(*) > 0 THEN json_group_array(json_object('kind', kind, 'value', value)) END
+            FROM (SELECT kind, value FROM user_link WHERE user_id = ?1 ORDER BY position)
+        UNION ALL SELECT 'fields.' || profile_field.name, user_field.value
+            FROM user_field INNER JOIN profile_field ON user_field.field_id = profile_field.id
+            WHERE user_field.user_id = ?1
+        UNION ALL SELECT 'tags', CASE WHEN COUNT(*) > 0 THEN json_group_array(name) END
+            FROM (
+                SELECT DISTINCT tag.name
+                FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+                WHERE current_user_tag.user_id = ?1
+                ORDER BY tag.name
+            )
+    )
+    WHERE value IS NOT NULL;
+";
+
+/// values of a profile by field, taken before and after a change
+pub(crate) type Snapshot = BTreeMap<String, String>;
+
+pub(crate) async fn snapshot(
+    conn: &mut SqliteConnection,
+    user_id: u32,
+) -> Result<Snapshot, DatabaseError> {
+    let values: Vec<(String, String)> = sqlx::query_as(SNAPSHOT)
+        .bind(user_id)
+        .fetch_all(conn)
+        .await?;
+    Ok(values.into_iter().collect())
+}
+
+/// add every field that differs from `before` to the history
+pub(crate) async fn record_changes(
+    conn: &mut SqliteConnection,
+    user_id: u32,
+    actor: Actor,
+    before: Snapshot,
+) -> Result<(), DatabaseError> {
+    let after = snapshot(conn, user_id).await?;
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    for field in fields {
+        let (old_value, new_value) = (before.get(field), after.get(field));
+        if old_value != new_value {
+            record_change(conn, user_id, actor, field, old_value, new_value).await?;
+        }
+    }
+    Ok(())
+}
+
+/// snapshots of every user a change touches, for changes to things users share like tags
+pub(crate) async fn snapshots(
+    conn: &mut SqliteConnection,
+    user_ids: Vec<u32>,
+) -> Result<Vec<(u32, Snapshot)>, DatabaseError> {
+    let mut snapshots = Vec::with_capacity(user_ids.len());
+    for user_id in user_ids {
+        snapshots.push((user_id, snapshot(conn, user_id).await?));
+    }
+    Ok(snapshots)
+}
+
+/// `record_changes` for every user of `snapshots`
+pub(crate) async fn record_all_changes(
+    conn: &mut SqliteConnection,
+    actor: Actor,
+    before: Vec<(u32, Snapshot)>,
+) -> Result<(), DatabaseError> {
+    for (user_id, before) in before {
+        record_changes(conn, user_id, actor, before).await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn record_change(
+    conn: &mut SqliteConnection,
+    user_id: u32,
+    actor: Actor,
+    field: &str,
+    old_value: Option<&String>,
+    new_value: Option<&String>,
+) -> Result<(), DatabaseError> {
+    sqlx::query(
+        "INSERT INTO profile_change
+            (user_id, field, old_value, new_value, actor_user_id, actor_service_id)
+        VALUES (?, ?, ?, ?, ?, ?);",
+    )
+    .bind(user_id)
+    .bind(field)
+    .bind(old_value)
+    .bind(new_value)
+    .bind(actor.user_id())
+    .bind(actor.service_id())
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub struct ProfileChangeRepo<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> ProfileChangeRepo<'a> {
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// most recent first
+    pub async fn for_user(
+        &self,
+        user_id: u32,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ProfileChange>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT profile_change.id, user_id, field, old_value, new_value,
+                actor_user_id, user.username AS actor_username, actor_service_id, changed_at
+            FROM profile_change
+                LEFT JOIN user ON profile_change.actor_user_id = user.id
+            WHERE user_id = ?
+            ORDER BY changed_at DESC, profile_change.id DESC
+            LIMIT ? OFFSET ?;
+            ",
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn count_for_user(&self, user_id: u32) -> Result<u32, DatabaseError> {
+        Ok(
+            sqlx::query_scalar("SELECT COUNT(*) FROM profile_change WHERE user_id = ?;")
+                .bind(user_id)
+                .fetch_one(self.db)
+                .await?,
+        )
+    }
+}
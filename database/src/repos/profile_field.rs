@@ -0,0 +1,146 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    error::DatabaseError,
+    items,
+    models::{
+        profile_change::Actor,
+        profile_field::{ProfileField, ProfileFieldCreate, ProfileFieldPatch, UserFieldValue},
+        user::OfUser,
+    },
+    placeholders,
+    repos::profile_change::{record_all_changes, snapshots},
+};
+
+pub struct ProfileFieldRepo<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> ProfileFieldRepo<'a> {
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    pub async fn all(&self) -> Result<Vec<ProfileField>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT id, name, label, kind, options, max_length, visibility, position
+            FROM profile_field
+            ORDER BY position, id;
+            ",
+        )
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn by_id(&self, field_id: u32) -> Result<ProfileField, DatabaseError> {
+        sqlx::query_as(
+            "SELECT id, name, label, kind, options, max_length, visibility, position
+            FROM profile_field
+            WHERE id = ?;
+            ",
+        )
+        .bind(field_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn create(&self, field: ProfileFieldCreate) -> Result<ProfileField, DatabaseError> {
+        Ok(sqlx::query_as(
+            "INSERT INTO profile_field
+                (name, label, kind, options, max_length, visibility, position)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            RETURNING id, name, label, kind, options, max_length, visibility, position;
+            ",
+        )
+        .bind(field.name)
+        .bind(field.label)
+        .bind(field.kind)
+        .bind(field.options)
+        .bind(field.max_length)
+        .bind(field.visibility)
+        .bind(field.position)
+        .fetch_one(self.db)
+        .await?)
+    }
+
+    pub async fn patch(
+        &self,
+        field_id: u32,
+        field: ProfileFieldPatch,
+    ) -> Result<ProfileField, DatabaseError> {
+        sqlx::query_as(
+            "UPDATE profile_field SET
+                label = COALESCE(?, label),
+                options = COALESCE(?, options),
+                max_length = CASE WHEN ? THEN ? ELSE max_length END,
+                visibility = COALESCE(?, visibility),
+                position = COALESCE(?, position)
+            WHERE id = ?
+            RETURNING id, name, label, kind, options, max_length, visibility, position;
+            ",
+        )
+        .bind(field.label)
+        .bind(field.options)
+        .bind(field.max_length.is_some())
+        .bind(field.max_length.flatten())
+        .bind(field.visibility)
+        .bind(field.position)
+        .bind(field_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// delete a field together with the values of all users
+    pub async fn delete(&self, field_id: u32, actor: Actor) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let user_ids = sqlx::query_scalar("SELECT user_id FROM user_field WHERE field_id = ?;")
+            .bind(field_id)
+            .fetch_all(&mut *tx)
+            .await?;
+        let before = snapshots(&mut tx, user_ids).await?;
+
+        let result = sqlx::query("DELETE FROM profile_field WHERE id = ?;")
+            .bind(field_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        record_all_changes(&mut tx, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// values a user filled in, in the order of the fields
+    pub async fn for_user(&self, user_id: u32) -> Result<Vec<UserFieldValue>, DatabaseError> {
+        Ok(items(self.for_users(&[user_id]).await?))
+    }
+
+    pub async fn for_users(
+        &self,
+        user_ids: &[u32],
+    ) -> Result<Vec<OfUser<UserFieldValue>>, DatabaseError> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT user_field.user_id, profile_field.id AS field_id, profile_field.name,
+                profile_field.label, profile_field.kind, profile_field.visibility, user_field.value
+            FROM user_field
+                INNER JOIN profile_field
+                    ON user_field.field_id = profile_field.id
+            WHERE user_field.user_id IN ({})
+            ORDER BY profile_field.position, profile_field.id;
+            ",
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for user_id in user_ids {
+            query = query.bind(user_id);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+}
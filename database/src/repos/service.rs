@@ -3,7 +3,7 @@ use sqlx::SqlitePool;
 
 use crate::{
     error::DatabaseError,
-    models::service::{Service, ServiceCreate, ServicePatch},
+    models::service::{Service, ServiceCreate, ServicePatch, ServiceRateLimitPatch},
 };
 
 pub struct ServiceRepo<'a> {
@@ -16,24 +16,47 @@ impl<'a> ServiceRepo<'a> {
     }
 
     pub async fn all(&self) -> Result<Vec<Service>, DatabaseError> {
-        Ok(sqlx::query_as("SELECT id, name, api_key FROM service;")
-            .fetch_all(self.db)
-            .await?)
+        Ok(sqlx::query_as(
+            "SELECT id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
+            FROM service;",
+        )
+        .fetch_all(self.db)
+        .await?)
     }
 
-    pub async fn create(&self, service: ServiceCreate) -> Result<Service, DatabaseError> {
-        let mut api_key = [0u8; 32];
-        rand::rng().fill_bytes(&mut api_key);
-        let api_key = base_62::encode(&api_key);
+    pub async fn by_id(&self, service_id: u32) -> Result<Service, DatabaseError> {
+        sqlx::query_as(
+            "SELECT id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
+            FROM service WHERE id = ?;",
+        )
+        .bind(service_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// find the service with this api key or test api key
+    pub async fn by_api_key(&self, api_key: &str) -> Result<Service, DatabaseError> {
+        sqlx::query_as(
+            "SELECT id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
+            FROM service WHERE api_key = ? OR test_api_key = ?;",
+        )
+        .bind(api_key)
+        .bind(api_key)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
 
+    pub async fn create(&self, service: ServiceCreate) -> Result<Service, DatabaseError> {
         sqlx::query_as(
             "
        INSERT INTO service (name, api_key) VALUES (?, ?)
-       RETURNING id, name, api_key;
+       RETURNING id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute;
        ",
         )
         .bind(service.name)
-        .bind(api_key)
+        .bind(generate_api_key())
         .fetch_optional(self.db)
         .await?
         .ok_or(DatabaseError::NotFound)
@@ -46,11 +69,14 @@ impl<'a> ServiceRepo<'a> {
     ) -> Result<Service, DatabaseError> {
         sqlx::query_as(
             "
-        UPDATE service SET name = ? WHERE id = ?
-        RETURNING id, name, api_key
+        UPDATE service SET name = ?, owner_id = CASE WHEN ? THEN ? ELSE owner_id END
+        WHERE id = ?
+        RETURNING id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
         ",
         )
         .bind(patch_service.name)
+        .bind(patch_service.owner_id.is_some())
+        .bind(patch_service.owner_id.flatten())
         .bind(service_id)
         .fetch_optional(self.db)
         .await?
@@ -58,20 +84,93 @@ impl<'a> ServiceRepo<'a> {
     }
 
     pub async fn regenerate_api_key(&self, service_id: u32) -> Result<Service, DatabaseError> {
-        let mut api_key = [0u8; 32];
-        rand::rng().fill_bytes(&mut api_key);
-        let api_key = base_62::encode(&api_key);
-
         sqlx::query_as(
             "
         UPDATE service SET api_key = ? WHERE id = ?
-        RETURNING id, name, api_key
+        RETURNING id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
         ",
         )
-        .bind(api_key)
+        .bind(generate_api_key())
+        .bind(service_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// create a new test api key, replacing the old one if there was one
+    pub async fn regenerate_test_api_key(&self, service_id: u32) -> Result<Service, DatabaseError> {
+        sqlx::query_as(
+            "
+        UPDATE service SET test_api_key = ? WHERE id = ?
+        RETURNING id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
+        ",
+        )
+        .bind(generate_api_key())
         .bind(service_id)
         .fetch_optional(self.db)
         .await?
         .ok_or(DatabaseError::NotFound)
     }
+
+    pub async fn remove_test_api_key(&self, service_id: u32) -> Result<Service, DatabaseError> {
+        sqlx::query_as(
+            "
+        UPDATE service SET test_api_key = NULL WHERE id = ?
+        RETURNING id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
+        ",
+        )
+        .bind(service_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn set_rate_limit(
+        &self,
+        service_id: u32,
+        rate_limit: ServiceRateLimitPatch,
+    ) -> Result<Service, DatabaseError> {
+        sqlx::query_as(
+            "
+        UPDATE service SET rate_limit_burst = ?, rate_limit_per_minute = ? WHERE id = ?
+        RETURNING id, name, api_key, test_api_key, owner_id, rate_limit_burst, rate_limit_per_minute
+        ",
+        )
+        .bind(rate_limit.burst)
+        .bind(rate_limit.per_minute)
+        .bind(service_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// delete all unlocks and data this service wrote with its test api key
+    pub async fn wipe_sandbox(&self, service_id: u32) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+
+        sqlx::query(
+            "DELETE FROM sandbox_unlock WHERE goal_id IN (
+                SELECT goal.id FROM goal
+                INNER JOIN achievement ON goal.achievement_id = achievement.id
+                WHERE achievement.service_id = ?
+            );",
+        )
+        .bind(service_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM sandbox_service_data WHERE service_id = ?;")
+            .bind(service_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+fn generate_api_key() -> String {
+    let mut api_key = [0u8; 32];
+    rand::rng().fill_bytes(&mut api_key);
+    base_62::encode(&api_key)
 }
@@ -0,0 +1,212 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    error::DatabaseError,
+    models::{
+        service::Namespace,
+        service_data::{ServiceData, ServiceDataPut},
+    },
+};
+
+pub struct ServiceDataRepo<'a> {
+    db: &'a SqlitePool,
+    table: &'static str,
+}
+
+impl<'a> ServiceDataRepo<'a> {
+    pub fn new(db: &'a SqlitePool, namespace: Namespace) -> Self {
+        Self {
+            db,
+            table: namespace.service_data_table(),
+        }
+    }
+
+    /// all data every service stores about a user
+    pub async fn for_user(&self, user_id: u32) -> Result<Vec<ServiceData>, DatabaseError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT service_id, user_id, key, value, version, updated_at
+            FROM {}
+            WHERE user_id = ?
+            ORDER BY service_id, key;
+            ",
+            self.table
+        ))
+        .bind(user_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// all data a service stores about every user
+    pub async fn for_service(&self, service_id: u32) -> Result<Vec<ServiceData>, DatabaseError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT service_id, user_id, key, value, version, updated_at
+            FROM {}
+            WHERE service_id = ?
+            ORDER BY user_id, key;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn for_service_user(
+        &self,
+        service_id: u32,
+        user_id: u32,
+    ) -> Result<Vec<ServiceData>, DatabaseError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT service_id, user_id, key, value, version, updated_at
+            FROM {}
+            WHERE service_id = ? AND user_id = ?
+            ORDER BY key;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .bind(user_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn by_key(
+        &self,
+        service_id: u32,
+        user_id: u32,
+        key: &str,
+    ) -> Result<ServiceData, DatabaseError> {
+        sqlx::query_as(&format!(
+            "SELECT service_id, user_id, key, value, version, updated_at
+            FROM {}
+            WHERE service_id = ? AND user_id = ? AND key = ?;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .bind(user_id)
+        .bind(key)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn count_for_service_user(
+        &self,
+        service_id: u32,
+        user_id: u32,
+    ) -> Result<u32, DatabaseError> {
+        Ok(sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {} WHERE service_id = ? AND user_id = ?;",
+            self.table
+        ))
+        .bind(service_id)
+        .bind(user_id)
+        .fetch_one(self.db)
+        .await?)
+    }
+
+    /// create or update a value
+    ///
+    /// fails with a conflict if the version doesn't match the stored version, or if a new key
+    /// would go over the maximum amount of keys. The maximum is checked in the insert itself,
+    /// so concurrent puts can't go over it.
+    pub async fn put(
+        &self,
+        service_id: u32,
+        user_id: u32,
+        data: ServiceDataPut,
+    ) -> Result<ServiceData, DatabaseError> {
+        let row = match data.version {
+            None => {
+                let row = sqlx::query_as(&format!(
+                    "
+                INSERT INTO {table} (service_id, user_id, key, value)
+                SELECT ?, ?, ?, ?
+                WHERE (SELECT COUNT(*) FROM {table} WHERE service_id = ? AND user_id = ?) < ?
+                ON CONFLICT DO NOTHING
+                RETURNING service_id, user_id, key, value, version, updated_at;
+                ",
+                    table = self.table
+                ))
+                .bind(service_id)
+                .bind(user_id)
+                .bind(&data.key)
+                .bind(data.value)
+                .bind(service_id)
+                .bind(user_id)
+                .bind(data.max_keys)
+                .fetch_optional(self.db)
+                .await?;
+                if row.is_none()
+                    && let Err(DatabaseError::NotFound) =
+                        self.by_key(service_id, user_id, &data.key).await
+                {
+                    return Err(DatabaseError::Conflict(format!(
+                        "A service can store at most {} keys per user",
+                        data.max_keys
+                    )));
+                }
+                row
+            }
+            Some(version) => {
+                sqlx::query_as(&format!(
+                    "
+                UPDATE {}
+                SET value = ?, version = version + 1, updated_at = CURRENT_TIMESTAMP
+                WHERE service_id = ? AND user_id = ? AND key = ? AND version = ?
+                RETURNING service_id, user_id, key, value, version, updated_at;
+                ",
+                    self.table
+                ))
+                .bind(data.value)
+                .bind(service_id)
+                .bind(user_id)
+                .bind(data.key)
+                .bind(version)
+                .fetch_optional(self.db)
+                .await?
+            }
+        };
+
+        row.ok_or(DatabaseError::Conflict(
+            "stored version does not match".into(),
+        ))
+    }
+
+    /// delete a value, if a version is given it has to match the stored version
+    pub async fn delete(
+        &self,
+        service_id: u32,
+        user_id: u32,
+        key: &str,
+        version: Option<u32>,
+    ) -> Result<(), DatabaseError> {
+        let stored = self.by_key(service_id, user_id, key).await?;
+        if version.is_some_and(|version| version != stored.version) {
+            return Err(DatabaseError::Conflict(
+                "stored version does not match".into(),
+            ));
+        }
+
+        let result = sqlx::query(&format!(
+            "DELETE FROM {}
+            WHERE service_id = ? AND user_id = ? AND key = ? AND version = ?;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .bind(user_id)
+        .bind(key)
+        .bind(stored.version)
+        .execute(self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::Conflict(
+                "stored version does not match".into(),
+            ));
+        }
+        Ok(())
+    }
+}
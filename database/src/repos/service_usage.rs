@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use crate::{
+    error::DatabaseError,
+    models::{
+        service::Namespace,
+        service_usage::{EndpointUsage, HourlyUsage, UsageCount},
+    },
+};
+
+pub struct ServiceUsageRepo<'a> {
+    db: &'a SqlitePool,
+}
+
+impl<'a> ServiceUsageRepo<'a> {
+    pub fn new(db: &'a SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// add counted requests to their hourly buckets, in one transaction
+    pub async fn record(&self, counts: &[UsageCount]) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        for usage in counts {
+            sqlx::query(
+                "INSERT INTO service_usage
+                    (service_id, namespace, hour, endpoint, status, count, last_called_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT DO UPDATE SET
+                    count = count + excluded.count,
+                    last_called_at = MAX(last_called_at, excluded.last_called_at);
+                ",
+            )
+            .bind(usage.service_id)
+            .bind(usage.namespace)
+            .bind(usage.hour.format("%Y-%m-%d %H:00:00").to_string())
+            .bind(&usage.endpoint)
+            .bind(usage.status)
+            .bind(usage.count)
+            .bind(usage.last_called_at.format("%Y-%m-%d %H:%M:%S").to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn by_endpoint(
+        &self,
+        service_id: u32,
+        namespace: Namespace,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<EndpointUsage>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT endpoint, status, SUM(count) AS count, MAX(last_called_at) AS last_called_at
+            FROM service_usage
+            WHERE service_id = ? AND namespace = ? AND hour >= datetime(?) AND hour < datetime(?)
+            GROUP BY endpoint, status
+            ORDER BY endpoint, status;
+            ",
+        )
+        .bind(service_id)
+        .bind(namespace)
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn hourly(
+        &self,
+        service_id: u32,
+        namespace: Namespace,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<HourlyUsage>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT
+                hour,
+                SUM(count) AS requests,
+                SUM(CASE WHEN status >= 400 THEN count ELSE 0 END) AS errors
+            FROM service_usage
+            WHERE service_id = ? AND namespace = ? AND hour >= datetime(?) AND hour < datetime(?)
+            GROUP BY hour
+            ORDER BY hour;
+            ",
+        )
+        .bind(service_id)
+        .bind(namespace)
+        .bind(from)
+        .bind(to)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// last time the service made any request
+    pub async fn last_called_at(
+        &self,
+        service_id: u32,
+        namespace: Namespace,
+    ) -> Result<Option<DateTime<Utc>>, DatabaseError> {
+        Ok(sqlx::query_scalar(
+            "SELECT MAX(last_called_at) FROM service_usage
+            WHERE service_id = ? AND namespace = ?;",
+        )
+        .bind(service_id)
+        .bind(namespace)
+        .fetch_one(self.db)
+        .await?)
+    }
+}
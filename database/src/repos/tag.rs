@@ -1,6 +1,19 @@
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
-use crate::{error::DatabaseError, models::tag::Tag};
+use crate::{
+    error::DatabaseError,
+    items,
+    models::{
+        profile_change::Actor,
+        tag::{
+            Tag, TagAssignment, TagAssignmentCreate, TagAssignmentPatch, TagCategory,
+            TagCategoryCreate, TagCategoryPatch, TagCreate, TagOverview, TagPatch, UserTag,
+        },
+        user::{OfUser, User},
+    },
+    placeholders,
+    repos::profile_change::{record_all_changes, record_changes, snapshot, snapshots},
+};
 
 pub struct TagRepo<'a> {
     db: &'a SqlitePool,
@@ -11,18 +24,790 @@ impl<'a> TagRepo<'a> {
         Self { db }
     }
 
-    pub async fn for_user(&self, user_id: u32) -> Result<Vec<Tag>, DatabaseError> {
-        Ok(sqlx::query_as(
-            "SELECT tag.id, tag.name, tag_category.name AS category, description
+    /// tags the user holds right now
+    pub async fn for_user(&self, user_id: u32) -> Result<Vec<UserTag>, DatabaseError> {
+        Ok(items(self.for_users(&[user_id]).await?))
+    }
+
+    /// tags each of the users holds right now
+    pub async fn for_users(&self, user_ids: &[u32]) -> Result<Vec<OfUser<UserTag>>, DatabaseError> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT current_user_tag.user_id, tag.id AS tag_id, tag.name,
+                tag_category.name AS category, tag.description,
+                COALESCE(tag.color, tag_category.color) AS color, tag.icon OR tag_category.icon AS icon,
+                current_user_tag.valid_from, current_user_tag.valid_until
+            FROM current_user_tag
+                INNER JOIN tag
+                    ON current_user_tag.tag_id = tag.id
+                INNER JOIN tag_category
+                    ON tag.category = tag_category.id
+            WHERE current_user_tag.user_id IN ({})
+            ORDER BY tag.id;
+            ",
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for user_id in user_ids {
+            query = query.bind(user_id);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+
+    /// tags the user held in the past, most recent first
+    pub async fn former_for_user(&self, user_id: u32) -> Result<Vec<UserTag>, DatabaseError> {
+        Ok(items(self.former_for_users(&[user_id]).await?))
+    }
+
+    /// tags each of the users held in the past, most recent first
+    pub async fn former_for_users(
+        &self,
+        user_ids: &[u32],
+    ) -> Result<Vec<OfUser<UserTag>>, DatabaseError> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT user_tag.user_id, tag.id AS tag_id, tag.name, tag_category.name AS category,
+                tag.description, COALESCE(tag.color, tag_category.color) AS color,
+                tag.icon OR tag_category.icon AS icon, user_tag.valid_from, user_tag.valid_until
             FROM user_tag
                 INNER JOIN tag
-                    ON user_tag.tag_id = tag.id AND user_tag.user_id = ?
+                    ON user_tag.tag_id = tag.id
+                INNER JOIN tag_category
+                    ON tag.category = tag_category.id
+            WHERE user_tag.user_id IN ({})
+                AND datetime(user_tag.valid_until) <= datetime('now')
+            ORDER BY datetime(user_tag.valid_until) DESC;
+            ",
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for user_id in user_ids {
+            query = query.bind(user_id);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+
+    pub async fn all(&self) -> Result<Vec<Tag>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT tag.id, tag.name, tag_category.name AS category, tag.description,
+                COALESCE(tag.color, tag_category.color) AS color, tag.icon OR tag_category.icon AS icon
+            FROM tag
+                INNER JOIN tag_category
+                    ON tag.category = tag_category.id
+            ORDER BY tag.id;
+            ",
+        )
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// all tags with the amount of profiles holding them that show their tags to members
+    pub async fn overview(&self) -> Result<Vec<TagOverview>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT tag.id, tag.name, tag_category.name AS category, tag.description,
+                COALESCE(tag.color, tag_category.color) AS color, tag.icon OR tag_category.icon AS icon,
+                COUNT(DISTINCT user.id) AS members
+            FROM tag
+                INNER JOIN tag_category
+                    ON tag.category = tag_category.id
+                LEFT JOIN current_user_tag
+                    ON current_user_tag.tag_id = tag.id
+                LEFT JOIN user
+                    ON current_user_tag.user_id = user.id AND user.visibility != 'private'
+                        AND COALESCE(user.tags_visibility, '') != 'private'
+            GROUP BY tag.id
+            ORDER BY tag_category.name, tag.name;
+            ",
+        )
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn by_name(&self, name: &str) -> Result<Tag, DatabaseError> {
+        sqlx::query_as(
+            "SELECT tag.id, tag.name, tag_category.name AS category, tag.description,
+                COALESCE(tag.color, tag_category.color) AS color, tag.icon OR tag_category.icon AS icon
+            FROM tag
+                INNER JOIN tag_category
+                    ON tag.category = tag_category.id
+            WHERE tag.name = ?;
+            ",
+        )
+        .bind(name)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// users holding a tag, ordered by username
+    ///
+    /// private profiles and users who keep their tags private are left out
+    pub async fn members(
+        &self,
+        tag_id: u32,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<User>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT DISTINCT user.id, user.username, user.about, user.visibility,
+                user.about_visibility, user.tags_visibility, user.achievements_visibility,
+                user.image_visibility
+            FROM current_user_tag
+                INNER JOIN user
+                    ON current_user_tag.user_id = user.id
+            WHERE current_user_tag.tag_id = ? AND user.visibility != 'private'
+                AND COALESCE(user.tags_visibility, '') != 'private'
+            ORDER BY user.username
+            LIMIT ? OFFSET ?;
+            ",
+        )
+        .bind(tag_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// amount of users holding a tag, counted like `members`
+    pub async fn count_members(&self, tag_id: u32) -> Result<u32, DatabaseError> {
+        Ok(sqlx::query_scalar(
+            "SELECT COUNT(DISTINCT user.id)
+            FROM current_user_tag
+                INNER JOIN user
+                    ON current_user_tag.user_id = user.id
+            WHERE current_user_tag.tag_id = ? AND user.visibility != 'private'
+                AND COALESCE(user.tags_visibility, '') != 'private';
+            ",
+        )
+        .bind(tag_id)
+        .fetch_one(self.db)
+        .await?)
+    }
+
+    pub async fn by_id(&self, tag_id: u32) -> Result<Tag, DatabaseError> {
+        sqlx::query_as(
+            "SELECT tag.id, tag.name, tag_category.name AS category, tag.description,
+                COALESCE(tag.color, tag_category.color) AS color, tag.icon OR tag_category.icon AS icon
+            FROM tag
+                INNER JOIN tag_category
+                    ON tag.category = tag_category.id
+            WHERE tag.id = ?;
+            ",
+        )
+        .bind(tag_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn create(&self, tag: TagCreate) -> Result<Tag, DatabaseError> {
+        self.category_by_id(tag.category_id).await?;
+
+        let tag_id: u32 = sqlx::query_scalar(
+            "INSERT INTO tag (name, description, category, color) VALUES (?, ?, ?, ?)
+            RETURNING id;
+            ",
+        )
+        .bind(tag.name)
+        .bind(tag.description)
+        .bind(tag.category_id)
+        .bind(tag.color)
+        .fetch_one(self.db)
+        .await?;
+
+        self.by_id(tag_id).await
+    }
+
+    pub async fn patch(&self, tag_id: u32, tag: TagPatch) -> Result<Tag, DatabaseError> {
+        if let Some(category_id) = tag.category_id {
+            self.category_by_id(category_id).await?;
+        }
+
+        let result = sqlx::query(
+            "UPDATE tag SET
+                name = COALESCE(?, name),
+                description = COALESCE(?, description),
+                category = COALESCE(?, category),
+                color = CASE WHEN ? THEN ? ELSE color END
+            WHERE id = ?;
+            ",
+        )
+        .bind(tag.name)
+        .bind(tag.description)
+        .bind(tag.category_id)
+        .bind(tag.color.is_some())
+        .bind(tag.color.flatten())
+        .bind(tag_id)
+        .execute(self.db)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        self.by_id(tag_id).await
+    }
+
+    /// delete a tag, removing it from all users
+    pub async fn delete(&self, tag_id: u32, actor: Actor) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let user_ids =
+            sqlx::query_scalar("SELECT DISTINCT user_id FROM current_user_tag WHERE tag_id = ?;")
+                .bind(tag_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let before = snapshots(&mut tx, user_ids).await?;
+
+        let result = sqlx::query("DELETE FROM tag WHERE id = ?;")
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        record_all_changes(&mut tx, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn categories(&self) -> Result<Vec<TagCategory>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT id, name, description, policy, max_per_user, service_id, color, icon
+            FROM tag_category ORDER BY id;",
+        )
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn category_by_id(&self, category_id: u32) -> Result<TagCategory, DatabaseError> {
+        sqlx::query_as(
+            "SELECT id, name, description, policy, max_per_user, service_id, color, icon
+            FROM tag_category WHERE id = ?;",
+        )
+        .bind(category_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn create_category(
+        &self,
+        category: TagCategoryCreate,
+    ) -> Result<TagCategory, DatabaseError> {
+        Ok(sqlx::query_as(
+            "INSERT INTO tag_category (name, description, policy, max_per_user, service_id, color)
+            VALUES (?, ?, ?, ?, ?, ?)
+            RETURNING id, name, description, policy, max_per_user, service_id, color, icon;
+            ",
+        )
+        .bind(category.name)
+        .bind(category.description)
+        .bind(category.policy)
+        .bind(category.max_per_user)
+        .bind(category.service_id)
+        .bind(category.color)
+        .fetch_one(self.db)
+        .await?)
+    }
+
+    pub async fn patch_category(
+        &self,
+        category_id: u32,
+        category: TagCategoryPatch,
+    ) -> Result<TagCategory, DatabaseError> {
+        sqlx::query_as(
+            "UPDATE tag_category SET
+                name = COALESCE(?, name),
+                description = COALESCE(?, description),
+                policy = COALESCE(?, policy),
+                max_per_user = CASE WHEN ? THEN ? ELSE max_per_user END,
+                service_id = CASE WHEN ? THEN ? ELSE service_id END,
+                color = COALESCE(?, color)
+            WHERE id = ?
+            RETURNING id, name, description, policy, max_per_user, service_id, color, icon;
+            ",
+        )
+        .bind(category.name)
+        .bind(category.description)
+        .bind(category.policy)
+        .bind(category.max_per_user.is_some())
+        .bind(category.max_per_user.flatten())
+        .bind(category.service_id.is_some())
+        .bind(category.service_id.flatten())
+        .bind(category.color)
+        .bind(category_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// delete a category, returns the ids of the tags that were deleted with it
+    ///
+    /// if the category still has tags, they are deleted too when `cascade` is set,
+    /// otherwise this fails with a conflict
+    pub async fn delete_category(
+        &self,
+        category_id: u32,
+        cascade: bool,
+        actor: Actor,
+    ) -> Result<Vec<u32>, DatabaseError> {
+        let mut tx = self.db.begin().await?;
+
+        let tag_ids: Vec<u32> = sqlx::query_scalar("SELECT id FROM tag WHERE category = ?;")
+            .bind(category_id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let tag_count = tag_ids.len();
+        if tag_count > 0 && !cascade {
+            return Err(DatabaseError::Conflict(format!(
+                "category still has {tag_count} tags"
+            )));
+        }
+
+        let user_ids = sqlx::query_scalar(
+            "SELECT DISTINCT current_user_tag.user_id
+            FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+            WHERE tag.category = ?;",
+        )
+        .bind(category_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        let before = snapshots(&mut tx, user_ids).await?;
+
+        sqlx::query("DELETE FROM tag WHERE category = ?;")
+            .bind(category_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM tag_category WHERE id = ?;")
+            .bind(category_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        record_all_changes(&mut tx, actor, before).await?;
+
+        tx.commit().await?;
+        Ok(tag_ids)
+    }
+
+    pub async fn set_icon(&self, tag_id: u32, icon: bool) -> Result<(), DatabaseError> {
+        let result = sqlx::query("UPDATE tag SET icon = ? WHERE id = ?;")
+            .bind(icon)
+            .bind(tag_id)
+            .execute(self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+
+    pub async fn set_category_icon(
+        &self,
+        category_id: u32,
+        icon: bool,
+    ) -> Result<(), DatabaseError> {
+        let result = sqlx::query("UPDATE tag_category SET icon = ? WHERE id = ?;")
+            .bind(icon)
+            .bind(category_id)
+            .execute(self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// category a tag belongs to
+    pub async fn category_for_tag(&self, tag_id: u32) -> Result<TagCategory, DatabaseError> {
+        sqlx::query_as(
+            "SELECT tag_category.id, tag_category.name, tag_category.description,
+                tag_category.policy, tag_category.max_per_user, tag_category.service_id,
+                tag_category.color, tag_category.icon
+            FROM tag
                 INNER JOIN tag_category
-                    ON tag.category = tag_category.id;
+                    ON tag.category = tag_category.id
+            WHERE tag.id = ?;
+            ",
+        )
+        .bind(tag_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// give a user a tag, unless they would have more than `max` tags of its category
+    ///
+    /// does nothing if they already have it
+    pub async fn assign_limited(
+        &self,
+        user_id: u32,
+        tag_id: u32,
+        max: u32,
+        actor: Actor,
+    ) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let already_assigned: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM current_user_tag WHERE user_id = ? AND tag_id = ?);",
+        )
+        .bind(user_id)
+        .bind(tag_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if already_assigned {
+            return Ok(());
+        }
+
+        let count: u32 = sqlx::query_scalar(
+            "SELECT COUNT(DISTINCT tag.id)
+            FROM current_user_tag
+                INNER JOIN tag
+                    ON current_user_tag.tag_id = tag.id
+            WHERE current_user_tag.user_id = ?
+                AND tag.category = (SELECT category FROM tag WHERE id = ?);
+            ",
+        )
+        .bind(user_id)
+        .bind(tag_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if count >= max {
+            return Err(DatabaseError::Conflict(format!(
+                "user already has {count} tags of this category"
+            )));
+        }
+
+        // an assignment that only starts in the future is moved to now
+        sqlx::query(
+            "INSERT INTO user_tag (user_id, tag_id, valid_from, assigned_by)
+            VALUES (?, ?, CURRENT_TIMESTAMP, ?)
+            ON CONFLICT (user_id, tag_id) WHERE valid_until IS NULL
+                DO UPDATE SET valid_from = excluded.valid_from;
+            ",
+        )
+        .bind(user_id)
+        .bind(tag_id)
+        .bind(actor.user_id())
+        .execute(&mut *tx)
+        .await?;
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// give a user a tag from now on, does nothing if they already have it
+    pub async fn assign(
+        &self,
+        user_id: u32,
+        tag_id: u32,
+        actor: Actor,
+    ) -> Result<(), DatabaseError> {
+        self.by_id(tag_id).await?;
+
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        // a tag granted by hand is no longer managed by the zauth sync
+        let result = sqlx::query(
+            "UPDATE user_tag SET source = 'manual'
+            WHERE id IN (SELECT id FROM current_user_tag WHERE user_id = ? AND tag_id = ?);
+            ",
+        )
+        .bind(user_id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            sqlx::query(
+                "INSERT INTO user_tag (user_id, tag_id, valid_from, assigned_by)
+                VALUES (?, ?, CURRENT_TIMESTAMP, ?)
+                ON CONFLICT (user_id, tag_id) WHERE valid_until IS NULL
+                    DO UPDATE SET source = 'manual', valid_from = excluded.valid_from;
+                ",
+            )
+            .bind(user_id)
+            .bind(tag_id)
+            .bind(actor.user_id())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// make the tags synced from zauth roles match `tag_names`
+    ///
+    /// tags that were not given by the sync are left alone, unknown names are ignored
+    pub async fn sync_zauth(
+        &self,
+        user_id: u32,
+        tag_names: &[String],
+    ) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let synced: Vec<(u32, String)> = sqlx::query_as(
+            "SELECT tag.id, tag.name
+            FROM current_user_tag
+                INNER JOIN tag
+                    ON current_user_tag.tag_id = tag.id
+            WHERE current_user_tag.user_id = ? AND current_user_tag.source = 'zauth';
+            ",
+        )
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (tag_id, name) in synced {
+            if !tag_names.contains(&name) {
+                sqlx::query(
+                    "UPDATE user_tag SET valid_until = CURRENT_TIMESTAMP
+                    WHERE id IN (
+                        SELECT id FROM current_user_tag
+                        WHERE user_id = ? AND tag_id = ? AND source = 'zauth'
+                    );
+                    ",
+                )
+                .bind(user_id)
+                .bind(tag_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for name in tag_names {
+            sqlx::query(
+                "INSERT INTO user_tag (user_id, tag_id, source, valid_from)
+                SELECT ?, id, 'zauth', CURRENT_TIMESTAMP FROM tag
+                WHERE name = ? AND NOT EXISTS (
+                    SELECT 1 FROM current_user_tag WHERE user_id = ? AND tag_id = tag.id
+                )
+                ON CONFLICT (user_id, tag_id) WHERE valid_until IS NULL DO NOTHING;
+                ",
+            )
+            .bind(user_id)
+            .bind(name)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        record_changes(&mut tx, user_id, Actor::System, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// end the current assignment of a tag, keeping it as history
+    pub async fn unassign(
+        &self,
+        user_id: u32,
+        tag_id: u32,
+        actor: Actor,
+    ) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let result = sqlx::query(
+            "UPDATE user_tag SET valid_until = CURRENT_TIMESTAMP
+            WHERE id IN (SELECT id FROM current_user_tag WHERE user_id = ? AND tag_id = ?);
+            ",
+        )
+        .bind(user_id)
+        .bind(tag_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// a user drops a tag they have right now
+    ///
+    /// an assignment they made themselves is removed without a trace, one made by someone else
+    /// is ended and kept as history. Past assignments are left alone.
+    pub async fn remove(
+        &self,
+        user_id: u32,
+        tag_id: u32,
+        actor: Actor,
+    ) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let result = sqlx::query(
+            "DELETE FROM user_tag
+            WHERE id IN (SELECT id FROM current_user_tag WHERE user_id = ? AND tag_id = ?)
+                AND assigned_by = ?;
+            ",
+        )
+        .bind(user_id)
+        .bind(tag_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            let result = sqlx::query(
+                "UPDATE user_tag SET valid_until = CURRENT_TIMESTAMP
+                WHERE id IN (SELECT id FROM current_user_tag WHERE user_id = ? AND tag_id = ?);
+                ",
+            )
+            .bind(user_id)
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await?;
+
+            if result.rows_affected() == 0 {
+                return Err(DatabaseError::NotFound);
+            }
+        }
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// every assignment of a user, also past and future ones
+    pub async fn assignments(&self, user_id: u32) -> Result<Vec<TagAssignment>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT id, user_id, tag_id, source, valid_from, valid_until
+            FROM user_tag
+            WHERE user_id = ?
+            ORDER BY tag_id, datetime(valid_from);
             ",
         )
         .bind(user_id)
         .fetch_all(self.db)
         .await?)
     }
+
+    /// only assignments that change the current tags end up in the history
+    pub async fn create_assignment(
+        &self,
+        user_id: u32,
+        assignment: TagAssignmentCreate,
+        actor: Actor,
+    ) -> Result<TagAssignment, DatabaseError> {
+        self.by_id(assignment.tag_id).await?;
+
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let created = sqlx::query_as(
+            "INSERT INTO user_tag (user_id, tag_id, valid_from, valid_until, assigned_by)
+            VALUES (?, ?, ?, ?, ?)
+            RETURNING id, user_id, tag_id, source, valid_from, valid_until;
+            ",
+        )
+        .bind(user_id)
+        .bind(assignment.tag_id)
+        .bind(assignment.valid_from)
+        .bind(assignment.valid_until)
+        .bind(actor.user_id())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(created)
+    }
+
+    pub async fn assignment_by_id(
+        &self,
+        assignment_id: u32,
+    ) -> Result<TagAssignment, DatabaseError> {
+        sqlx::query_as(
+            "SELECT id, user_id, tag_id, source, valid_from, valid_until
+            FROM user_tag
+            WHERE id = ?;
+            ",
+        )
+        .bind(assignment_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    pub async fn patch_assignment(
+        &self,
+        assignment_id: u32,
+        assignment: TagAssignmentPatch,
+        actor: Actor,
+    ) -> Result<TagAssignment, DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let user_id = assignment_user(&mut tx, assignment_id).await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let patched = sqlx::query_as(
+            "UPDATE user_tag SET
+                valid_from = CASE WHEN ? THEN ? ELSE valid_from END,
+                valid_until = CASE WHEN ? THEN ? ELSE valid_until END
+            WHERE id = ?
+            RETURNING id, user_id, tag_id, source, valid_from, valid_until;
+            ",
+        )
+        .bind(assignment.valid_from.is_some())
+        .bind(assignment.valid_from.flatten())
+        .bind(assignment.valid_until.is_some())
+        .bind(assignment.valid_until.flatten())
+        .bind(assignment_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(patched)
+    }
+
+    pub async fn delete_assignment(
+        &self,
+        assignment_id: u32,
+        actor: Actor,
+    ) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let user_id = assignment_user(&mut tx, assignment_id).await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        sqlx::query("DELETE FROM user_tag WHERE id = ?;")
+            .bind(assignment_id)
+            .execute(&mut *tx)
+            .await?;
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// the user an assignment belongs to
+async fn assignment_user(
+    conn: &mut SqliteConnection,
+    assignment_id: u32,
+) -> Result<u32, DatabaseError> {
+    sqlx::query_scalar("SELECT user_id FROM user_tag WHERE id = ?;")
+        .bind(assignment_id)
+        .fetch_optional(conn)
+        .await?
+        .ok_or(DatabaseError::NotFound)
 }
@@ -0,0 +1,116 @@
+use sqlx::SqlitePool;
+
+use crate::{
+    error::DatabaseError,
+    models::{achievement::Unlock, service::Namespace},
+};
+
+pub struct UnlockRepo<'a> {
+    db: &'a SqlitePool,
+    table: &'static str,
+}
+
+impl<'a> UnlockRepo<'a> {
+    pub fn new(db: &'a SqlitePool, namespace: Namespace) -> Self {
+        Self {
+            db,
+            table: namespace.unlock_table(),
+        }
+    }
+
+    pub async fn for_user(&self, user_id: u32) -> Result<Vec<Unlock>, DatabaseError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {0}.user_id, {0}.goal_id, achievement.service_id, {0}.time
+            FROM {0}
+                INNER JOIN goal ON {0}.goal_id = goal.id
+                INNER JOIN achievement ON goal.achievement_id = achievement.id
+            WHERE {0}.user_id = ?
+            ORDER BY {0}.time;
+            ",
+            self.table
+        ))
+        .bind(user_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn for_service(&self, service_id: u32) -> Result<Vec<Unlock>, DatabaseError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {0}.user_id, {0}.goal_id, achievement.service_id, {0}.time
+            FROM {0}
+                INNER JOIN goal ON {0}.goal_id = goal.id
+                INNER JOIN achievement ON goal.achievement_id = achievement.id
+            WHERE achievement.service_id = ?
+            ORDER BY {0}.time;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    pub async fn for_service_user(
+        &self,
+        service_id: u32,
+        user_id: u32,
+    ) -> Result<Vec<Unlock>, DatabaseError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {0}.user_id, {0}.goal_id, achievement.service_id, {0}.time
+            FROM {0}
+                INNER JOIN goal ON {0}.goal_id = goal.id
+                INNER JOIN achievement ON goal.achievement_id = achievement.id
+            WHERE achievement.service_id = ? AND {0}.user_id = ?
+            ORDER BY {0}.time;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .bind(user_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// unlock a goal of a service for a user
+    ///
+    /// unlocking a goal twice keeps the original time,
+    /// goals of other services are not found
+    pub async fn create(
+        &self,
+        service_id: u32,
+        user_id: u32,
+        goal_id: u32,
+    ) -> Result<Unlock, DatabaseError> {
+        sqlx::query(&format!(
+            "INSERT INTO {} (user_id, goal_id)
+            SELECT ?, goal.id
+            FROM goal
+                INNER JOIN achievement ON goal.achievement_id = achievement.id
+            WHERE goal.id = ? AND achievement.service_id = ?
+            ON CONFLICT DO NOTHING;
+            ",
+            self.table
+        ))
+        .bind(user_id)
+        .bind(goal_id)
+        .bind(service_id)
+        .execute(self.db)
+        .await?;
+
+        sqlx::query_as(&format!(
+            "SELECT {0}.user_id, {0}.goal_id, achievement.service_id, {0}.time
+            FROM {0}
+                INNER JOIN goal ON {0}.goal_id = goal.id
+                INNER JOIN achievement ON goal.achievement_id = achievement.id
+            WHERE achievement.service_id = ? AND {0}.user_id = ? AND {0}.goal_id = ?;
+            ",
+            self.table
+        ))
+        .bind(service_id)
+        .bind(user_id)
+        .bind(goal_id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+}
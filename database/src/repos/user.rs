@@ -1,10 +1,39 @@
-use sqlx::SqlitePool;
+use chrono::{DateTime, Utc};
+use sqlx::{Sqlite, SqliteConnection, SqlitePool, query::QueryAs, sqlite::SqliteArguments};
 
 use crate::{
     error::DatabaseError,
-    models::user::{User, UserCreate, UserPatch},
+    items,
+    models::{
+        profile_change::Actor,
+        user::{
+            FormerUsername, OfUser, SessionState, SocialLink, User, UserCreate, UserFilter,
+            UserListEntry, UserModeration, UserPatch, UserRecord, UserRecordFilter, UserSession,
+            UserSort, UsernameMatch,
+        },
+    },
+    placeholders,
+    repos::profile_change::{record_change, record_changes, snapshot},
 };
 
+/// conditions of a `UserFilter`, bound with `bind_filter`
+///
+/// parts of a profile that are private can't be filtered on
+static FILTER: &str = "user.visibility != 'private'
+    AND (? IS NULL OR user.id IN (SELECT rowid FROM user_search WHERE user_search MATCH ?))
+    AND (? IS NULL OR COALESCE(user.tags_visibility, '') != 'private' AND EXISTS (
+        SELECT 1 FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+        WHERE current_user_tag.user_id = user.id AND tag.name = ?))
+    AND (? IS NULL OR COALESCE(user.achievements_visibility, '') != 'private' AND EXISTS (
+        SELECT 1 FROM unlock INNER JOIN goal ON unlock.goal_id = goal.id
+        WHERE unlock.user_id = user.id AND goal.achievement_id = ?))
+    AND (? IS NULL OR (user.has_image AND COALESCE(user.image_visibility, '') != 'private') = ?)";
+
+/// conditions of a `UserRecordFilter`, bound with `bind_record_filter`
+static RECORD_FILTER: &str =
+    "(? IS NULL OR instr(lower(username), lower(?)) > 0 OR CAST(id AS TEXT) = ?)
+    AND (? IS NULL OR (disabled_at IS NOT NULL) = ?)";
+
 pub struct UserRepo<'a> {
     db: &'a SqlitePool,
 }
@@ -15,48 +44,664 @@ impl<'a> UserRepo<'a> {
     }
 
     pub async fn by_id(&self, id: u32) -> Result<User, DatabaseError> {
-        sqlx::query_as("SELECT id, username, about FROM user WHERE id == ? LIMIT 1;")
-            .bind(id)
-            .fetch_optional(self.db)
-            .await?
-            .ok_or(DatabaseError::NotFound)
+        sqlx::query_as(
+            "SELECT id, username, about, visibility,
+                about_visibility, tags_visibility, achievements_visibility, image_visibility
+            FROM user WHERE id == ? LIMIT 1;",
+        )
+        .bind(id)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
     }
 
     pub async fn by_username(&self, username: String) -> Result<User, DatabaseError> {
-        sqlx::query_as("SELECT id, username, about FROM user WHERE username == ? LIMIT 1;")
-            .bind(username)
-            .fetch_optional(self.db)
+        sqlx::query_as(
+            "SELECT id, username, about, visibility,
+                about_visibility, tags_visibility, achievements_visibility, image_visibility
+            FROM user WHERE username == ? LIMIT 1;",
+        )
+        .bind(username)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// the user who had this username last, before zauth renamed them
+    pub async fn by_former_username(&self, username: String) -> Result<User, DatabaseError> {
+        sqlx::query_as(
+            "SELECT user.id, user.username, user.about, user.visibility, user.about_visibility,
+                user.tags_visibility, user.achievements_visibility, user.image_visibility
+            FROM username_history INNER JOIN user ON username_history.user_id = user.id
+            WHERE username_history.username == ? LIMIT 1;",
+        )
+        .bind(username)
+        .fetch_optional(self.db)
+        .await?
+        .ok_or(DatabaseError::NotFound)
+    }
+
+    /// the users with these ids, in no particular order
+    pub async fn by_ids(&self, ids: &[u32]) -> Result<Vec<User>, DatabaseError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT id, username, about, visibility,
+                about_visibility, tags_visibility, achievements_visibility, image_visibility
+            FROM user WHERE id IN ({});",
+            placeholders(ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for id in ids {
+            query = query.bind(id);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+
+    /// the users with these usernames, a username that isn't in use anymore gives the user who
+    /// had it last
+    pub async fn by_usernames(
+        &self,
+        usernames: &[String],
+    ) -> Result<Vec<UsernameMatch>, DatabaseError> {
+        if usernames.is_empty() {
+            return Ok(Vec::new());
+        }
+        let names = placeholders(usernames.len());
+        let sql = format!(
+            "SELECT username AS name, id, username, about, visibility,
+                about_visibility, tags_visibility, achievements_visibility, image_visibility
+            FROM user WHERE username IN ({names})
+            UNION ALL
+            SELECT username_history.username, user.id, user.username, user.about,
+                user.visibility, user.about_visibility, user.tags_visibility,
+                user.achievements_visibility, user.image_visibility
+            FROM username_history INNER JOIN user ON username_history.user_id = user.id
+            WHERE username_history.username IN ({names})
+                AND username_history.username NOT IN (SELECT username FROM user);"
+        );
+        let mut query = sqlx::query_as(&sql);
+        for username in usernames.iter().chain(usernames) {
+            query = query.bind(username);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+
+    /// previous usernames of a user, most recent first
+    pub async fn former_usernames(
+        &self,
+        user_id: u32,
+    ) -> Result<Vec<FormerUsername>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT username, changed_at FROM username_history
+            WHERE user_id = ? ORDER BY changed_at DESC, username;",
+        )
+        .bind(user_id)
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// find non-private users with an fts5 match expression, best matches first
+    ///
+    /// a match on the username weighs more than one on the tags, which weighs more than the about
+    pub async fn search(
+        &self,
+        match_query: &str,
+        filter: &UserFilter,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<User>, DatabaseError> {
+        let sql = format!(
+            "SELECT user.id, user.username, user.about, user.visibility, user.about_visibility,
+                user.tags_visibility, user.achievements_visibility, user.image_visibility
+            FROM user_search
+                INNER JOIN user ON user_search.rowid = user.id
+            WHERE user_search MATCH ? AND {FILTER}
+            ORDER BY bm25(user_search, 10.0, 1.0, 5.0), user.username
+            LIMIT ? OFFSET ?;
+            "
+        );
+        let query = sqlx::query_as(&sql).bind(match_query);
+        Ok(bind_filter(query, filter)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    /// non-private users in the order of `sort`, starting after the `(sort_key, id)` cursor
+    pub async fn list(
+        &self,
+        sort: UserSort,
+        filter: &UserFilter,
+        after: Option<(String, u32)>,
+        limit: u32,
+    ) -> Result<Vec<UserListEntry>, DatabaseError> {
+        let column = sort.column();
+        let direction = sort.direction();
+        let cursor = match after {
+            Some(_) => format!("({column}, user.id) {} (?, ?)", sort.after()),
+            None => "TRUE".to_string(),
+        };
+        let sql = format!(
+            "SELECT user.id, user.username, user.about, user.visibility, user.about_visibility,
+                user.tags_visibility, user.achievements_visibility, user.image_visibility,
+                CAST({column} AS TEXT) AS sort_key
+            FROM user
+            WHERE {FILTER} AND {cursor}
+            ORDER BY {column} {direction}, user.id {direction}
+            LIMIT ?;
+            "
+        );
+
+        let mut query = bind_filter(sqlx::query_as(&sql), filter);
+        if let Some((key, id)) = after {
+            query = query.bind(key).bind(id);
+        }
+        Ok(query.bind(limit).fetch_all(self.db).await?)
+    }
+
+    /// remember whether a user has a profile image, for filtering
+    /// mark the users with these ids as having a profile image
+    pub async fn set_have_image(&self, user_ids: &[u32]) -> Result<(), DatabaseError> {
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+        let sql = format!(
+            "UPDATE user SET has_image = TRUE WHERE id IN ({});",
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query(&sql);
+        for id in user_ids {
+            query = query.bind(id);
+        }
+        query.execute(self.db).await?;
+        Ok(())
+    }
+
+    /// remember that the user is using the site, at most every few minutes
+    pub async fn touch(&self, user_id: u32) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE user SET last_active_at = CURRENT_TIMESTAMP
+            WHERE id = ? AND last_active_at < datetime('now', '-5 minutes');",
+        )
+        .bind(user_id)
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// set or clear the profile image of a user, and keep it in the history
+    ///
+    /// uploading a new image over an old one is kept as well
+    pub async fn change_image(
+        &self,
+        user_id: u32,
+        has_image: bool,
+        actor: Actor,
+    ) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+
+        let had_image: bool = sqlx::query_scalar("SELECT has_image FROM user WHERE id = ?;")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
             .await?
-            .ok_or(DatabaseError::NotFound)
+            .ok_or(DatabaseError::NotFound)?;
+
+        sqlx::query("UPDATE user SET has_image = ? WHERE id = ?;")
+            .bind(has_image)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if had_image || has_image {
+            let uploaded = String::from("uploaded");
+            let old_value = had_image.then_some(&uploaded);
+            let new_value = has_image.then_some(&uploaded);
+            record_change(&mut tx, user_id, actor, "image", old_value, new_value).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
     }
 
+    /// create a user on their first login, or update their username on later logins
+    ///
+    /// the previous username is kept in the history when it changes
     pub async fn create(&self, user: UserCreate) -> Result<User, DatabaseError> {
-        sqlx::query_as(
+        let mut tx = self.db.begin().await?;
+        let old_username: Option<String> =
+            sqlx::query_scalar("SELECT username FROM user WHERE id = ?;")
+                .bind(user.id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+        sqlx::query(
             "
-        INSERT INTO user (id, username) VALUES (?, ?)
-        ON CONFLICT(id) DO UPDATE SET username = ?
-        RETURNING id, username, about;
+        INSERT INTO username_history (username, user_id)
+        SELECT username, id FROM user WHERE id = ? AND username != ?
+        ON CONFLICT (username) DO UPDATE SET
+            user_id = excluded.user_id, changed_at = CURRENT_TIMESTAMP;
         ",
         )
         .bind(user.id)
         .bind(&user.username)
+        .execute(&mut *tx)
+        .await?;
+
+        // the name is current again, for this user or someone else
+        sqlx::query("DELETE FROM username_history WHERE username = ?;")
+            .bind(&user.username)
+            .execute(&mut *tx)
+            .await?;
+
+        let created = sqlx::query_as(
+            "
+        INSERT INTO user (id, username, last_login_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+        ON CONFLICT(id) DO UPDATE SET username = ?,
+            last_active_at = CURRENT_TIMESTAMP, last_login_at = CURRENT_TIMESTAMP
+        RETURNING id, username, about, visibility,
+            about_visibility, tags_visibility, achievements_visibility, image_visibility;
+        ",
+        )
+        .bind(user.id)
         .bind(&user.username)
-        .fetch_optional(self.db)
+        .bind(&user.username)
+        .fetch_optional(&mut *tx)
         .await?
-        .ok_or(DatabaseError::NotFound)
+        .ok_or(DatabaseError::NotFound)?;
+
+        // the username comes from zauth, so the change is made by zpi itself
+        if let Some(old_username) = old_username
+            && old_username != user.username
+        {
+            record_change(
+                &mut tx,
+                user.id,
+                Actor::System,
+                "username",
+                Some(&old_username),
+                Some(&user.username),
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(created)
     }
 
-    pub async fn patch(&self, user_id: u32, patch_user: UserPatch) -> Result<User, DatabaseError> {
-        sqlx::query_as(
+    /// every field that changes is kept in the history
+    pub async fn patch(
+        &self,
+        user_id: u32,
+        patch_user: UserPatch,
+        actor: Actor,
+    ) -> Result<User, DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let user = sqlx::query_as(
             "
-        UPDATE user SET about = ? WHERE id = ?
-        RETURNING id, username, about
+        UPDATE user SET
+            about = COALESCE(?, about),
+            visibility = COALESCE(?, visibility),
+            about_visibility = CASE WHEN ? THEN ? ELSE about_visibility END,
+            tags_visibility = CASE WHEN ? THEN ? ELSE tags_visibility END,
+            achievements_visibility = CASE WHEN ? THEN ? ELSE achievements_visibility END,
+            image_visibility = CASE WHEN ? THEN ? ELSE image_visibility END,
+            last_active_at = CURRENT_TIMESTAMP
+        WHERE id = ?
+        RETURNING id, username, about, visibility,
+            about_visibility, tags_visibility, achievements_visibility, image_visibility
         ",
         )
         .bind(patch_user.about)
+        .bind(patch_user.visibility)
+        .bind(patch_user.field_visibility.is_some())
+        .bind(patch_user.field_visibility.and_then(|fields| fields.about))
+        .bind(patch_user.field_visibility.is_some())
+        .bind(patch_user.field_visibility.and_then(|fields| fields.tags))
+        .bind(patch_user.field_visibility.is_some())
+        .bind(
+            patch_user
+                .field_visibility
+                .and_then(|fields| fields.achievements),
+        )
+        .bind(patch_user.field_visibility.is_some())
+        .bind(patch_user.field_visibility.and_then(|fields| fields.image))
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(DatabaseError::NotFound)?;
+
+        if let Some(links) = patch_user.links {
+            sqlx::query("DELETE FROM user_link WHERE user_id = ?;")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+
+            for (position, link) in links.into_iter().enumerate() {
+                sqlx::query(
+                    "INSERT INTO user_link (user_id, position, kind, value) VALUES (?, ?, ?, ?);",
+                )
+                .bind(user_id)
+                .bind(position as u32)
+                .bind(link.kind)
+                .bind(link.value)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        for (name, value) in patch_user.fields.unwrap_or_default() {
+            match value {
+                Some(value) => {
+                    sqlx::query(
+                        "INSERT INTO user_field (user_id, field_id, value)
+                        SELECT ?, id, ? FROM profile_field WHERE name = ?
+                        ON CONFLICT (user_id, field_id) DO UPDATE SET value = excluded.value;
+                        ",
+                    )
+                    .bind(user_id)
+                    .bind(value)
+                    .bind(&name)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                None => {
+                    sqlx::query(
+                        "DELETE FROM user_field
+                        WHERE user_id = ? AND field_id = (SELECT id FROM profile_field WHERE name = ?);
+                        ",
+                    )
+                    .bind(user_id)
+                    .bind(&name)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        record_changes(&mut tx, user_id, actor, before).await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    /// delete a user with everything that belongs to them and end their sessions
+    ///
+    /// logging in again through zauth creates a new, empty profile
+    pub async fn delete(&self, user_id: u32) -> Result<(), DatabaseError> {
+        let mut tx = self.db.begin().await?;
+
+        // unlocks don't cascade, the rest of the user's rows do
+        sqlx::query("DELETE FROM unlock WHERE user_id = ?;")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM user WHERE id = ?;")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(DatabaseError::NotFound);
+        }
+
+        revoke_sessions(&mut tx, user_id).await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// end every session of a user that is active right now
+    pub async fn revoke_sessions(&self, user_id: u32) -> Result<(), DatabaseError> {
+        let mut conn = self.db.acquire().await?;
+        revoke_sessions(&mut conn, user_id).await
+    }
+
+    /// whether the sessions of a user can still be used
+    ///
+    /// users that don't exist (anymore) are not disabled
+    pub async fn session_state(&self, user_id: u32) -> Result<SessionState, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT
+                (SELECT revoked_at FROM session_revocation WHERE user_id = ?) AS revoked_at,
+                EXISTS (SELECT 1 FROM user WHERE id = ? AND disabled_at IS NOT NULL) AS disabled;",
+        )
+        .bind(user_id)
+        .bind(user_id)
+        .fetch_one(self.db)
+        .await?)
+    }
+
+    /// remember a session a user logged in with
+    pub async fn add_session(
+        &self,
+        user_id: u32,
+        logged_in_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO user_session (user_id, logged_in_at, expires_at)
+            VALUES (?, ?, ?);",
+        )
+        .bind(user_id)
+        .bind(logged_in_at)
+        .bind(expires_at)
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// move the expiry of a session, it's pushed back when the session is written again
+    pub async fn extend_session(
+        &self,
+        user_id: u32,
+        logged_in_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query(
+            "UPDATE user_session SET expires_at = ? WHERE user_id = ? AND logged_in_at = ?;",
+        )
+        .bind(expires_at)
+        .bind(user_id)
+        .bind(logged_in_at)
+        .execute(self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_session(
+        &self,
+        user_id: u32,
+        logged_in_at: DateTime<Utc>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query("DELETE FROM user_session WHERE user_id = ? AND logged_in_at = ?;")
+            .bind(user_id)
+            .bind(logged_in_at)
+            .execute(self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// forget all sessions, they don't survive a restart
+    pub async fn clear_sessions(&self) -> Result<(), DatabaseError> {
+        sqlx::query("DELETE FROM user_session;")
+            .execute(self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// sessions of a user that were not ended and have not expired, most recent first
+    pub async fn sessions(&self, user_id: u32) -> Result<Vec<UserSession>, DatabaseError> {
+        Ok(sqlx::query_as(
+            "SELECT logged_in_at, expires_at FROM user_session
+            WHERE user_id = ? AND expires_at > ? ORDER BY logged_in_at DESC;",
+        )
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_all(self.db)
+        .await?)
+    }
+
+    /// everything admins can see about a user
+    pub async fn record(&self, user_id: u32) -> Result<UserRecord, DatabaseError> {
+        sqlx::query_as(
+            "SELECT id, username, about, visibility,
+                about_visibility, tags_visibility, achievements_visibility, image_visibility,
+                created_at, last_active_at, last_login_at, has_image, disabled_at, disabled_reason
+            FROM user WHERE id = ?;",
+        )
         .bind(user_id)
         .fetch_optional(self.db)
         .await?
         .ok_or(DatabaseError::NotFound)
     }
+
+    /// a page of all users for admins, private ones included, ordered by username
+    pub async fn records(
+        &self,
+        filter: &UserRecordFilter,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<UserRecord>, DatabaseError> {
+        let sql = format!(
+            "SELECT id, username, about, visibility,
+                about_visibility, tags_visibility, achievements_visibility, image_visibility,
+                created_at, last_active_at, last_login_at, has_image, disabled_at, disabled_reason
+            FROM user
+            WHERE {RECORD_FILTER}
+            ORDER BY username, id
+            LIMIT ? OFFSET ?;"
+        );
+        Ok(bind_record_filter(sqlx::query_as(&sql), filter)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(self.db)
+            .await?)
+    }
+
+    pub async fn count_records(&self, filter: &UserRecordFilter) -> Result<u32, DatabaseError> {
+        let sql = format!("SELECT COUNT(*) FROM user WHERE {RECORD_FILTER};");
+        let (count,) = bind_record_filter(sqlx::query_as(&sql), filter)
+            .fetch_one(self.db)
+            .await?;
+        Ok(count)
+    }
+
+    /// change a user as an admin
+    pub async fn moderate(
+        &self,
+        user_id: u32,
+        moderation: UserModeration,
+        actor: Actor,
+    ) -> Result<UserRecord, DatabaseError> {
+        let mut tx = self.db.begin().await?;
+        let before = snapshot(&mut tx, user_id).await?;
+
+        let record = sqlx::query_as(
+            "
+        UPDATE user SET
+            about = COALESCE(?, about),
+            disabled_at = CASE
+                WHEN ? IS NULL THEN disabled_at
+                WHEN ? THEN COALESCE(disabled_at, CURRENT_TIMESTAMP)
+                ELSE NULL END,
+            disabled_reason = CASE
+                WHEN ? IS NULL THEN disabled_reason
+                WHEN ? THEN COALESCE(?, disabled_reason)
+                ELSE NULL END
+        WHERE id = ?
+        RETURNING id, username, about, visibility,
+            about_visibility, tags_visibility, achievements_visibility, image_visibility,
+            created_at, last_active_at, last_login_at, has_image, disabled_at, disabled_reason;
+        ",
+        )
+        .bind(moderation.about)
+        .bind(moderation.disabled)
+        .bind(moderation.disabled)
+        .bind(moderation.disabled)
+        .bind(moderation.disabled)
+        .bind(moderation.disabled_reason)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(DatabaseError::NotFound)?;
+
+        if moderation.disabled == Some(true) {
+            revoke_sessions(&mut tx, user_id).await?;
+        }
+        record_changes(&mut tx, user_id, actor, before).await?;
+
+        tx.commit().await?;
+        Ok(record)
+    }
+
+    /// social links of a user, in the order they chose
+    pub async fn links(&self, user_id: u32) -> Result<Vec<SocialLink>, DatabaseError> {
+        Ok(items(self.links_for_users(&[user_id]).await?))
+    }
+
+    /// links of each of the users, in their order
+    pub async fn links_for_users(
+        &self,
+        user_ids: &[u32],
+    ) -> Result<Vec<OfUser<SocialLink>>, DatabaseError> {
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT user_id, kind, value FROM user_link WHERE user_id IN ({}) ORDER BY position;",
+            placeholders(user_ids.len())
+        );
+        let mut query = sqlx::query_as(&sql);
+        for user_id in user_ids {
+            query = query.bind(user_id);
+        }
+        Ok(query.fetch_all(self.db).await?)
+    }
+}
+
+async fn revoke_sessions(conn: &mut SqliteConnection, user_id: u32) -> Result<(), DatabaseError> {
+    sqlx::query(
+        "INSERT INTO session_revocation (user_id, revoked_at) VALUES (?, ?)
+        ON CONFLICT (user_id) DO UPDATE SET revoked_at = excluded.revoked_at;",
+    )
+    .bind(user_id)
+    .bind(Utc::now())
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query("DELETE FROM user_session WHERE user_id = ?;")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+fn bind_record_filter<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    filter: &UserRecordFilter,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    query
+        .bind(filter.search.clone())
+        .bind(filter.search.clone())
+        .bind(filter.search.clone())
+        .bind(filter.disabled)
+        .bind(filter.disabled)
+}
+
+fn bind_filter<'q, O>(
+    query: QueryAs<'q, Sqlite, O, SqliteArguments<'q>>,
+    filter: &UserFilter,
+) -> QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    query
+        .bind(filter.search.clone())
+        .bind(filter.search.clone())
+        .bind(filter.tag.clone())
+        .bind(filter.tag.clone())
+        .bind(filter.achievement_id)
+        .bind(filter.achievement_id)
+        .bind(filter.has_image)
+        .bind(filter.has_image)
 }
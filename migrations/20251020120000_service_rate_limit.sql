@@ -0,0 +1,2 @@
+ALTER TABLE service ADD COLUMN rate_limit_burst INTEGER NOT NULL DEFAULT 60;
+ALTER TABLE service ADD COLUMN rate_limit_per_minute INTEGER NOT NULL DEFAULT 60;
@@ -0,0 +1,2 @@
+ALTER TABLE user ADD COLUMN visibility TEXT NOT NULL DEFAULT 'members'
+    CHECK (visibility IN ('public', 'members', 'private'));
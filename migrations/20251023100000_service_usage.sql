@@ -0,0 +1,12 @@
+-- requests made with a test api key are counted apart from production requests
+CREATE TABLE service_usage (
+    service_id INTEGER NOT NULL,
+    namespace TEXT NOT NULL DEFAULT 'production' CHECK (namespace IN ('production', 'sandbox')),
+    hour DATETIME NOT NULL,
+    endpoint TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    count INTEGER NOT NULL DEFAULT 1,
+    last_called_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (service_id, namespace, hour, endpoint, status),
+    FOREIGN KEY (service_id) REFERENCES service (id) ON DELETE CASCADE
+);
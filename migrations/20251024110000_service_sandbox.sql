@@ -0,0 +1,27 @@
+ALTER TABLE service ADD COLUMN test_api_key TEXT;
+ALTER TABLE service ADD COLUMN owner_id INTEGER REFERENCES user (id) ON DELETE SET NULL;
+
+CREATE UNIQUE INDEX service_test_api_key ON service (test_api_key);
+
+-- unlocks written with a test api key
+CREATE TABLE sandbox_unlock (
+    user_id INTEGER NOT NULL,
+    goal_id INTEGER NOT NULL,
+    time DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, goal_id),
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (goal_id) REFERENCES goal (id) ON DELETE CASCADE
+);
+
+-- service data written with a test api key
+CREATE TABLE sandbox_service_data (
+    service_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    version INTEGER NOT NULL DEFAULT 1,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (service_id, user_id, key),
+    FOREIGN KEY (service_id) REFERENCES service (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE
+);
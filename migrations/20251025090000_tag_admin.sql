@@ -0,0 +1,3 @@
+ALTER TABLE tag_category ADD COLUMN description TEXT;
+
+CREATE UNIQUE INDEX tag_category_name ON tag_category (name);
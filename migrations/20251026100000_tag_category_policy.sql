@@ -0,0 +1,6 @@
+ALTER TABLE tag_category ADD COLUMN policy TEXT NOT NULL DEFAULT 'admin_only'
+    CHECK (policy IN ('admin_only', 'self_assignable', 'service_assignable'));
+-- NULL means no maximum
+ALTER TABLE tag_category ADD COLUMN max_per_user INTEGER;
+-- the service that hands out the tags of a service_assignable category, no service can if NULL
+ALTER TABLE tag_category ADD COLUMN service_id INTEGER REFERENCES service (id) ON DELETE SET NULL;
@@ -0,0 +1,4 @@
+-- tags synced from zauth roles are removed again when the role is gone,
+-- tags granted in any other way are never touched by the sync
+ALTER TABLE user_tag ADD COLUMN source TEXT NOT NULL DEFAULT 'manual'
+    CHECK (source IN ('manual', 'zauth'));
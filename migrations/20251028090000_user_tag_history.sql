@@ -0,0 +1,35 @@
+-- a user can hold the same tag multiple times over the years, so assignments get their own id
+CREATE TABLE user_tag_new
+(
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    source TEXT NOT NULL DEFAULT 'manual' CHECK (source IN ('manual', 'zauth')),
+    -- the user who made the assignment, NULL for services, zauth and older assignments
+    assigned_by INTEGER,
+    -- NULL means since forever / until further notice
+    valid_from DATETIME,
+    valid_until DATETIME,
+
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id)  REFERENCES tag (id) ON DELETE CASCADE,
+    FOREIGN KEY (assigned_by) REFERENCES user (id) ON DELETE SET NULL,
+
+    CHECK (valid_from IS NULL OR valid_until IS NULL OR datetime(valid_until) >= datetime(valid_from))
+);
+
+INSERT INTO user_tag_new (user_id, tag_id, source)
+SELECT user_id, tag_id, source FROM user_tag;
+
+DROP TABLE user_tag;
+ALTER TABLE user_tag_new RENAME TO user_tag;
+
+CREATE INDEX user_tag_user ON user_tag (user_id, tag_id);
+CREATE INDEX user_tag_tag ON user_tag (tag_id);
+-- at most one assignment per user and tag without an end
+CREATE UNIQUE INDEX user_tag_open ON user_tag (user_id, tag_id) WHERE valid_until IS NULL;
+
+CREATE VIEW current_user_tag AS
+SELECT * FROM user_tag
+WHERE (valid_from IS NULL OR datetime(valid_from) <= datetime('now'))
+    AND (valid_until IS NULL OR datetime(valid_until) > datetime('now'));
@@ -0,0 +1,12 @@
+-- colors are css hex colors like '#FF8000'
+ALTER TABLE tag_category ADD COLUMN color TEXT NOT NULL DEFAULT '#A0A0A0';
+ALTER TABLE tag_category ADD COLUMN icon BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- NULL uses the color of the category
+ALTER TABLE tag ADD COLUMN color TEXT;
+ALTER TABLE tag ADD COLUMN icon BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- the colors the frontend used to hardcode
+UPDATE tag_category SET color = '#FF8000' WHERE name = 'bestuur';
+UPDATE tag_category SET color = '#FF0000' WHERE name = 'bestuursfunctie';
+UPDATE tag_category SET color = '#00B400' WHERE name = 'topper';
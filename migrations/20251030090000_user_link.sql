@@ -0,0 +1,12 @@
+CREATE TABLE user_link
+(
+    user_id INTEGER NOT NULL,
+    -- order in which the user wants to show their links
+    position INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+
+    PRIMARY KEY (user_id, position)
+);
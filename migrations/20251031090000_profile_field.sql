@@ -0,0 +1,29 @@
+-- extra profile fields defined by admins
+CREATE TABLE profile_field
+(
+    id INTEGER PRIMARY KEY,
+    -- key used in the api, like 'pronouns'
+    name TEXT NOT NULL UNIQUE,
+    label TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('text', 'enum', 'date', 'url')),
+    -- json array of the allowed values of an enum field
+    options TEXT NOT NULL DEFAULT '[]',
+    -- maximum length of a text field, NULL for the default
+    max_length INTEGER,
+    -- who can see the values of this field
+    visibility TEXT NOT NULL DEFAULT 'members' CHECK (visibility IN ('public', 'members', 'private')),
+    -- order in which fields are shown
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE user_field
+(
+    user_id INTEGER NOT NULL,
+    field_id INTEGER NOT NULL,
+    value TEXT NOT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (field_id) REFERENCES profile_field (id) ON DELETE CASCADE,
+
+    PRIMARY KEY (user_id, field_id)
+);
@@ -0,0 +1,71 @@
+-- full text index of profiles, the rowid is the id of the user
+CREATE VIRTUAL TABLE user_search USING fts5
+(
+    username,
+    about,
+    -- names of the current tags, separated by spaces
+    tags,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+INSERT INTO user_search (rowid, username, about, tags)
+SELECT user.id, user.username, user.about,
+    (SELECT COALESCE(group_concat(tag.name, ' '), '')
+    FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+    WHERE current_user_tag.user_id = user.id)
+FROM user;
+
+CREATE TRIGGER user_search_insert AFTER INSERT ON user
+BEGIN
+    INSERT INTO user_search (rowid, username, about, tags)
+    VALUES (NEW.id, NEW.username, NEW.about, '');
+END;
+
+CREATE TRIGGER user_search_update AFTER UPDATE OF username, about ON user
+BEGIN
+    UPDATE user_search SET username = NEW.username, about = NEW.about WHERE rowid = NEW.id;
+END;
+
+CREATE TRIGGER user_search_delete AFTER DELETE ON user
+BEGIN
+    DELETE FROM user_search WHERE rowid = OLD.id;
+END;
+
+-- tags are indexed as they are when they change, an assignment that starts or ends
+-- by the passing of time is picked up on the next change of that user's tags
+CREATE TRIGGER user_search_tag_insert AFTER INSERT ON user_tag
+BEGIN
+    UPDATE user_search SET tags =
+        (SELECT COALESCE(group_concat(tag.name, ' '), '')
+        FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+        WHERE current_user_tag.user_id = NEW.user_id)
+    WHERE rowid = NEW.user_id;
+END;
+
+CREATE TRIGGER user_search_tag_update AFTER UPDATE ON user_tag
+BEGIN
+    UPDATE user_search SET tags =
+        (SELECT COALESCE(group_concat(tag.name, ' '), '')
+        FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+        WHERE current_user_tag.user_id = NEW.user_id)
+    WHERE rowid = NEW.user_id;
+END;
+
+CREATE TRIGGER user_search_tag_delete AFTER DELETE ON user_tag
+BEGIN
+    UPDATE user_search SET tags =
+        (SELECT COALESCE(group_concat(tag.name, ' '), '')
+        FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+        WHERE current_user_tag.user_id = OLD.user_id)
+    WHERE rowid = OLD.user_id;
+END;
+
+CREATE TRIGGER user_search_tag_rename AFTER UPDATE OF name ON tag
+BEGIN
+    UPDATE user_search SET tags =
+        (SELECT COALESCE(group_concat(tag.name, ' '), '')
+        FROM current_user_tag INNER JOIN tag ON current_user_tag.tag_id = tag.id
+        WHERE current_user_tag.user_id = user_search.rowid)
+    WHERE rowid IN (SELECT user_id FROM user_tag WHERE tag_id = NEW.id);
+END;
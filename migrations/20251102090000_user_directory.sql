@@ -0,0 +1,21 @@
+ALTER TABLE user ADD COLUMN created_at DATETIME;
+ALTER TABLE user ADD COLUMN last_active_at DATETIME;
+-- whether the user uploaded a profile image, kept in sync by the image endpoints
+ALTER TABLE user ADD COLUMN has_image BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- the real join date of existing users is unknown
+UPDATE user SET created_at = CURRENT_TIMESTAMP, last_active_at = CURRENT_TIMESTAMP;
+
+-- a column added later can't default to the current time
+CREATE TRIGGER user_set_created_at AFTER INSERT ON user
+WHEN NEW.created_at IS NULL
+BEGIN
+    UPDATE user SET
+        created_at = CURRENT_TIMESTAMP,
+        last_active_at = COALESCE(NEW.last_active_at, CURRENT_TIMESTAMP)
+    WHERE id = NEW.id;
+END;
+
+CREATE INDEX user_by_username ON user (username, id);
+CREATE INDEX user_by_created_at ON user (created_at, id);
+CREATE INDEX user_by_last_active_at ON user (last_active_at, id);
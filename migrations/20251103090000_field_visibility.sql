@@ -0,0 +1,62 @@
+-- overrides of the profile visibility for parts of a profile, NULL follows the profile
+ALTER TABLE user ADD COLUMN about_visibility TEXT
+    CHECK (about_visibility IN ('public', 'members', 'private'));
+ALTER TABLE user ADD COLUMN tags_visibility TEXT
+    CHECK (tags_visibility IN ('public', 'members', 'private'));
+ALTER TABLE user ADD COLUMN achievements_visibility TEXT
+    CHECK (achievements_visibility IN ('public', 'members', 'private'));
+ALTER TABLE user ADD COLUMN image_visibility TEXT
+    CHECK (image_visibility IN ('public', 'members', 'private'));
+
+-- the search index only holds what members are allowed to see
+CREATE VIEW user_search_tags AS
+SELECT user.id AS user_id, group_concat(tag.name, ' ') AS tags
+FROM user
+    INNER JOIN current_user_tag ON current_user_tag.user_id = user.id
+    INNER JOIN tag ON current_user_tag.tag_id = tag.id
+WHERE COALESCE(user.tags_visibility, '') != 'private'
+GROUP BY user.id;
+
+DROP TRIGGER user_search_update;
+DROP TRIGGER user_search_tag_insert;
+DROP TRIGGER user_search_tag_update;
+DROP TRIGGER user_search_tag_delete;
+DROP TRIGGER user_search_tag_rename;
+
+CREATE TRIGGER user_search_update
+AFTER UPDATE OF username, about, about_visibility, tags_visibility ON user
+BEGIN
+    UPDATE user_search SET
+        username = NEW.username,
+        about = CASE WHEN NEW.about_visibility = 'private' THEN '' ELSE NEW.about END,
+        tags = COALESCE((SELECT tags FROM user_search_tags WHERE user_id = NEW.id), '')
+    WHERE rowid = NEW.id;
+END;
+
+CREATE TRIGGER user_search_tag_insert AFTER INSERT ON user_tag
+BEGIN
+    UPDATE user_search SET
+        tags = COALESCE((SELECT tags FROM user_search_tags WHERE user_id = NEW.user_id), '')
+    WHERE rowid = NEW.user_id;
+END;
+
+CREATE TRIGGER user_search_tag_update AFTER UPDATE ON user_tag
+BEGIN
+    UPDATE user_search SET
+        tags = COALESCE((SELECT tags FROM user_search_tags WHERE user_id = NEW.user_id), '')
+    WHERE rowid = NEW.user_id;
+END;
+
+CREATE TRIGGER user_search_tag_delete AFTER DELETE ON user_tag
+BEGIN
+    UPDATE user_search SET
+        tags = COALESCE((SELECT tags FROM user_search_tags WHERE user_id = OLD.user_id), '')
+    WHERE rowid = OLD.user_id;
+END;
+
+CREATE TRIGGER user_search_tag_rename AFTER UPDATE OF name ON tag
+BEGIN
+    UPDATE user_search SET
+        tags = COALESCE((SELECT tags FROM user_search_tags WHERE user_id = user_search.rowid), '')
+    WHERE rowid IN (SELECT user_id FROM user_tag WHERE tag_id = NEW.id);
+END;
@@ -0,0 +1,6 @@
+-- sessions of a user that started before `revoked_at` are no longer valid
+-- not tied to the user row, so it outlives a deleted account
+CREATE TABLE session_revocation (
+    user_id INTEGER PRIMARY KEY NOT NULL,
+    revoked_at DATETIME NOT NULL
+);
@@ -0,0 +1,10 @@
+-- usernames zauth renamed users from, so links to old profiles keep working
+-- a name belongs to whoever had it last
+CREATE TABLE username_history (
+    username TEXT PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    changed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE
+);
+
+CREATE INDEX username_history_by_user ON username_history (user_id);
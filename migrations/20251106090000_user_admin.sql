@@ -0,0 +1,16 @@
+ALTER TABLE user ADD COLUMN last_login_at DATETIME;
+-- a disabled user can't log in, their profile stays as it is
+ALTER TABLE user ADD COLUMN disabled_at DATETIME;
+ALTER TABLE user ADD COLUMN disabled_reason TEXT;
+
+-- sessions are kept in memory, these rows are only there to show them to admins
+-- and are cleared when the server starts
+-- the login time is also stored in the session, the session id itself isn't stored
+-- `expires_at` follows the expiry of the session, rows past it are no longer shown
+CREATE TABLE user_session (
+    user_id INTEGER NOT NULL,
+    logged_in_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE,
+    PRIMARY KEY (user_id, logged_in_at)
+);
@@ -0,0 +1,17 @@
+-- audit log of admins acting as another user
+-- usernames are copied and there are no foreign keys, so the log outlives deleted accounts
+CREATE TABLE impersonation (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    admin_id INTEGER NOT NULL,
+    admin_username TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    allow_writes BOOLEAN NOT NULL DEFAULT FALSE,
+    started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- when the session of the admin expires, it's not written while impersonating
+    expires_at DATETIME NOT NULL,
+    ended_at DATETIME
+);
+
+CREATE INDEX impersonation_admin_id ON impersonation (admin_id);
+CREATE INDEX impersonation_user_id ON impersonation (user_id);
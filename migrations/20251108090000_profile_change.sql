@@ -0,0 +1,17 @@
+-- who changed what on a profile, for moderation disputes
+-- values are stored as text, lists like links and tags as json arrays
+-- the actor is a user or a service, neither is set for changes made by zpi itself
+CREATE TABLE profile_change (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    old_value TEXT,
+    new_value TEXT,
+    actor_user_id INTEGER,
+    actor_service_id INTEGER,
+    changed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE
+);
+
+CREATE INDEX profile_change_user_id ON profile_change (user_id, changed_at);
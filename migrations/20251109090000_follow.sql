@@ -0,0 +1,14 @@
+CREATE TABLE follow
+(
+    follower_id INTEGER NOT NULL,
+    followed_id INTEGER NOT NULL,
+    followed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (follower_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (followed_id) REFERENCES user (id) ON DELETE CASCADE,
+
+    PRIMARY KEY (follower_id, followed_id),
+    CHECK (follower_id != followed_id)
+);
+
+CREATE INDEX follow_followed ON follow (followed_id);
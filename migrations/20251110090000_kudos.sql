@@ -0,0 +1,30 @@
+-- short public endorsements between members, at most one per giver and recipient
+-- withdrawn kudos can be given again, which reuses the row
+CREATE TABLE kudos
+(
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    giver_id INTEGER NOT NULL,
+    recipient_id INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    -- what the kudos is for, a tag or a free skill
+    tag_id INTEGER,
+    skill TEXT,
+    created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- hidden by the recipient
+    hidden_at DATETIME,
+    -- removed by an admin, the row stays so it can't be given again
+    removed_at DATETIME,
+    removed_by INTEGER,
+    -- taken back by the giver
+    withdrawn_at DATETIME,
+
+    FOREIGN KEY (giver_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (recipient_id) REFERENCES user (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tag (id) ON DELETE SET NULL,
+
+    UNIQUE (giver_id, recipient_id),
+    CHECK (giver_id != recipient_id),
+    CHECK (tag_id IS NULL OR skill IS NULL)
+);
+
+CREATE INDEX kudos_recipient ON kudos (recipient_id, created_at);
@@ -1,6 +1,6 @@
-use std::{env, path::PathBuf};
+use std::{env, net::IpAddr, path::PathBuf, str::FromStr};
 
-use crate::error::AppError;
+use crate::{error::AppError, rate_limit::RateLimit};
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
@@ -15,6 +15,17 @@ pub struct AppConfig {
     pub database_url: String,
 
     pub frontend_url: String,
+
+    pub ip_rate_limit: RateLimit,
+    /// reverse proxies whose client ip header is trusted
+    pub trusted_proxies: Vec<IpAddr>,
+    /// header trusted proxies put the client ip in, like `X-Forwarded-For`
+    pub client_ip_header: String,
+    /// kudos a single member can give
+    pub kudos_rate_limit: RateLimit,
+
+    /// zauth roles and the tag name users with that role get
+    pub zauth_role_tags: Vec<(String, String)>,
 }
 
 impl AppConfig {
@@ -28,8 +39,28 @@ impl AppConfig {
             magick_path: get_env_var("MAGICK_PATH")?,
             database_url: get_env_var("DATABASE_URL")?,
             frontend_url: get_env_var("FRONTEND_URL")?,
+            ip_rate_limit: RateLimit {
+                burst: get_env_var_or("IP_RATE_LIMIT_BURST", 120)?,
+                per_minute: get_env_var_or("IP_RATE_LIMIT_PER_MINUTE", 120)?,
+            },
+            trusted_proxies: get_env_var_ips("TRUSTED_PROXIES")?,
+            client_ip_header: get_env_var_or("CLIENT_IP_HEADER", "X-Forwarded-For".to_string())?,
+            kudos_rate_limit: RateLimit {
+                burst: get_env_var_or("KUDOS_RATE_LIMIT_BURST", 5)?,
+                per_minute: get_env_var_or("KUDOS_RATE_LIMIT_PER_MINUTE", 1)?,
+            },
+            zauth_role_tags: get_env_var_role_tags("ZAUTH_ROLE_TAGS")?,
         })
     }
+
+    /// names of the tags a user with these zauth roles should have
+    pub fn tags_for_roles(&self, roles: &[String]) -> Vec<String> {
+        self.zauth_role_tags
+            .iter()
+            .filter(|(role, _)| roles.contains(role))
+            .map(|(_, tag)| tag.clone())
+            .collect()
+    }
 }
 
 fn get_env_var(name: &str) -> Result<String, AppError> {
@@ -41,3 +72,41 @@ fn get_env_var_path(name: &str) -> Result<PathBuf, AppError> {
         env::var(name).map_err(|_| AppError::Env(name.to_string()))?,
     ))
 }
+
+fn get_env_var_or<T: FromStr>(name: &str, default: T) -> Result<T, AppError> {
+    match env::var(name) {
+        Ok(value) => value.parse().map_err(|_| AppError::Env(name.to_string())),
+        Err(_) => Ok(default),
+    }
+}
+
+/// parse a comma separated list of ip addresses
+fn get_env_var_ips(name: &str) -> Result<Vec<IpAddr>, AppError> {
+    let Ok(value) = env::var(name) else {
+        return Ok(Vec::new());
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(|ip| ip.parse().map_err(|_| AppError::Env(name.to_string())))
+        .collect()
+}
+
+/// parse a comma separated list of `role=tag` pairs
+fn get_env_var_role_tags(name: &str) -> Result<Vec<(String, String)>, AppError> {
+    let Ok(value) = env::var(name) else {
+        return Ok(Vec::new());
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((role, tag)) if !role.trim().is_empty() && !tag.trim().is_empty() => {
+                Ok((role.trim().to_string(), tag.trim().to_string()))
+            }
+            _ => Err(AppError::Env(name.to_string())),
+        })
+        .collect()
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use database::{Database, error::DatabaseError};
+use pulldown_cmark::{
+    CowStr, Event, LinkType, Options, Parser, Tag, TagEnd, TextMergeStream, html,
+};
+
+use crate::{dto::user::Viewer, error::AppError};
+
+/// maximum length of an about in characters
+static MAX_ABOUT_LENGTH: usize = 2000;
+/// maximum amount of different users that are looked up for mentions
+static MAX_MENTIONS: usize = 20;
+
+/// check the markdown source of an about before it's saved
+pub fn check_about(about: &str) -> Result<(), AppError> {
+    if about.chars().count() > MAX_ABOUT_LENGTH {
+        return Err(AppError::PayloadError(format!(
+            "About can be at most {MAX_ABOUT_LENGTH} characters"
+        )));
+    }
+    if about
+        .chars()
+        .any(|c| (c.is_control() && !matches!(c, '\n' | '\r' | '\t')) || is_format(c))
+    {
+        return Err(AppError::PayloadError(
+            "About can't contain control or format characters".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// invisible format characters (unicode category Cf) like bidi overrides that can disguise text
+///
+/// the zero width joiner is allowed because emoji sequences need it
+fn is_format(c: char) -> bool {
+    matches!(c,
+        '\u{ad}'
+        | '\u{600}'..='\u{605}'
+        | '\u{61c}'
+        | '\u{6dd}'
+        | '\u{70f}'
+        | '\u{890}'..='\u{891}'
+        | '\u{8e2}'
+        | '\u{180e}'
+        | '\u{200b}'..='\u{200c}'
+        | '\u{200e}'..='\u{200f}'
+        | '\u{202a}'..='\u{202e}'
+        | '\u{2060}'..='\u{2064}'
+        | '\u{2066}'..='\u{206f}'
+        | '\u{feff}'
+        | '\u{fff9}'..='\u{fffb}'
+        | '\u{110bd}'
+        | '\u{110cd}'
+        | '\u{13430}'..='\u{1343f}'
+        | '\u{1bca0}'..='\u{1bca3}'
+        | '\u{1d173}'..='\u{1d17a}'
+        | '\u{e0001}'
+        | '\u{e0020}'..='\u{e007f}'
+    )
+}
+
+/// render the markdown of abouts to html that can be shown as is
+///
+/// raw html is escaped, headings become paragraphs, images are replaced by their description
+/// and only http(s) and mailto links are kept. `@username` links to the profile of that user
+/// if the viewer is allowed to see it. The mentions of all abouts are looked up at once.
+pub async fn render_abouts(
+    db: &Database,
+    abouts: &[String],
+    viewer: &Viewer,
+    frontend_url: &str,
+) -> Result<Vec<String>, DatabaseError> {
+    let documents: Vec<Vec<Mentionable>> = abouts
+        .iter()
+        .map(|about| {
+            sanitize(TextMergeStream::new(Parser::new_ext(
+                about,
+                Options::ENABLE_STRIKETHROUGH,
+            )))
+        })
+        .collect();
+
+    let mentioned: Vec<Vec<&str>> = documents.iter().map(|events| mentions(events)).collect();
+    let mut names: Vec<String> = Vec::new();
+    for name in mentioned.iter().flatten() {
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+    }
+    // former usernames link to the current profile
+    let found: HashMap<String, String> = db
+        .users()
+        .by_usernames(&names)
+        .await?
+        .into_iter()
+        .filter(|found| viewer.can_see(&found.user))
+        .map(|found| (found.name, found.user.username))
+        .collect();
+
+    let profile_url = format!("{}/profile/", frontend_url.trim_end_matches('/'));
+    Ok(documents
+        .iter()
+        .zip(mentioned)
+        .map(|(events, names)| {
+            let known: HashMap<String, String> = names
+                .into_iter()
+                .filter_map(|name| Some((name.to_string(), found.get(name)?.clone())))
+                .collect();
+            let mut output = Vec::new();
+            for event in events {
+                match event {
+                    Mentionable::Text(text) => {
+                        link_mentions(text, &known, &profile_url, &mut output)
+                    }
+                    Mentionable::Other(event) => output.push(event.clone()),
+                }
+            }
+            let mut rendered = String::new();
+            html::push_html(&mut rendered, output.into_iter());
+            rendered
+        })
+        .collect())
+}
+
+/// the different names mentioned in an about, only the first few are looked up
+fn mentions<'a>(events: &'a [Mentionable]) -> Vec<&'a str> {
+    let mut names: Vec<&str> = Vec::new();
+    for event in events {
+        if let Mentionable::Text(text) = event {
+            for (_, name) in find_mentions(text) {
+                if names.len() < MAX_MENTIONS && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    names
+}
+
+/// text outside of links and code can contain mentions
+enum Mentionable<'a> {
+    Text(CowStr<'a>),
+    Other(Event<'a>),
+}
+
+/// keep only the markdown subset that's allowed in an about
+fn sanitize<'a>(parser: impl Iterator<Item = Event<'a>>) -> Vec<Mentionable<'a>> {
+    let mut events = Vec::new();
+    // whether each open link is kept
+    let mut links: Vec<bool> = Vec::new();
+    let mut in_code_block = false;
+
+    for event in parser {
+        let event = match event {
+            Event::Start(Tag::Heading { .. }) => Event::Start(Tag::Paragraph),
+            Event::End(TagEnd::Heading(_)) => Event::End(TagEnd::Paragraph),
+            Event::Start(Tag::Image { .. })
+            | Event::End(TagEnd::Image)
+            | Event::Start(Tag::HtmlBlock)
+            | Event::End(TagEnd::HtmlBlock) => continue,
+            Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let allowed = is_allowed_url(&dest_url);
+                links.push(allowed);
+                if !allowed {
+                    continue;
+                }
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                })
+            }
+            Event::End(TagEnd::Link) => {
+                if !links.pop().unwrap_or_default() {
+                    continue;
+                }
+                Event::End(TagEnd::Link)
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                Event::Start(Tag::CodeBlock(kind))
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                Event::End(TagEnd::CodeBlock)
+            }
+            event => event,
+        };
+
+        events.push(match event {
+            Event::Text(text) if links.is_empty() && !in_code_block => Mentionable::Text(text),
+            event => Mentionable::Other(event),
+        });
+    }
+    events
+}
+
+fn is_allowed_url(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    ["https://", "http://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+}
+
+/// byte offset and name of every `@username` in a text, email addresses are skipped
+fn find_mentions(text: &str) -> Vec<(usize, &str)> {
+    let mut mentions = Vec::new();
+    let mut previous = None;
+    for (start, c) in text.char_indices() {
+        if c == '@' && !previous.is_some_and(char::is_alphanumeric) {
+            let rest = text.get(start + 1..).unwrap_or_default();
+            let length = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')))
+                .unwrap_or(rest.len());
+            let name = rest
+                .get(..length)
+                .unwrap_or_default()
+                .trim_end_matches(['.', '-']);
+            if !name.is_empty() {
+                mentions.push((start, name));
+            }
+        }
+        previous = Some(c);
+    }
+    mentions
+}
+
+/// split a text into text and links for the mentions of known users
+///
+/// `known` maps a mentioned name to the current username of the user
+fn link_mentions<'a>(
+    text: &CowStr<'a>,
+    known: &HashMap<String, String>,
+    profile_url: &str,
+    output: &mut Vec<Event<'a>>,
+) {
+    let mut rest_start = 0;
+    for (start, name) in find_mentions(text) {
+        let Some(username) = known.get(name) else {
+            continue;
+        };
+        let end = start + 1 + name.len();
+        if let Some(before) = text.get(rest_start..start)
+            && !before.is_empty()
+        {
+            output.push(Event::Text(before.to_string().into()));
+        }
+        output.push(Event::Start(Tag::Link {
+            link_type: LinkType::Inline,
+            dest_url: format!("{profile_url}{username}").into(),
+            title: CowStr::Borrowed(""),
+            id: CowStr::Borrowed(""),
+        }));
+        output.push(Event::Text(format!("@{name}").into()));
+        output.push(Event::End(TagEnd::Link));
+        rest_start = end;
+    }
+
+    if rest_start == 0 {
+        output.push(Event::Text(text.clone()));
+    } else if let Some(rest) = text.get(rest_start..)
+        && !rest.is_empty()
+    {
+        output.push(Event::Text(rest.to_string().into()));
+    }
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Local};
+use database::{
+    Database,
+    models::{
+        follow::Follow,
+        kudos::{Kudos, KudosScope},
+        profile_change::ProfileChange,
+        service::Namespace,
+        tag::{TagAssignment, UserTag},
+        user::{FormerUsername, User},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::{
+        link::SocialLinkPayload, profile_field::ProfileFieldValuePayload, sandbox::SandboxPayload,
+        service_data::ServiceDataUserPayload, unlock::UnlockPayload,
+    },
+    error::AppError,
+};
+
+/// everything zpi stores about a user, the manifest of their data export
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserExportPayload {
+    pub exported_at: DateTime<Local>,
+    pub user: User,
+    /// usernames the user had before, most recent first
+    pub former_usernames: Vec<FormerUsername>,
+    pub tags: Vec<UserTag>,
+    /// tags the user held in the past, most recent first
+    pub former_tags: Vec<UserTag>,
+    /// tags the user will get or lose at a set time
+    pub tag_assignments: Vec<TagAssignment>,
+    pub links: Vec<SocialLinkPayload>,
+    pub fields: Vec<ProfileFieldValuePayload>,
+    /// who changed what on the profile, most recent first
+    pub history: Vec<ProfileChange>,
+    /// users the user follows, most recent first
+    pub following: Vec<Follow>,
+    /// kudos the user gave, most recent first
+    pub kudos_given: Vec<Kudos>,
+    /// kudos the user got that are shown on their profile or hidden by them, most recent first
+    pub kudos_received: Vec<Kudos>,
+    pub unlocks: Vec<UnlockPayload>,
+    pub service_data: Vec<ServiceDataUserPayload>,
+    /// what services wrote with a test api key
+    pub sandbox: SandboxPayload,
+    /// name of the original profile image in the archive, if there is one
+    pub image: Option<String>,
+}
+
+impl UserExportPayload {
+    pub async fn for_user(db: &Database, user_id: u32) -> Result<Self, AppError> {
+        Ok(Self {
+            exported_at: Local::now(),
+            user: db.users().by_id(user_id).await?,
+            former_usernames: db.users().former_usernames(user_id).await?,
+            tags: db.tags().for_user(user_id).await?,
+            former_tags: db.tags().former_for_user(user_id).await?,
+            tag_assignments: db.tags().assignments(user_id).await?,
+            links: db
+                .users()
+                .links(user_id)
+                .await?
+                .into_iter()
+                .map(SocialLinkPayload::from)
+                .collect(),
+            fields: db
+                .profile_fields()
+                .for_user(user_id)
+                .await?
+                .into_iter()
+                .map(ProfileFieldValuePayload::from)
+                .collect(),
+            history: db.profile_changes().for_user(user_id, u32::MAX, 0).await?,
+            following: db.follows().all_following(user_id).await?,
+            kudos_given: db.kudos().given_by(user_id).await?,
+            kudos_received: db
+                .kudos()
+                .for_recipient(
+                    user_id,
+                    &KudosScope {
+                        hidden: true,
+                        members_only_givers: true,
+                    },
+                    u32::MAX,
+                    0,
+                )
+                .await?,
+            unlocks: db
+                .unlocks(Namespace::Production)
+                .for_user(user_id)
+                .await?
+                .into_iter()
+                .map(UnlockPayload::from)
+                .collect(),
+            service_data: ServiceDataUserPayload::for_user(db, Namespace::Production, user_id)
+                .await?,
+            sandbox: SandboxPayload::for_user(db, user_id).await?,
+            image: None,
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use database::{
+    Database,
+    models::follow::{FeedEntry, FeedKind},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::AppConfig,
+    dto::{
+        page::page,
+        user::{UserId, UserProfile, Viewer},
+    },
+    error::AppError,
+};
+
+/// follow a user the follower is allowed to see
+pub async fn follow(
+    db: &Database,
+    follower_id: u32,
+    user_id: u32,
+    viewer: &Viewer,
+) -> Result<(), AppError> {
+    if follower_id == user_id {
+        return Err(AppError::PayloadError("You can't follow yourself".into()));
+    }
+    UserId::Id(user_id).visible_user(db, viewer).await?;
+    Ok(db.follows().follow(follower_id, user_id).await?)
+}
+
+/// which side of the follows of a user to list
+#[derive(Debug, Clone, Copy)]
+pub enum FollowList {
+    Followers,
+    Following,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct FollowListQuery {
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// a page of the followers or followed users of a user, most recent first
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FollowListPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub users: Vec<UserProfile>,
+}
+
+impl FollowListQuery {
+    pub async fn list(
+        self,
+        db: &Database,
+        user_id: UserId,
+        list: FollowList,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<FollowListPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+        let user = user_id.visible_user(db, viewer).await?;
+
+        let counts = db.follows().counts(user.id).await?;
+        let (total, users) = match list {
+            FollowList::Followers => (
+                counts.followers,
+                db.follows().followers(user.id, limit, offset).await?,
+            ),
+            FollowList::Following => (
+                counts.following,
+                db.follows().following(user.id, limit, offset).await?,
+            ),
+        };
+
+        Ok(FollowListPayload {
+            total,
+            offset,
+            limit,
+            users: UserProfile::from_users(db, users, viewer, config).await?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct FeedQuery {
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// a page of what the users someone follows did, most recent first
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FeedPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub items: Vec<FeedItem>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FeedItem {
+    Unlock {
+        user_id: u32,
+        username: String,
+        goal_id: u32,
+        achievement_id: u32,
+        service_id: u32,
+        time: DateTime<Utc>,
+    },
+    /// only says which part changed, see the profile for what it is now
+    ProfileUpdate {
+        user_id: u32,
+        username: String,
+        /// `about`, `tags`, `image` or `links`
+        field: String,
+        time: DateTime<Utc>,
+    },
+}
+
+impl From<FeedEntry> for FeedItem {
+    fn from(value: FeedEntry) -> Self {
+        match value.kind {
+            FeedKind::Unlock => Self::Unlock {
+                user_id: value.user_id,
+                username: value.username,
+                goal_id: value.goal_id.unwrap_or_default(),
+                achievement_id: value.achievement_id.unwrap_or_default(),
+                service_id: value.service_id.unwrap_or_default(),
+                time: value.time,
+            },
+            FeedKind::ProfileUpdate => Self::ProfileUpdate {
+                user_id: value.user_id,
+                username: value.username,
+                field: value.field.unwrap_or_default(),
+                time: value.time,
+            },
+        }
+    }
+}
+
+impl FeedQuery {
+    pub async fn feed(self, db: &Database, user_id: u32) -> Result<FeedPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+        Ok(FeedPayload {
+            total: db.follows().count_feed(user_id).await?,
+            offset,
+            limit,
+            items: db
+                .follows()
+                .feed(user_id, limit, offset)
+                .await?
+                .into_iter()
+                .map(FeedItem::from)
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use database::{
+    Database,
+    models::impersonation::{Impersonation, ImpersonationCreate, ImpersonationFilter},
+};
+use serde::{Deserialize, Serialize};
+use tower_sessions::Session;
+
+use crate::{
+    dto::page::page,
+    error::AppError,
+    extractors::authenticated_user::{AuthenticatedUser, ImpersonationSession},
+};
+
+/// the logged in user, as shown at `/users/me`
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CurrentUserPayload {
+    #[serde(flatten)]
+    pub user: AuthenticatedUser,
+    /// only there while an admin acts as this user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonation: Option<ImpersonationPayload>,
+}
+
+impl CurrentUserPayload {
+    pub async fn get(user: AuthenticatedUser, session: &Session) -> Result<Self, AppError> {
+        let impersonation = AuthenticatedUser::impersonation(session)
+            .await?
+            .map(|impersonation| ImpersonationPayload::new(impersonation, &user));
+        Ok(Self {
+            user,
+            impersonation,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ImpersonationPayload {
+    pub admin_id: u32,
+    pub admin_username: String,
+    /// write requests are refused unless this is set
+    pub allow_writes: bool,
+    pub started_at: DateTime<Utc>,
+    /// text for a banner, so it's never forgotten who you are looking as
+    pub banner: String,
+}
+
+impl ImpersonationPayload {
+    fn new(impersonation: ImpersonationSession, user: &AuthenticatedUser) -> Self {
+        let banner = if impersonation.allow_writes {
+            format!(
+                "You are acting as {}, changes are made in their name",
+                user.username
+            )
+        } else {
+            format!(
+                "You are viewing ZPI as {}, changes are blocked",
+                user.username
+            )
+        };
+        Self {
+            admin_id: impersonation.admin.id,
+            admin_username: impersonation.admin.username,
+            allow_writes: impersonation.allow_writes,
+            started_at: impersonation.started_at,
+            banner,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ImpersonationStartPayload {
+    /// allow changes while acting as the user, off by default
+    #[serde(default)]
+    pub allow_writes: bool,
+}
+
+impl ImpersonationStartPayload {
+    /// let an admin act as another user on their session, and put it in the audit log
+    pub async fn start(
+        self,
+        db: &Database,
+        session: &Session,
+        admin: AuthenticatedUser,
+        user_id: u32,
+    ) -> Result<CurrentUserPayload, AppError> {
+        if user_id == admin.id {
+            return Err(AppError::PayloadError(
+                "You can't impersonate yourself".into(),
+            ));
+        }
+        let user = db.users().by_id(user_id).await?;
+        if db.users().session_state(user_id).await?.disabled {
+            return Err(AppError::PayloadError(
+                "You can't impersonate a disabled user".into(),
+            ));
+        }
+
+        let impersonation = db
+            .impersonations()
+            .create(ImpersonationCreate {
+                admin_id: admin.id,
+                admin_username: admin.username.clone(),
+                user_id: user.id,
+                username: user.username.clone(),
+                allow_writes: self.allow_writes,
+                expires_at: AuthenticatedUser::expiry(session),
+            })
+            .await?;
+        let user = AuthenticatedUser {
+            id: user.id,
+            username: user.username,
+            admin: false,
+        }
+        .impersonate(session, admin, &impersonation)
+        .await?;
+        CurrentUserPayload::get(user, session).await
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ImpersonationQuery {
+    pub admin_id: Option<u32>,
+    pub user_id: Option<u32>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// a page of the impersonation audit log
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImpersonationListPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub impersonations: Vec<Impersonation>,
+}
+
+impl ImpersonationQuery {
+    pub async fn list(self, db: &Database) -> Result<ImpersonationListPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+        db.impersonations().end_expired().await?;
+
+        let filter = ImpersonationFilter {
+            admin_id: self.admin_id,
+            user_id: self.user_id,
+        };
+        Ok(ImpersonationListPayload {
+            total: db.impersonations().count(&filter).await?,
+            offset,
+            limit,
+            impersonations: db.impersonations().list(&filter, limit, offset).await?,
+        })
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use database::{
+    Database,
+    error::DatabaseError,
+    models::kudos::{Kudos, KudosCreate, KudosFilter},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::AppConfig,
+    dto::{
+        page::page,
+        user::{UserId, Viewer},
+    },
+    error::AppError,
+    rate_limit::{RateLimitKey, RateLimiter},
+};
+
+/// maximum amount of characters in the message of kudos
+static MAX_MESSAGE_LENGTH: usize = 140;
+/// maximum amount of characters in a free skill
+static MAX_SKILL_LENGTH: usize = 40;
+
+/// kudos for a tag or a free skill, never both
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct KudosCreatePayload {
+    /// like "helped me with Rust"
+    pub message: String,
+    pub tag_id: Option<u32>,
+    pub skill: Option<String>,
+}
+
+impl KudosCreatePayload {
+    /// give kudos to a user the giver is allowed to see, limited per giver
+    pub async fn give(
+        self,
+        db: &Database,
+        limiter: &RateLimiter,
+        config: &AppConfig,
+        giver: &Viewer,
+        giver_id: u32,
+        recipient_id: u32,
+    ) -> Result<Kudos, AppError> {
+        if giver_id == recipient_id {
+            return Err(AppError::PayloadError(
+                "You can't give yourself kudos".into(),
+            ));
+        }
+        let message = self.message.trim().to_string();
+        if message.is_empty() {
+            return Err(AppError::PayloadError("Message can't be empty".into()));
+        }
+        if message.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(AppError::PayloadError(format!(
+                "Message can be at most {MAX_MESSAGE_LENGTH} characters"
+            )));
+        }
+        let skill = self
+            .skill
+            .map(|skill| skill.trim().to_string())
+            .filter(|skill| !skill.is_empty());
+        if skill
+            .as_ref()
+            .is_some_and(|skill| skill.chars().count() > MAX_SKILL_LENGTH)
+        {
+            return Err(AppError::PayloadError(format!(
+                "Skill can be at most {MAX_SKILL_LENGTH} characters"
+            )));
+        }
+        match (self.tag_id, &skill) {
+            (Some(_), Some(_)) | (None, None) => {
+                return Err(AppError::PayloadError(
+                    "Kudos need either a tag or a skill".into(),
+                ));
+            }
+            (Some(tag_id), None) => match db.tags().by_id(tag_id).await {
+                Err(DatabaseError::NotFound) => {
+                    return Err(AppError::PayloadError(format!("Unknown tag {tag_id}")));
+                }
+                result => {
+                    result?;
+                }
+            },
+            (None, Some(_)) => {}
+        }
+
+        UserId::Id(recipient_id).visible_user(db, giver).await?;
+        db.kudos().check_can_give(giver_id, recipient_id).await?;
+
+        // only kudos that could be given count towards the limit
+        limiter.acquire(RateLimitKey::Kudos(giver_id), config.kudos_rate_limit)?;
+        Ok(db
+            .kudos()
+            .create(KudosCreate {
+                giver_id,
+                recipient_id,
+                message,
+                tag_id: self.tag_id,
+                skill,
+            })
+            .await?)
+    }
+}
+
+/// kudos as shown to members and services, without what only admins see
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct KudosPayload {
+    pub id: u32,
+    pub giver_id: u32,
+    pub giver_username: String,
+    pub recipient_id: u32,
+    pub message: String,
+    pub tag_id: Option<u32>,
+    pub tag_name: Option<String>,
+    pub skill: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// only the recipient sees the kudos they hid
+    pub hidden: bool,
+}
+
+impl From<Kudos> for KudosPayload {
+    fn from(value: Kudos) -> Self {
+        Self {
+            id: value.id,
+            giver_id: value.giver_id,
+            giver_username: value.giver_username,
+            recipient_id: value.recipient_id,
+            message: value.message,
+            tag_id: value.tag_id,
+            tag_name: value.tag_name,
+            skill: value.skill,
+            created_at: value.created_at,
+            hidden: value.hidden_at.is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct KudosHidePayload {
+    pub hidden: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct KudosListQuery {
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// a page of kudos for admins, most recent first
+#[derive(Debug, Deserialize, Serialize)]
+pub struct KudosListPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub kudos: Vec<Kudos>,
+}
+
+/// a page of kudos a user got, most recent first
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserKudosPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub kudos: Vec<KudosPayload>,
+}
+
+impl KudosListQuery {
+    /// kudos a user got, the user themselves also sees the ones they hid
+    pub async fn list(
+        self,
+        db: &Database,
+        user_id: UserId,
+        viewer: &Viewer,
+    ) -> Result<UserKudosPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+        let user = user_id.visible_user(db, viewer).await?;
+        let scope =
+            viewer.kudos_scope(matches!(viewer, Viewer::Member { id, .. } if *id == user.id));
+
+        Ok(UserKudosPayload {
+            total: db.kudos().count_for_recipient(user.id, &scope).await?,
+            offset,
+            limit,
+            kudos: db
+                .kudos()
+                .for_recipient(user.id, &scope, limit, offset)
+                .await?
+                .into_iter()
+                .map(KudosPayload::from)
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct KudosAdminQuery {
+    /// only kudos given or received by this user
+    pub user_id: Option<u32>,
+    pub removed: Option<bool>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+impl KudosAdminQuery {
+    /// all kudos, hidden and removed ones included
+    pub async fn list(self, db: &Database) -> Result<KudosListPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+        let filter = KudosFilter {
+            user_id: self.user_id,
+            removed: self.removed,
+        };
+        Ok(KudosListPayload {
+            total: db.kudos().count(&filter).await?,
+            offset,
+            limit,
+            kudos: db.kudos().list(&filter, limit, offset).await?,
+        })
+    }
+}
@@ -0,0 +1,188 @@
+use database::models::user::{LinkKind, SocialLink};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// maximum amount of links on a profile
+static MAX_LINKS: usize = 10;
+/// maximum length of a link value
+static MAX_VALUE_LENGTH: usize = 256;
+
+static MATTERMOST_URL: &str = "https://mattermost.zeus.gent/zeus/messages/@";
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct SocialLinkPayload {
+    pub kind: LinkKind,
+    /// normalized username, address or url
+    pub value: String,
+    /// where the link points to
+    pub url: String,
+}
+
+impl From<SocialLink> for SocialLinkPayload {
+    fn from(value: SocialLink) -> Self {
+        Self {
+            url: link_url(value.kind, &value.value),
+            kind: value.kind,
+            value: value.value,
+        }
+    }
+}
+
+/// validate and normalize the links a user submitted
+pub fn normalize_links(links: Vec<SocialLink>) -> Result<Vec<SocialLink>, AppError> {
+    if links.len() > MAX_LINKS {
+        return Err(AppError::PayloadError(format!(
+            "A profile can have at most {MAX_LINKS} links"
+        )));
+    }
+
+    let mut normalized: Vec<SocialLink> = Vec::with_capacity(links.len());
+    for link in links {
+        let link = SocialLink {
+            value: normalize_value(link.kind, link.value.trim())?,
+            kind: link.kind,
+        };
+        if normalized.contains(&link) {
+            return Err(AppError::PayloadError(format!(
+                "Duplicate link '{}'",
+                link.value
+            )));
+        }
+        normalized.push(link);
+    }
+    Ok(normalized)
+}
+
+fn normalize_value(kind: LinkKind, value: &str) -> Result<String, AppError> {
+    let invalid = || AppError::PayloadError(format!("Invalid {kind:?} link '{value}'"));
+
+    if value.is_empty() || value.len() > MAX_VALUE_LENGTH {
+        return Err(invalid());
+    }
+
+    match kind {
+        LinkKind::Github => {
+            let name = strip_profile_url(value, "github.com");
+            let valid = name.len() <= 39
+                && !name.starts_with('-')
+                && !name.ends_with('-')
+                && is_username(name, &['-']);
+            valid.then(|| name.to_string()).ok_or_else(invalid)
+        }
+        LinkKind::Gitlab => {
+            let name = strip_profile_url(value, "gitlab.com");
+            is_username(name, &['-', '_', '.'])
+                .then(|| name.to_string())
+                .ok_or_else(invalid)
+        }
+        LinkKind::Mattermost => {
+            let name = value.trim_start_matches('@').to_lowercase();
+            (is_username(&name, &['-', '_', '.']) && name.len() <= 22)
+                .then_some(name)
+                .ok_or_else(invalid)
+        }
+        LinkKind::Matrix => {
+            // @localpart:server
+            let (localpart, server) = value
+                .strip_prefix('@')
+                .and_then(|id| id.split_once(':'))
+                .ok_or_else(invalid)?;
+            let localpart = localpart.to_lowercase();
+            let valid = is_username(&localpart, &['-', '_', '.', '=', '/']) && is_domain(server);
+            valid
+                .then(|| format!("@{localpart}:{}", server.to_lowercase()))
+                .ok_or_else(invalid)
+        }
+        LinkKind::Website => normalize_url(value).ok_or_else(invalid),
+        LinkKind::Email => {
+            let value = value.strip_prefix("mailto:").unwrap_or(value);
+            let (local, domain) = value.rsplit_once('@').ok_or_else(invalid)?;
+            let valid = is_dot_atom(local) && is_domain(domain);
+            valid
+                .then(|| format!("{local}@{}", domain.to_lowercase()))
+                .ok_or_else(invalid)
+        }
+    }
+}
+
+/// parse a http(s) url on a domain, `https://` is added if the scheme is missing
+pub fn normalize_url(value: &str) -> Option<String> {
+    let with_scheme = if value.contains("://") {
+        value.to_string()
+    } else {
+        format!("https://{value}")
+    };
+    let url = Url::parse(&with_scheme).ok()?;
+    let valid = matches!(url.scheme(), "http" | "https") && url.host_str().is_some_and(is_domain);
+    valid.then(|| url.to_string())
+}
+
+fn link_url(kind: LinkKind, value: &str) -> String {
+    match kind {
+        LinkKind::Github => format!("https://github.com/{value}"),
+        LinkKind::Gitlab => format!("https://gitlab.com/{value}"),
+        LinkKind::Mattermost => format!("{MATTERMOST_URL}{value}"),
+        LinkKind::Matrix => format!("https://matrix.to/#/{value}"),
+        LinkKind::Website => value.to_string(),
+        LinkKind::Email => match value.rsplit_once('@') {
+            Some((local, domain)) => format!("mailto:{}@{domain}", encode_mailto(local)),
+            None => format!("mailto:{value}"),
+        },
+    }
+}
+
+/// accepts `name`, `@name` and profile urls like `https://github.com/name/`
+fn strip_profile_url<'a>(value: &'a str, host: &str) -> &'a str {
+    let path = value
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.");
+    path.strip_prefix(host)
+        .map(|rest| rest.trim_start_matches('/'))
+        .unwrap_or(path)
+        .trim_start_matches('@')
+        .trim_end_matches('/')
+}
+
+fn is_username(name: &str, allowed: &[char]) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || allowed.contains(&c))
+}
+
+/// the local part of an email address without quotes, like `first.last+tag`
+fn is_dot_atom(local: &str) -> bool {
+    !local.is_empty()
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~.".contains(c))
+}
+
+/// percent encode the local part of an address so it can't add headers to a `mailto:` url
+fn encode_mailto(local: &str) -> String {
+    local
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~!$'*+".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect()
+}
+
+fn is_domain(domain: &str) -> bool {
+    domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+}
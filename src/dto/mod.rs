@@ -1,4 +1,18 @@
+pub mod about;
 pub mod achievement;
+pub mod export;
+pub mod follow;
 pub mod goal;
+pub mod impersonation;
+pub mod kudos;
+pub mod link;
+pub mod page;
+pub mod profile_change;
+pub mod profile_field;
+pub mod sandbox;
 pub mod service;
+pub mod service_data;
+pub mod tag;
+pub mod unlock;
 pub mod user;
+pub mod user_record;
@@ -0,0 +1,22 @@
+use crate::error::AppError;
+
+/// default amount of items per page
+static DEFAULT_PAGE_SIZE: u32 = 50;
+/// maximum amount of items per page
+static MAX_PAGE_SIZE: u32 = 100;
+
+/// offset and limit of a page, with their defaults
+pub fn page(offset: Option<u32>, limit: Option<u32>) -> Result<(u32, u32), AppError> {
+    Ok((offset.unwrap_or_default(), page_limit(limit)?))
+}
+
+/// limit of a page, with its default
+pub fn page_limit(limit: Option<u32>) -> Result<u32, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(AppError::PayloadError(format!(
+            "Limit must be between 1 and {MAX_PAGE_SIZE}"
+        )));
+    }
+    Ok(limit)
+}
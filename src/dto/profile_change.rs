@@ -0,0 +1,40 @@
+use database::{Database, models::profile_change::ProfileChange};
+use serde::{Deserialize, Serialize};
+
+use crate::{dto::page::page, error::AppError};
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct ProfileHistoryQuery {
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// a page of the changes to a profile, most recent first
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProfileHistoryPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub changes: Vec<ProfileChange>,
+}
+
+impl ProfileHistoryQuery {
+    pub async fn list(
+        self,
+        db: &Database,
+        user_id: u32,
+    ) -> Result<ProfileHistoryPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+
+        db.users().by_id(user_id).await?;
+        Ok(ProfileHistoryPayload {
+            total: db.profile_changes().count_for_user(user_id).await?,
+            offset,
+            limit,
+            changes: db
+                .profile_changes()
+                .for_user(user_id, limit, offset)
+                .await?,
+        })
+    }
+}
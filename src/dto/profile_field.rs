@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use database::{
+    Database,
+    models::{
+        profile_field::{
+            FieldKind, ProfileField, ProfileFieldCreate, ProfileFieldPatch, UserFieldValue,
+        },
+        user::Visibility,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::{link::normalize_url, tag::present},
+    error::AppError,
+};
+
+/// maximum length of a text value if the field doesn't set one
+static DEFAULT_MAX_LENGTH: u32 = 256;
+/// maximum length an admin can allow for a text field
+static MAX_TEXT_LENGTH: u32 = 4096;
+/// maximum length of a field name
+static MAX_NAME_LENGTH: usize = 32;
+/// maximum length of a url value
+static MAX_URL_LENGTH: usize = 256;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProfileFieldPayload {
+    pub id: u32,
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    /// allowed values of an enum field
+    pub options: Vec<String>,
+    pub max_length: Option<u32>,
+    pub visibility: Visibility,
+    pub position: u32,
+}
+
+impl TryFrom<ProfileField> for ProfileFieldPayload {
+    type Error = AppError;
+
+    fn try_from(field: ProfileField) -> Result<Self, Self::Error> {
+        Ok(Self {
+            options: serde_json::from_str(&field.options)
+                .map_err(|err| AppError::Internal(format!("invalid stored json: {err}")))?,
+            id: field.id,
+            name: field.name,
+            label: field.label,
+            kind: field.kind,
+            max_length: field.max_length,
+            visibility: field.visibility,
+            position: field.position,
+        })
+    }
+}
+
+impl ProfileFieldPayload {
+    pub async fn all(db: &Database) -> Result<Vec<Self>, AppError> {
+        db.profile_fields()
+            .all()
+            .await?
+            .into_iter()
+            .map(Self::try_from)
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileFieldCreatePayload {
+    /// key used in the api, lowercase letters, digits and underscores
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    /// allowed values, only for enum fields
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// only for text fields, defaults to 256
+    pub max_length: Option<u32>,
+    /// defaults to `members`
+    pub visibility: Option<Visibility>,
+    #[serde(default)]
+    pub position: u32,
+}
+
+impl ProfileFieldCreatePayload {
+    pub async fn create(self, db: &Database) -> Result<ProfileFieldPayload, AppError> {
+        check_field_name(&self.name)?;
+        check_label(&self.label)?;
+        check_options(self.kind, &self.options)?;
+        check_max_length(self.kind, self.max_length)?;
+        db.profile_fields()
+            .create(ProfileFieldCreate {
+                options: encode_options(&self.options)?,
+                name: self.name,
+                label: self.label,
+                kind: self.kind,
+                max_length: self.max_length,
+                visibility: self.visibility.unwrap_or(Visibility::Members),
+                position: self.position,
+            })
+            .await?
+            .try_into()
+    }
+}
+
+/// the name and kind of a field can't be changed, values of users depend on them
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ProfileFieldPatchPayload {
+    pub label: Option<String>,
+    /// replaces the allowed values of an enum field
+    ///
+    /// values users already picked are kept, even if they are no longer an option
+    pub options: Option<Vec<String>>,
+    /// missing leaves the maximum unchanged, `null` goes back to the default
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_length: Option<Option<u32>>,
+    pub visibility: Option<Visibility>,
+    pub position: Option<u32>,
+}
+
+impl ProfileFieldPatchPayload {
+    pub async fn patch(
+        self,
+        field_id: u32,
+        db: &Database,
+    ) -> Result<ProfileFieldPayload, AppError> {
+        let field = db.profile_fields().by_id(field_id).await?;
+        if let Some(label) = &self.label {
+            check_label(label)?;
+        }
+        if let Some(options) = &self.options {
+            check_options(field.kind, options)?;
+        }
+        if let Some(max_length) = self.max_length {
+            check_max_length(field.kind, max_length)?;
+        }
+        db.profile_fields()
+            .patch(
+                field_id,
+                ProfileFieldPatch {
+                    label: self.label,
+                    options: self.options.as_deref().map(encode_options).transpose()?,
+                    max_length: self.max_length,
+                    visibility: self.visibility,
+                    position: self.position,
+                },
+            )
+            .await?
+            .try_into()
+    }
+}
+
+/// value of a profile field as shown on a profile
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProfileFieldValuePayload {
+    pub name: String,
+    pub label: String,
+    pub kind: FieldKind,
+    pub value: String,
+}
+
+impl From<UserFieldValue> for ProfileFieldValuePayload {
+    fn from(value: UserFieldValue) -> Self {
+        Self {
+            name: value.name,
+            label: value.label,
+            kind: value.kind,
+            value: value.value,
+        }
+    }
+}
+
+/// validate and normalize the field values a user submitted against the fields admins defined
+///
+/// empty values are turned into `None`, which removes the value
+pub async fn check_field_values(
+    db: &Database,
+    values: BTreeMap<String, Option<String>>,
+) -> Result<BTreeMap<String, Option<String>>, AppError> {
+    let fields = ProfileFieldPayload::all(db).await?;
+
+    let mut checked = BTreeMap::new();
+    for (name, value) in values {
+        let field = fields
+            .iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| AppError::PayloadError(format!("Unknown profile field '{name}'")))?;
+        let value = match value.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(value) => Some(check_value(field, value)?),
+        };
+        checked.insert(name, value);
+    }
+    Ok(checked)
+}
+
+fn check_value(field: &ProfileFieldPayload, value: &str) -> Result<String, AppError> {
+    let invalid = |reason: &str| {
+        AppError::PayloadError(format!("Invalid value for '{}': {reason}", field.name))
+    };
+
+    match field.kind {
+        FieldKind::Text => {
+            let max_length = field.max_length.unwrap_or(DEFAULT_MAX_LENGTH) as usize;
+            if value.chars().count() > max_length {
+                return Err(invalid(&format!("at most {max_length} characters")));
+            }
+            if value.chars().any(|c| c.is_control() && c != '\n') {
+                return Err(invalid("contains control characters"));
+            }
+            Ok(value.to_string())
+        }
+        FieldKind::Enum => field
+            .options
+            .iter()
+            .find(|option| *option == value)
+            .cloned()
+            .ok_or_else(|| invalid(&format!("expected one of {:?}", field.options))),
+        FieldKind::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .map_err(|_| invalid("expected a date like 2025-09-22")),
+        FieldKind::Url => (value.len() <= MAX_URL_LENGTH)
+            .then(|| normalize_url(value))
+            .flatten()
+            .ok_or_else(|| invalid("expected a http(s) url")),
+    }
+}
+
+fn check_field_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err(AppError::PayloadError(format!(
+            "Invalid field name '{name}', use at most {MAX_NAME_LENGTH} lowercase letters, digits and underscores"
+        )));
+    }
+    Ok(())
+}
+
+fn check_label(label: &str) -> Result<(), AppError> {
+    if label.trim().is_empty() {
+        return Err(AppError::PayloadError("Label can't be empty".into()));
+    }
+    Ok(())
+}
+
+fn check_options(kind: FieldKind, options: &[String]) -> Result<(), AppError> {
+    match kind {
+        FieldKind::Enum if options.is_empty() => Err(AppError::PayloadError(
+            "An enum field needs at least one option".into(),
+        )),
+        FieldKind::Enum => {
+            for (i, option) in options.iter().enumerate() {
+                if option.trim().is_empty() || options.iter().take(i).any(|o| o == option) {
+                    return Err(AppError::PayloadError(format!(
+                        "Invalid or duplicate option '{option}'"
+                    )));
+                }
+            }
+            Ok(())
+        }
+        _ if !options.is_empty() => Err(AppError::PayloadError(
+            "Only enum fields have options".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn check_max_length(kind: FieldKind, max_length: Option<u32>) -> Result<(), AppError> {
+    match max_length {
+        Some(_) if kind != FieldKind::Text => Err(AppError::PayloadError(
+            "Only text fields have a maximum length".into(),
+        )),
+        Some(max) if max == 0 || max > MAX_TEXT_LENGTH => Err(AppError::PayloadError(format!(
+            "Maximum length must be between 1 and {MAX_TEXT_LENGTH}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn encode_options(options: &[String]) -> Result<String, AppError> {
+    serde_json::to_string(options).map_err(|err| AppError::Internal(err.to_string()))
+}
@@ -0,0 +1,96 @@
+use database::{
+    Database,
+    models::{achievement::Unlock, service::Namespace, service_data::ServiceData},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::{service_data::ServiceDataPayload, unlock::UnlockPayload},
+    error::AppError,
+    extractors::AuthenticatedUser,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SandboxDataPayload {
+    pub service_id: u32,
+    pub user_id: u32,
+    #[serde(flatten)]
+    pub data: ServiceDataPayload,
+}
+
+/// everything that was written with a test api key
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SandboxPayload {
+    pub unlocks: Vec<UnlockPayload>,
+    pub data: Vec<SandboxDataPayload>,
+}
+
+impl SandboxPayload {
+    /// sandbox of a service, only for its owner and admins
+    pub async fn for_service(
+        db: &Database,
+        user: &AuthenticatedUser,
+        service_id: u32,
+    ) -> Result<Self, AppError> {
+        check_owner(db, user, service_id).await?;
+
+        let unlocks = db
+            .unlocks(Namespace::Sandbox)
+            .for_service(service_id)
+            .await?;
+        let data = db
+            .service_data(Namespace::Sandbox)
+            .for_service(service_id)
+            .await?;
+        Self::from_rows(unlocks, data)
+    }
+
+    /// sandbox data of every service about a user
+    pub async fn for_user(db: &Database, user_id: u32) -> Result<Self, AppError> {
+        let unlocks = db.unlocks(Namespace::Sandbox).for_user(user_id).await?;
+        let data = db
+            .service_data(Namespace::Sandbox)
+            .for_user(user_id)
+            .await?;
+        Self::from_rows(unlocks, data)
+    }
+
+    /// delete the sandbox of a service, only for its owner and admins
+    pub async fn wipe(
+        db: &Database,
+        user: &AuthenticatedUser,
+        service_id: u32,
+    ) -> Result<(), AppError> {
+        check_owner(db, user, service_id).await?;
+        Ok(db.services().wipe_sandbox(service_id).await?)
+    }
+
+    fn from_rows(unlocks: Vec<Unlock>, data: Vec<ServiceData>) -> Result<Self, AppError> {
+        Ok(Self {
+            unlocks: unlocks.into_iter().map(UnlockPayload::from).collect(),
+            data: data
+                .into_iter()
+                .map(|row| {
+                    Ok(SandboxDataPayload {
+                        service_id: row.service_id,
+                        user_id: row.user_id,
+                        data: row.try_into()?,
+                    })
+                })
+                .collect::<Result<_, AppError>>()?,
+        })
+    }
+}
+
+async fn check_owner(
+    db: &Database,
+    user: &AuthenticatedUser,
+    service_id: u32,
+) -> Result<(), AppError> {
+    let service = db.services().by_id(service_id).await?;
+    if user.admin || service.owner_id == Some(user.id) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden)
+    }
+}
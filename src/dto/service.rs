@@ -1,16 +1,26 @@
+use chrono::{DateTime, Duration, Utc};
 use database::{
     Database,
-    models::service::{Service, ServiceCreate, ServicePatch},
+    models::{
+        service::{Namespace, Service, ServiceCreate, ServicePatch, ServiceRateLimitPatch},
+        service_usage::{EndpointUsage, HourlyUsage},
+    },
 };
 use serde::{Deserialize, Serialize};
 
-use crate::error::AppError;
+use crate::{
+    dto::tag::present,
+    error::AppError,
+    rate_limit::{RateLimit, RateLimitKey, RateLimiter},
+};
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct ServicePayloadAdmin {
     pub id: u32,
     pub name: String,
     pub api_key: String,
+    pub test_api_key: Option<String>,
+    pub owner_id: Option<u32>,
 }
 
 impl From<Service> for ServicePayloadAdmin {
@@ -19,6 +29,8 @@ impl From<Service> for ServicePayloadAdmin {
             id: value.id,
             name: value.name,
             api_key: value.api_key,
+            test_api_key: value.test_api_key,
+            owner_id: value.owner_id,
         }
     }
 }
@@ -40,6 +52,24 @@ impl ServicePayloadAdmin {
     ) -> Result<ServicePayloadAdmin, AppError> {
         Ok(db.services().regenerate_api_key(service_id).await?.into())
     }
+
+    pub async fn regenerate_test_api_key(
+        db: &Database,
+        service_id: u32,
+    ) -> Result<ServicePayloadAdmin, AppError> {
+        Ok(db
+            .services()
+            .regenerate_test_api_key(service_id)
+            .await?
+            .into())
+    }
+
+    pub async fn remove_test_api_key(
+        db: &Database,
+        service_id: u32,
+    ) -> Result<ServicePayloadAdmin, AppError> {
+        Ok(db.services().remove_test_api_key(service_id).await?.into())
+    }
 }
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct ServicePayloadUser {
@@ -89,6 +119,13 @@ impl From<ServiceCreatePayload> for ServiceCreate {
 #[derive(Serialize, Deserialize)]
 pub struct ServicePatchPayload {
     pub name: String,
+    /// user that develops this service, missing keeps the current owner, `null` removes it
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub owner_id: Option<Option<u32>>,
 }
 
 impl ServicePatchPayload {
@@ -104,6 +141,138 @@ impl ServicePatchPayload {
 
 impl From<ServicePatchPayload> for ServicePatch {
     fn from(value: ServicePatchPayload) -> Self {
-        Self { name: value.name }
+        Self {
+            name: value.name,
+            owner_id: value.owner_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServiceRateLimitPayload {
+    pub burst: u32,
+    pub per_minute: u32,
+    pub remaining: u32,
+    pub rejected: u64,
+}
+
+impl ServiceRateLimitPayload {
+    pub async fn get(
+        db: &Database,
+        limiter: &RateLimiter,
+        service_id: u32,
+    ) -> Result<Self, AppError> {
+        let service = db.services().by_id(service_id).await?;
+        Ok(Self::from_service(&service, limiter))
+    }
+
+    fn from_service(service: &Service, limiter: &RateLimiter) -> Self {
+        let limit = RateLimit::from(service);
+        let usage = limiter.usage(RateLimitKey::Service(service.id), limit);
+        Self {
+            burst: limit.burst,
+            per_minute: limit.per_minute,
+            remaining: usage.remaining,
+            rejected: usage.rejected,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ServiceRateLimitPatchPayload {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl ServiceRateLimitPatchPayload {
+    pub async fn patch(
+        self,
+        service_id: u32,
+        db: &Database,
+        limiter: &RateLimiter,
+    ) -> Result<ServiceRateLimitPayload, AppError> {
+        if self.burst == 0 {
+            return Err(AppError::PayloadError(
+                "Burst should allow at least 1 request".into(),
+            ));
+        }
+
+        let service = db
+            .services()
+            .set_rate_limit(service_id, self.into())
+            .await?;
+        Ok(ServiceRateLimitPayload::from_service(&service, limiter))
+    }
+}
+
+impl From<ServiceRateLimitPatchPayload> for ServiceRateLimitPatch {
+    fn from(value: ServiceRateLimitPatchPayload) -> Self {
+        Self {
+            burst: value.burst,
+            per_minute: value.per_minute,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceUsageQuery {
+    /// start of the range, defaults to a week before `to`
+    pub from: Option<DateTime<Utc>>,
+    /// end of the range, defaults to now
+    pub to: Option<DateTime<Utc>>,
+    /// requests made with the test api key are counted apart, defaults to production
+    pub namespace: Option<Namespace>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServiceUsagePayload {
+    pub namespace: Namespace,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub requests: u32,
+    pub errors: u32,
+    pub error_rate: f64,
+    /// last request of this service, also outside of the range
+    pub last_called_at: Option<DateTime<Utc>>,
+    pub endpoints: Vec<EndpointUsage>,
+    pub hours: Vec<HourlyUsage>,
+}
+
+impl ServiceUsagePayload {
+    pub async fn get(
+        db: &Database,
+        service_id: u32,
+        query: ServiceUsageQuery,
+    ) -> Result<Self, AppError> {
+        let namespace = query.namespace.unwrap_or(Namespace::Production);
+        let to = query.to.unwrap_or_else(Utc::now);
+        let from = query.from.unwrap_or(to - Duration::days(7));
+        if from >= to {
+            return Err(AppError::PayloadError("from should be before to".into()));
+        }
+
+        // check if the service exists
+        db.services().by_id(service_id).await?;
+
+        let usage = db.service_usage();
+        let hours = usage.hourly(service_id, namespace, from, to).await?;
+        let requests = hours.iter().map(|hour| hour.requests).sum();
+        let errors = hours.iter().map(|hour| hour.errors).sum();
+
+        Ok(Self {
+            namespace,
+            from,
+            to,
+            requests,
+            errors,
+            error_rate: if requests == 0 {
+                0.0
+            } else {
+                errors as f64 / requests as f64
+            },
+            last_called_at: usage.last_called_at(service_id, namespace).await?,
+            endpoints: usage.by_endpoint(service_id, namespace, from, to).await?,
+            hours,
+        })
     }
 }
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Local};
+use database::{
+    Database,
+    models::{
+        service::Namespace,
+        service_data::{ServiceData, ServiceDataPut},
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{dto::service::ServicePayloadUser, error::AppError};
+
+/// maximum length of a key
+static MAX_KEY_LENGTH: usize = 128;
+/// maximum size of a json encoded value in bytes
+static MAX_VALUE_SIZE: usize = 16 * 1024;
+/// maximum amount of keys a service can store per user
+static MAX_KEYS_PER_USER: u32 = 100;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServiceDataPayload {
+    pub key: String,
+    pub value: Value,
+    pub version: u32,
+    pub updated_at: DateTime<Local>,
+}
+
+impl TryFrom<ServiceData> for ServiceDataPayload {
+    type Error = AppError;
+
+    fn try_from(data: ServiceData) -> Result<Self, Self::Error> {
+        Ok(Self {
+            value: serde_json::from_str(&data.value)
+                .map_err(|err| AppError::Internal(format!("invalid stored json: {err}")))?,
+            key: data.key,
+            version: data.version,
+            updated_at: data.updated_at,
+        })
+    }
+}
+
+impl ServiceDataPayload {
+    pub async fn for_service_user(
+        db: &Database,
+        namespace: Namespace,
+        service_id: u32,
+        user_id: u32,
+    ) -> Result<Vec<Self>, AppError> {
+        db.users().by_id(user_id).await?;
+        db.service_data(namespace)
+            .for_service_user(service_id, user_id)
+            .await?
+            .into_iter()
+            .map(Self::try_from)
+            .collect()
+    }
+
+    pub async fn get(
+        db: &Database,
+        namespace: Namespace,
+        service_id: u32,
+        user_id: u32,
+        key: &str,
+    ) -> Result<Self, AppError> {
+        db.service_data(namespace)
+            .by_key(service_id, user_id, key)
+            .await?
+            .try_into()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceDataPutPayload {
+    pub value: Value,
+    /// the version this value replaces, leave empty to create a new key
+    pub version: Option<u32>,
+}
+
+impl ServiceDataPutPayload {
+    pub async fn put(
+        self,
+        db: &Database,
+        namespace: Namespace,
+        service_id: u32,
+        user_id: u32,
+        key: String,
+    ) -> Result<ServiceDataPayload, AppError> {
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return Err(AppError::PayloadError(format!(
+                "Key should be between 1 and {MAX_KEY_LENGTH} bytes"
+            )));
+        }
+
+        let value = serde_json::to_string(&self.value)
+            .map_err(|err| AppError::PayloadError(err.to_string()))?;
+        if value.len() > MAX_VALUE_SIZE {
+            return Err(AppError::PayloadError(format!(
+                "Value should be at most {MAX_VALUE_SIZE} bytes"
+            )));
+        }
+
+        db.users().by_id(user_id).await?;
+        if self.version.is_none()
+            && db
+                .service_data(namespace)
+                .count_for_service_user(service_id, user_id)
+                .await?
+                >= MAX_KEYS_PER_USER
+        {
+            return Err(AppError::PayloadError(format!(
+                "A service can store at most {MAX_KEYS_PER_USER} keys per user"
+            )));
+        }
+
+        db.service_data(namespace)
+            .put(
+                service_id,
+                user_id,
+                ServiceDataPut {
+                    key,
+                    value,
+                    version: self.version,
+                    max_keys: MAX_KEYS_PER_USER,
+                },
+            )
+            .await?
+            .try_into()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceDataDeleteQuery {
+    pub version: Option<u32>,
+}
+
+/// everything a single service stores about a user
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServiceDataUserPayload {
+    pub service: ServicePayloadUser,
+    pub data: Vec<ServiceDataPayload>,
+}
+
+impl ServiceDataUserPayload {
+    pub async fn for_user(
+        db: &Database,
+        namespace: Namespace,
+        user_id: u32,
+    ) -> Result<Vec<Self>, AppError> {
+        let services = db.services().all().await?;
+        let mut grouped: Vec<Self> = Vec::new();
+
+        for row in db.service_data(namespace).for_user(user_id).await? {
+            let service_id = row.service_id;
+            let payload = ServiceDataPayload::try_from(row)?;
+            match grouped.last_mut() {
+                Some(last) if last.service.id == service_id => last.data.push(payload),
+                _ => {
+                    let service = services
+                        .iter()
+                        .find(|service| service.id == service_id)
+                        .ok_or(AppError::NotFound)?;
+                    grouped.push(Self {
+                        service: ServicePayloadUser {
+                            id: service.id,
+                            name: service.name.clone(),
+                        },
+                        data: vec![payload],
+                    });
+                }
+            }
+        }
+
+        Ok(grouped)
+    }
+}
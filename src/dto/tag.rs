@@ -0,0 +1,434 @@
+use chrono::{DateTime, Utc};
+use database::{
+    Database,
+    error::DatabaseError,
+    models::{
+        profile_change::Actor,
+        service::Namespace,
+        tag::{
+            Tag, TagAssignment, TagAssignmentCreate, TagAssignmentPatch, TagCategory,
+            TagCategoryCreate, TagCategoryPatch, TagCreate, TagPatch, TagPolicy,
+        },
+    },
+};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    config::AppConfig,
+    dto::{
+        page::page,
+        user::{UserProfile, Viewer},
+    },
+    error::AppError,
+    extractors::AuthenticatedService,
+};
+
+/// color of new categories
+static DEFAULT_COLOR: &str = "#A0A0A0";
+
+#[derive(Serialize, Deserialize)]
+pub struct TagCreatePayload {
+    pub name: String,
+    pub description: Option<String>,
+    pub category_id: u32,
+    /// css hex color like `#FF8000`, uses the color of the category if missing
+    pub color: Option<String>,
+}
+
+impl TagCreatePayload {
+    pub async fn create(mut self, db: &Database) -> Result<Tag, AppError> {
+        check_name(&self.name)?;
+        self.color = self.color.as_deref().map(check_color).transpose()?;
+        Ok(db.tags().create(self.into()).await?)
+    }
+}
+
+impl From<TagCreatePayload> for TagCreate {
+    fn from(value: TagCreatePayload) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            category_id: value.category_id,
+            color: value.color,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct TagPatchPayload {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub category_id: Option<u32>,
+    /// missing leaves the color unchanged, `null` uses the color of the category
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color: Option<Option<String>>,
+}
+
+impl TagPatchPayload {
+    pub async fn patch(mut self, tag_id: u32, db: &Database) -> Result<Tag, AppError> {
+        if let Some(name) = &self.name {
+            check_name(name)?;
+        }
+        if let Some(Some(color)) = &self.color {
+            self.color = Some(Some(check_color(color)?));
+        }
+        Ok(db.tags().patch(tag_id, self.into()).await?)
+    }
+}
+
+impl From<TagPatchPayload> for TagPatch {
+    fn from(value: TagPatchPayload) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            category_id: value.category_id,
+            color: value.color,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagCategoryCreatePayload {
+    pub name: String,
+    pub description: Option<String>,
+    /// defaults to `admin_only`
+    #[serde(default)]
+    pub policy: TagPolicy,
+    pub max_per_user: Option<u32>,
+    /// the service that can hand out the tags, for a `service_assignable` category
+    pub service_id: Option<u32>,
+    /// css hex color like `#FF8000`, defaults to grey
+    pub color: Option<String>,
+}
+
+impl TagCategoryCreatePayload {
+    pub async fn create(mut self, db: &Database) -> Result<TagCategory, AppError> {
+        check_name(&self.name)?;
+        if let Some(service_id) = self.service_id {
+            check_service(db, service_id).await?;
+        }
+        self.color = self.color.as_deref().map(check_color).transpose()?;
+        Ok(db.tags().create_category(self.into()).await?)
+    }
+}
+
+impl From<TagCategoryCreatePayload> for TagCategoryCreate {
+    fn from(value: TagCategoryCreatePayload) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            policy: value.policy,
+            max_per_user: value.max_per_user,
+            service_id: value.service_id,
+            color: value.color.unwrap_or_else(|| DEFAULT_COLOR.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct TagCategoryPatchPayload {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub policy: Option<TagPolicy>,
+    /// missing leaves the maximum unchanged, `null` removes it
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_per_user: Option<Option<u32>>,
+    /// missing leaves the service unchanged, `null` removes it
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub service_id: Option<Option<u32>>,
+    /// missing leaves the color unchanged, `null` goes back to grey
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color: Option<Option<String>>,
+}
+
+impl TagCategoryPatchPayload {
+    pub async fn patch(mut self, category_id: u32, db: &Database) -> Result<TagCategory, AppError> {
+        if let Some(name) = &self.name {
+            check_name(name)?;
+        }
+        if let Some(Some(service_id)) = self.service_id {
+            check_service(db, service_id).await?;
+        }
+        if let Some(Some(color)) = &self.color {
+            self.color = Some(Some(check_color(color)?));
+        }
+        Ok(db.tags().patch_category(category_id, self.into()).await?)
+    }
+}
+
+impl From<TagCategoryPatchPayload> for TagCategoryPatch {
+    fn from(value: TagCategoryPatchPayload) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            policy: value.policy,
+            max_per_user: value.max_per_user,
+            service_id: value.service_id,
+            color: value
+                .color
+                .map(|color| color.unwrap_or_else(|| DEFAULT_COLOR.to_string())),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TagCategoryDeleteQuery {
+    /// also delete all tags in the category
+    #[serde(default)]
+    pub cascade: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct TagMembersQuery {
+    /// amount of members to skip
+    pub offset: Option<u32>,
+    /// amount of members to return, at most 100
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct TagMembersPayload {
+    pub tag: Tag,
+    /// total amount of members, not only the ones on this page
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub users: Vec<UserProfile>,
+}
+
+impl TagMembersPayload {
+    pub async fn get(
+        db: &Database,
+        name: &str,
+        query: TagMembersQuery,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<Self, AppError> {
+        let (offset, limit) = page(query.offset, query.limit)?;
+
+        let tag = db.tags().by_name(name).await?;
+        let total = db.tags().count_members(tag.id).await?;
+
+        let members = db.tags().members(tag.id, limit, offset).await?;
+        let users = UserProfile::from_users(db, members, viewer, config).await?;
+
+        Ok(Self {
+            tag,
+            total,
+            offset,
+            limit,
+            users,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagAssignmentCreatePayload {
+    pub tag_id: u32,
+    /// `None` if it's unknown since when the user has the tag
+    pub valid_from: Option<DateTime<Utc>>,
+    /// `None` if the user keeps the tag until further notice
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+impl TagAssignmentCreatePayload {
+    pub async fn create(
+        self,
+        user_id: u32,
+        db: &Database,
+        actor: Actor,
+    ) -> Result<TagAssignment, AppError> {
+        check_period(self.valid_from, self.valid_until)?;
+        db.users().by_id(user_id).await?;
+        Ok(db
+            .tags()
+            .create_assignment(user_id, self.into(), actor)
+            .await?)
+    }
+}
+
+impl From<TagAssignmentCreatePayload> for TagAssignmentCreate {
+    fn from(value: TagAssignmentCreatePayload) -> Self {
+        Self {
+            tag_id: value.tag_id,
+            valid_from: value.valid_from,
+            valid_until: value.valid_until,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct TagAssignmentPatchPayload {
+    /// missing leaves the start unchanged, `null` clears it
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_from: Option<Option<DateTime<Utc>>>,
+    /// missing leaves the end unchanged, `null` clears it
+    #[serde(
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_until: Option<Option<DateTime<Utc>>>,
+}
+
+impl TagAssignmentPatchPayload {
+    pub async fn patch(
+        self,
+        assignment_id: u32,
+        db: &Database,
+        actor: Actor,
+    ) -> Result<TagAssignment, AppError> {
+        let current = db.tags().assignment_by_id(assignment_id).await?;
+        check_period(
+            self.valid_from.unwrap_or(current.valid_from),
+            self.valid_until.unwrap_or(current.valid_until),
+        )?;
+        Ok(db
+            .tags()
+            .patch_assignment(assignment_id, self.into(), actor)
+            .await?)
+    }
+}
+
+impl From<TagAssignmentPatchPayload> for TagAssignmentPatch {
+    fn from(value: TagAssignmentPatchPayload) -> Self {
+        Self {
+            valid_from: value.valid_from,
+            valid_until: value.valid_until,
+        }
+    }
+}
+
+/// give a user a tag on behalf of someone who may only hand out tags with `policy`
+///
+/// admins don't go through this and can assign any tag
+pub async fn assign_with_policy(
+    db: &Database,
+    user_id: u32,
+    tag_id: u32,
+    policy: TagPolicy,
+    actor: Actor,
+) -> Result<(), AppError> {
+    let category = db.tags().category_for_tag(tag_id).await?;
+    if category.policy != policy {
+        return Err(AppError::Forbidden);
+    }
+    match category.max_per_user {
+        Some(max) => {
+            db.tags()
+                .assign_limited(user_id, tag_id, max, actor)
+                .await?
+        }
+        None => db.tags().assign(user_id, tag_id, actor).await?,
+    }
+    Ok(())
+}
+
+/// services only manage the tags of categories they were given, and not with a test api key
+pub async fn check_service_tag(
+    db: &Database,
+    service: &AuthenticatedService,
+    tag_id: u32,
+) -> Result<(), AppError> {
+    if service.namespace == Namespace::Sandbox {
+        return Err(AppError::Forbidden);
+    }
+    let category = db.tags().category_for_tag(tag_id).await?;
+    if category.service_id != Some(service.service.id) {
+        return Err(AppError::Forbidden);
+    }
+    Ok(())
+}
+
+/// remove a tag from a user on behalf of someone who may only manage tags with `policy`
+pub async fn unassign_with_policy(
+    db: &Database,
+    user_id: u32,
+    tag_id: u32,
+    policy: TagPolicy,
+    actor: Actor,
+) -> Result<(), AppError> {
+    let category = db.tags().category_for_tag(tag_id).await?;
+    if category.policy != policy {
+        return Err(AppError::Forbidden);
+    }
+    match policy {
+        // members can undo tags they picked themselves, other assignments are kept as history
+        TagPolicy::SelfAssignable => db.tags().remove(user_id, tag_id, actor).await?,
+        TagPolicy::AdminOnly | TagPolicy::ServiceAssignable => {
+            db.tags().unassign(user_id, tag_id, actor).await?
+        }
+    }
+    Ok(())
+}
+
+/// distinguishes a field set to `null` from a missing field
+pub(crate) fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+async fn check_service(db: &Database, service_id: u32) -> Result<(), AppError> {
+    match db.services().by_id(service_id).await {
+        Err(DatabaseError::NotFound) => Err(AppError::PayloadError(format!(
+            "Unknown service {service_id}"
+        ))),
+        result => result.map(|_| ()).map_err(AppError::from),
+    }
+}
+
+fn check_period(
+    valid_from: Option<DateTime<Utc>>,
+    valid_until: Option<DateTime<Utc>>,
+) -> Result<(), AppError> {
+    if let (Some(from), Some(until)) = (valid_from, valid_until)
+        && until < from
+    {
+        return Err(AppError::PayloadError(
+            "valid_until can't be before valid_from".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// check that a color is a css hex color like `#FF8000`, returns it in uppercase
+fn check_color(color: &str) -> Result<String, AppError> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(color.to_ascii_uppercase())
+        }
+        _ => Err(AppError::PayloadError(format!(
+            "Invalid color '{color}', expected a hex color like #FF8000"
+        ))),
+    }
+}
+
+fn check_name(name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::PayloadError("Name can't be empty".into()));
+    }
+    Ok(())
+}
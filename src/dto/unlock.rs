@@ -0,0 +1,69 @@
+use chrono::{DateTime, Local};
+use database::{
+    Database,
+    models::{achievement::Unlock, service::Namespace},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UnlockPayload {
+    pub user_id: u32,
+    pub service_id: u32,
+    pub goal_id: u32,
+    pub unlocked_at: DateTime<Local>,
+}
+
+impl From<Unlock> for UnlockPayload {
+    fn from(value: Unlock) -> Self {
+        Self {
+            user_id: value.user_id,
+            service_id: value.service_id,
+            goal_id: value.goal_id,
+            unlocked_at: value.time,
+        }
+    }
+}
+
+impl UnlockPayload {
+    /// the unlocks a service gave a user, the service wrote them so it sees them whatever the
+    /// user hides
+    pub async fn for_service_user(
+        db: &Database,
+        namespace: Namespace,
+        service_id: u32,
+        user_id: u32,
+    ) -> Result<Vec<Self>, AppError> {
+        db.users().by_id(user_id).await?;
+        Ok(db
+            .unlocks(namespace)
+            .for_service_user(service_id, user_id)
+            .await?
+            .into_iter()
+            .map(Self::from)
+            .collect())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnlockCreatePayload {
+    pub goal_id: u32,
+}
+
+impl UnlockCreatePayload {
+    pub async fn create(
+        self,
+        db: &Database,
+        namespace: Namespace,
+        service_id: u32,
+        user_id: u32,
+    ) -> Result<UnlockPayload, AppError> {
+        db.users().by_id(user_id).await?;
+        Ok(db
+            .unlocks(namespace)
+            .create(service_id, user_id, self.goal_id)
+            .await?
+            .into())
+    }
+}
@@ -1,18 +1,79 @@
+use std::collections::{BTreeMap, HashMap};
+
 use database::{
     Database,
     error::DatabaseError,
-    models::{tag::Tag, user::UserPatch},
+    models::{
+        follow::FollowCounts,
+        kudos::KudosScope,
+        profile_change::Actor,
+        tag::UserTag,
+        user::{
+            FieldVisibility, OfUser, SocialLink, User, UserFilter, UserPatch, UserSort, Visibility,
+        },
+    },
 };
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{
+    config::AppConfig,
+    dto::{
+        about::{check_about, render_abouts},
+        kudos::KudosPayload,
+        link::{SocialLinkPayload, normalize_links},
+        page::page_limit,
+        profile_field::{ProfileFieldValuePayload, check_field_values},
+    },
+    error::AppError,
+    extractors::AuthenticatedUser,
+};
+
+/// maximum amount of users that can be looked up in one request
+static MAX_LOOKUP_BATCH: usize = 100;
+/// maximum amount of words in a search query
+static MAX_SEARCH_TERMS: usize = 8;
+/// amount of kudos shown on a profile
+static RECENT_KUDOS: u32 = 5;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
 pub struct UserPatchPayload {
-    pub about: String,
+    pub about: Option<String>,
+    pub visibility: Option<Visibility>,
+    /// replaces all overrides of the visibility for parts of the profile
+    pub field_visibility: Option<FieldVisibility>,
+    /// replaces all links, in the order they should be shown
+    pub links: Option<Vec<SocialLink>>,
+    /// values of custom profile fields by name, `null` or an empty string removes a value
+    pub fields: Option<BTreeMap<String, Option<String>>>,
+}
+
+impl UserPatchPayload {
+    pub async fn patch(
+        mut self,
+        user_id: u32,
+        db: &Database,
+        actor: Actor,
+    ) -> Result<User, AppError> {
+        if let Some(about) = &self.about {
+            check_about(about)?;
+        }
+        self.links = self.links.map(normalize_links).transpose()?;
+        if let Some(fields) = self.fields {
+            self.fields = Some(check_field_values(db, fields).await?);
+        }
+        Ok(db.users().patch(user_id, self.into(), actor).await?)
+    }
 }
 
 impl From<UserPatchPayload> for UserPatch {
     fn from(value: UserPatchPayload) -> Self {
-        UserPatch { about: value.about }
+        UserPatch {
+            about: value.about,
+            visibility: value.visibility,
+            field_visibility: value.field_visibility,
+            links: value.links,
+            fields: value.fields,
+        }
     }
 }
 
@@ -20,8 +81,25 @@ impl From<UserPatchPayload> for UserPatch {
 pub struct UserProfile {
     pub id: u32,
     pub username: String,
+    /// markdown source, empty if the viewer is not allowed to see it
     pub about: String,
-    pub tags: Vec<Tag>,
+    /// sanitized html of the about, with mentions linking to the profiles
+    pub about_html: String,
+    /// tags the user holds right now, empty if the viewer is not allowed to see them
+    pub tags: Vec<UserTag>,
+    /// tags the user held in the past, most recent first
+    pub former_tags: Vec<UserTag>,
+    pub links: Vec<SocialLinkPayload>,
+    /// custom profile fields the viewer is allowed to see
+    pub fields: Vec<ProfileFieldValuePayload>,
+    /// amount of users following this user, private profiles are not counted
+    pub followers: u32,
+    /// amount of users this user follows, private profiles are not counted
+    pub following: u32,
+    /// amount of kudos the user got, without hidden or removed ones
+    pub kudos: u32,
+    /// the most recent kudos the user got, most recent first
+    pub recent_kudos: Vec<KudosPayload>,
 }
 
 pub enum UserId {
@@ -44,19 +122,472 @@ impl From<u32> for UserId {
     }
 }
 
-impl UserProfile {
-    pub async fn get(db: &Database, user_id: UserId) -> Result<UserProfile, DatabaseError> {
-        let user = match user_id {
-            UserId::Username(username) => db.users().by_username(username).await?,
+/// who is requesting a profile
+pub enum Viewer {
+    Member {
+        id: u32,
+        admin: bool,
+    },
+    Service,
+    /// someone who is not logged in
+    Anonymous,
+}
+
+impl From<Option<AuthenticatedUser>> for Viewer {
+    fn from(value: Option<AuthenticatedUser>) -> Self {
+        value.as_ref().map_or(Self::Anonymous, Self::from)
+    }
+}
+
+impl From<&AuthenticatedUser> for Viewer {
+    fn from(value: &AuthenticatedUser) -> Self {
+        Self::Member {
+            id: value.id,
+            admin: value.admin,
+        }
+    }
+}
+
+impl Viewer {
+    pub(crate) fn can_see(&self, user: &User) -> bool {
+        self.can_see_level(user.id, user.visibility)
+    }
+
+    /// whether a part of the profile with this override is shown to the viewer
+    pub fn can_see_part(&self, user: &User, part: Option<Visibility>) -> bool {
+        self.can_see_level(user.id, user.visibility_of(part))
+    }
+
+    /// whether the value of a custom profile field is shown to the viewer
+    ///
+    /// services only get the values shown to everyone
+    pub fn can_see_field(&self, user: &User, visibility: Visibility) -> bool {
+        match self {
+            Self::Service => Self::Anonymous.can_see_part(user, Some(visibility)),
+            _ => self.can_see_part(user, Some(visibility)),
+        }
+    }
+
+    /// which of the kudos a user got are shown to the viewer
+    ///
+    /// kudos from profiles only shown to members need a login
+    pub fn kudos_scope(&self, hidden: bool) -> KudosScope {
+        KudosScope {
+            hidden,
+            members_only_givers: matches!(self, Self::Member { .. } | Self::Service),
+        }
+    }
+
+    /// whether something of `owner_id` with this visibility is shown to the viewer
+    fn can_see_level(&self, owner_id: u32, visibility: Visibility) -> bool {
+        match (visibility, self) {
+            (Visibility::Public, _) => true,
+            (Visibility::Members, Self::Member { .. } | Self::Service) => true,
+            (Visibility::Private, Self::Member { id, admin }) => *admin || *id == owner_id,
+            (Visibility::Members | Visibility::Private, _) => false,
+        }
+    }
+}
+
+impl UserId {
+    /// the user, users the viewer can't see are not found
+    ///
+    /// a username that isn't in use anymore gives the user who had it last
+    pub async fn visible_user(self, db: &Database, viewer: &Viewer) -> Result<User, DatabaseError> {
+        let user = match self {
+            // links to old usernames keep working after a rename
+            UserId::Username(username) => match db.users().by_username(username.clone()).await {
+                Err(DatabaseError::NotFound) => db.users().by_former_username(username).await?,
+                user => user?,
+            },
             UserId::Id(id) => db.users().by_id(id).await?,
         };
-        let tags = db.tags().for_user(user.id).await?;
+        if !viewer.can_see(&user) {
+            return Err(DatabaseError::NotFound);
+        }
+        Ok(user)
+    }
+}
 
-        Ok(UserProfile {
-            id: user.id,
-            username: user.username,
-            about: user.about,
-            tags,
+impl UserProfile {
+    /// get a profile, profiles the viewer can't see are not found
+    ///
+    /// a username that isn't in use anymore gives the profile of the user who had it last
+    pub async fn get(
+        db: &Database,
+        user_id: UserId,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<UserProfile, DatabaseError> {
+        let user = user_id.visible_user(db, viewer).await?;
+        Self::from_user(db, user, viewer, config).await
+    }
+
+    /// build the profile of a user the viewer is already known to be allowed to see
+    pub async fn from_user(
+        db: &Database,
+        user: User,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<UserProfile, DatabaseError> {
+        Self::from_users(db, vec![user], viewer, config)
+            .await?
+            .pop()
+            .ok_or(DatabaseError::NotFound)
+    }
+
+    /// build the profiles of users the viewer is already known to be allowed to see
+    ///
+    /// every part of the profiles is looked up with one query for all users
+    pub async fn from_users(
+        db: &Database,
+        users: Vec<User>,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<Vec<UserProfile>, DatabaseError> {
+        let ids: Vec<u32> = users.iter().map(|user| user.id).collect();
+        let tag_ids: Vec<u32> = users
+            .iter()
+            .filter(|user| viewer.can_see_part(user, user.field_visibility.tags))
+            .map(|user| user.id)
+            .collect();
+        let mut tags = by_user(db.tags().for_users(&tag_ids).await?);
+        let mut former_tags = by_user(db.tags().former_for_users(&tag_ids).await?);
+        let abouts: Vec<String> = users
+            .iter()
+            .map(|user| {
+                if viewer.can_see_part(user, user.field_visibility.about) {
+                    user.about.clone()
+                } else {
+                    String::new()
+                }
+            })
+            .collect();
+        let abouts_html = render_abouts(db, &abouts, viewer, &config.frontend_url).await?;
+        let mut links = by_user(db.users().links_for_users(&ids).await?);
+        let mut fields = by_user(db.profile_fields().for_users(&ids).await?);
+        let follow_counts: HashMap<u32, FollowCounts> = db
+            .follows()
+            .counts_for_users(&ids)
+            .await?
+            .into_iter()
+            .map(|counts| (counts.user_id, counts.item))
+            .collect();
+        let kudos_scope = viewer.kudos_scope(false);
+        let kudos: HashMap<u32, u32> = db
+            .kudos()
+            .count_for_recipients(&ids, &kudos_scope)
+            .await?
+            .into_iter()
+            .collect();
+        let mut recent_kudos: HashMap<u32, Vec<KudosPayload>> = HashMap::new();
+        for kudos in db
+            .kudos()
+            .recent_for_recipients(&ids, &kudos_scope, RECENT_KUDOS)
+            .await?
+        {
+            recent_kudos
+                .entry(kudos.recipient_id)
+                .or_default()
+                .push(KudosPayload::from(kudos));
+        }
+
+        Ok(users
+            .into_iter()
+            .zip(abouts.into_iter().zip(abouts_html))
+            .map(|(user, (about, about_html))| {
+                let follow_counts = follow_counts.get(&user.id).copied().unwrap_or_default();
+                UserProfile {
+                    fields: fields
+                        .remove(&user.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|field| viewer.can_see_field(&user, field.visibility))
+                        .map(ProfileFieldValuePayload::from)
+                        .collect(),
+                    id: user.id,
+                    about,
+                    about_html,
+                    tags: tags.remove(&user.id).unwrap_or_default(),
+                    former_tags: former_tags.remove(&user.id).unwrap_or_default(),
+                    links: links
+                        .remove(&user.id)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(SocialLinkPayload::from)
+                        .collect(),
+                    followers: follow_counts.followers,
+                    following: follow_counts.following,
+                    kudos: kudos.get(&user.id).copied().unwrap_or_default(),
+                    recent_kudos: recent_kudos.remove(&user.id).unwrap_or_default(),
+                    username: user.username,
+                }
+            })
+            .collect())
+    }
+
+    /// get multiple profiles at once, skipping the ones that are not found
+    pub async fn get_many(
+        db: &Database,
+        user_ids: Vec<UserId>,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<Vec<UserProfile>, DatabaseError> {
+        let mut ids = Vec::new();
+        let mut usernames = Vec::new();
+        for user_id in &user_ids {
+            match user_id {
+                UserId::Id(id) => ids.push(*id),
+                UserId::Username(username) => usernames.push(username.clone()),
+            }
+        }
+        let by_name: HashMap<String, User> = db
+            .users()
+            .by_usernames(&usernames)
+            .await?
+            .into_iter()
+            .map(|found| (found.name, found.user))
+            .collect();
+        let by_id: HashMap<u32, User> = db
+            .users()
+            .by_ids(&ids)
+            .await?
+            .into_iter()
+            .map(|user| (user.id, user))
+            .collect();
+
+        let mut users: Vec<User> = Vec::new();
+        for user_id in user_ids {
+            let user = match user_id {
+                UserId::Id(id) => by_id.get(&id),
+                UserId::Username(username) => by_name.get(&username),
+            };
+            if let Some(user) = user
+                && viewer.can_see(user)
+                && users.iter().all(|u| u.id != user.id)
+            {
+                users.push(user.clone());
+            }
+        }
+
+        Self::from_users(db, users, viewer, config).await
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserLookupQuery {
+    /// comma separated zauth ids
+    pub ids: Option<String>,
+    /// comma separated usernames
+    pub usernames: Option<String>,
+}
+
+impl UserLookupQuery {
+    pub fn into_user_ids(self) -> Result<Vec<UserId>, AppError> {
+        let mut user_ids = Vec::new();
+
+        for id in split_list(self.ids.as_deref()) {
+            match UserId::from(id.to_string()) {
+                UserId::Id(id) => user_ids.push(UserId::Id(id)),
+                UserId::Username(_) => {
+                    return Err(AppError::PayloadError(format!("Invalid user id '{id}'")));
+                }
+            }
+        }
+
+        user_ids.extend(
+            split_list(self.usernames.as_deref()).map(|name| UserId::Username(name.to_string())),
+        );
+
+        if user_ids.len() > MAX_LOOKUP_BATCH {
+            return Err(AppError::PayloadError(format!(
+                "Can look up at most {MAX_LOOKUP_BATCH} users at once"
+            )));
+        }
+
+        Ok(user_ids)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct UserListQuery {
+    /// words to search for in the username, about and tag names, the last word may be incomplete
+    pub q: Option<String>,
+    /// order of the users, with `q` the best matches come first unless this is set
+    pub sort: Option<UserSort>,
+    /// only users who currently hold the tag with this name
+    pub tag: Option<String>,
+    /// only users who unlocked a goal of this achievement
+    pub achievement: Option<u32>,
+    /// only users with or without a profile image
+    pub has_image: Option<bool>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    /// amount of users per page, at most 100
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct UserListPayload {
+    pub users: Vec<UserProfile>,
+    /// pass this as `cursor` to get the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// where a page of users starts
+#[derive(Debug, PartialEq)]
+enum Cursor {
+    /// after the user with this sort key and id
+    After(UserSort, String, u32),
+    /// search results are ranked, so they can only be skipped
+    Offset(u32),
+}
+
+impl UserListQuery {
+    /// list profiles, filtered, sorted and one page at a time
+    pub async fn list(
+        self,
+        db: &Database,
+        viewer: &Viewer,
+        config: &AppConfig,
+    ) -> Result<UserListPayload, AppError> {
+        let limit = page_limit(self.limit)?;
+        let cursor = self.cursor.as_deref().map(Cursor::decode).transpose()?;
+        let filter = UserFilter {
+            search: self.match_query()?,
+            tag: self.tag,
+            achievement_id: self.achievement,
+            has_image: self.has_image,
+        };
+
+        // one extra user to know if there is a next page
+        let (users, next_cursor) = match (&filter.search, self.sort) {
+            (Some(match_query), None) => {
+                let offset = match cursor {
+                    None => 0,
+                    Some(Cursor::Offset(offset)) => offset,
+                    Some(Cursor::After(..)) => return Err(Cursor::mismatch()),
+                };
+                let mut users = db
+                    .users()
+                    .search(match_query, &filter, limit + 1, offset)
+                    .await?;
+                let next =
+                    (users.len() > limit as usize).then(|| Cursor::Offset(offset + limit).encode());
+                users.truncate(limit as usize);
+                (users, next)
+            }
+            (_, sort) => {
+                let sort = sort.unwrap_or_default();
+                let after = match cursor {
+                    None => None,
+                    Some(Cursor::After(cursor_sort, key, id)) if cursor_sort == sort => {
+                        Some((key, id))
+                    }
+                    Some(_) => return Err(Cursor::mismatch()),
+                };
+                let mut entries = db.users().list(sort, &filter, after, limit + 1).await?;
+                let has_next = entries.len() > limit as usize;
+                entries.truncate(limit as usize);
+                let next = entries.last().filter(|_| has_next).map(|entry| {
+                    Cursor::After(sort, entry.sort_key.clone(), entry.user.id).encode()
+                });
+                (entries.into_iter().map(|entry| entry.user).collect(), next)
+            }
+        };
+
+        Ok(UserListPayload {
+            users: UserProfile::from_users(db, users, viewer, config).await?,
+            next_cursor,
         })
     }
+
+    /// turn the query into an fts5 expression where every word is a prefix that has to match
+    ///
+    /// only letters and digits are kept, so users can't write fts5 syntax themselves
+    fn match_query(&self) -> Result<Option<String>, AppError> {
+        let terms: Vec<String> = self
+            .q
+            .as_deref()
+            .unwrap_or_default()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|term| !term.is_empty())
+            .map(|term| format!("\"{term}\"*"))
+            .collect();
+        if terms.len() > MAX_SEARCH_TERMS {
+            return Err(AppError::PayloadError(format!(
+                "Can search for at most {MAX_SEARCH_TERMS} words"
+            )));
+        }
+        Ok((!terms.is_empty()).then(|| terms.join(" ")))
+    }
+}
+
+impl Cursor {
+    /// hex encoded so it can be put in a url as is
+    fn encode(&self) -> String {
+        let raw = match self {
+            Self::After(sort, key, id) => format!("{}\n{id}\n{key}", sort_name(*sort)),
+            Self::Offset(offset) => format!("relevance\n{offset}"),
+        };
+        raw.bytes().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn decode(cursor: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::PayloadError("Invalid cursor".into());
+
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| {
+                cursor
+                    .get(i..i + 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        let mut parts = raw.splitn(3, '\n');
+        let (Some(sort), Some(position)) = (parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let position = position.parse::<u32>().map_err(|_| invalid())?;
+        let sort = match sort {
+            "relevance" => return Ok(Self::Offset(position)),
+            "username" => UserSort::Username,
+            "joined" => UserSort::Joined,
+            "active" => UserSort::Active,
+            _ => return Err(invalid()),
+        };
+        let key = parts.next().ok_or_else(invalid)?;
+        Ok(Self::After(sort, key.to_string(), position))
+    }
+
+    fn mismatch() -> AppError {
+        AppError::PayloadError("Cursor belongs to a different sort".into())
+    }
+}
+
+fn sort_name(sort: UserSort) -> &'static str {
+    match sort {
+        UserSort::Username => "username",
+        UserSort::Joined => "joined",
+        UserSort::Active => "active",
+    }
+}
+
+fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// rows of several users grouped per user, in the order they came in
+fn by_user<T>(rows: Vec<OfUser<T>>) -> HashMap<u32, Vec<T>> {
+    let mut grouped: HashMap<u32, Vec<T>> = HashMap::new();
+    for row in rows {
+        grouped.entry(row.user_id).or_default().push(row.item);
+    }
+    grouped
 }
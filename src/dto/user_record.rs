@@ -0,0 +1,117 @@
+use database::{
+    Database,
+    models::{
+        profile_change::Actor,
+        user::{FormerUsername, UserModeration, UserRecord, UserRecordFilter, UserSession},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::{about::check_about, page::page},
+    error::AppError,
+};
+
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct UserRecordQuery {
+    /// part of the username, or the exact id
+    pub q: Option<String>,
+    pub disabled: Option<bool>,
+    pub offset: Option<u32>,
+    pub limit: Option<u32>,
+}
+
+/// a page of all users, for admins
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserRecordListPayload {
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+    pub users: Vec<UserRecord>,
+}
+
+impl UserRecordQuery {
+    pub async fn list(self, db: &Database) -> Result<UserRecordListPayload, AppError> {
+        let (offset, limit) = page(self.offset, self.limit)?;
+
+        let filter = UserRecordFilter {
+            search: self
+                .q
+                .map(|q| q.trim().to_string())
+                .filter(|q| !q.is_empty()),
+            disabled: self.disabled,
+        };
+        Ok(UserRecordListPayload {
+            total: db.users().count_records(&filter).await?,
+            offset,
+            limit,
+            users: db.users().records(&filter, limit, offset).await?,
+        })
+    }
+}
+
+/// everything admins can see about a user
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UserRecordPayload {
+    #[serde(flatten)]
+    pub record: UserRecord,
+    /// sessions that are still active, most recent first
+    pub sessions: Vec<UserSession>,
+    /// most recent first
+    pub former_usernames: Vec<FormerUsername>,
+}
+
+impl UserRecordPayload {
+    pub async fn get(db: &Database, user_id: u32) -> Result<Self, AppError> {
+        Ok(Self {
+            record: db.users().record(user_id).await?,
+            sessions: db.users().sessions(user_id).await?,
+            former_usernames: db.users().former_usernames(user_id).await?,
+        })
+    }
+}
+
+/// changes an admin makes to a user
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct UserModerationPayload {
+    pub about: Option<String>,
+    /// a disabled user can't log in and is logged out everywhere
+    pub disabled: Option<bool>,
+    /// why the user is disabled, only when disabling, the previous reason is kept without one
+    pub disabled_reason: Option<String>,
+}
+
+impl UserModerationPayload {
+    pub async fn moderate(
+        self,
+        db: &Database,
+        user_id: u32,
+        actor: Actor,
+    ) -> Result<UserRecordPayload, AppError> {
+        if let Some(about) = &self.about {
+            check_about(about)?;
+        }
+        if self.disabled_reason.is_some() && self.disabled != Some(true) {
+            return Err(AppError::PayloadError(
+                "A reason can only be given when disabling a user".into(),
+            ));
+        }
+        // nobody would be left to enable the admin again
+        if self.disabled == Some(true) && actor == Actor::User(user_id) {
+            return Err(AppError::PayloadError("You can't disable yourself".into()));
+        }
+
+        db.users().moderate(user_id, self.into(), actor).await?;
+        UserRecordPayload::get(db, user_id).await
+    }
+}
+
+impl From<UserModerationPayload> for UserModeration {
+    fn from(value: UserModerationPayload) -> Self {
+        Self {
+            about: value.about,
+            disabled: value.disabled,
+            disabled_reason: value.disabled_reason,
+        }
+    }
+}
@@ -5,6 +5,7 @@ use axum::{
 use database::error::DatabaseError;
 use reqwest::Error as ReqwestError;
 use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
 use std::io::Error as IoError;
 use thiserror::Error;
 use tokio::task::JoinError;
@@ -63,6 +64,15 @@ pub enum AppError {
     #[error("Forbidden")]
     Forbidden,
 
+    #[error("Invalid API key")]
+    InvalidApiKey,
+
+    #[error("Rate limit exceeded, retry after {0}s")]
+    TooManyRequests(u64),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Payload error: {0}")]
     PayloadError(String),
 }
@@ -71,7 +81,13 @@ impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         // log!
         tracing::error!("{}", self);
-        self.error_page().into_response()
+        let mut response = self.error_page().into_response();
+        if let Self::TooManyRequests(retry_after) = self {
+            response
+                .headers_mut()
+                .insert(RETRY_AFTER, retry_after.into());
+        }
+        response
     }
 }
 
@@ -79,8 +95,17 @@ impl AppError {
     fn error_page(&self) -> (StatusCode, &'static str) {
         let (status, msg) = match self {
             Self::PayloadError(_) => (StatusCode::BAD_REQUEST, "Payload error"),
+            Self::Conflict(_) => (
+                StatusCode::CONFLICT,
+                "This conflicts with the current state. Please refresh and try again.",
+            ),
             Self::NotLoggedIn => (StatusCode::UNAUTHORIZED, "Not logged in."),
             Self::Forbidden => (StatusCode::FORBIDDEN, "Forbidden."),
+            Self::InvalidApiKey => (StatusCode::UNAUTHORIZED, "Invalid API key."),
+            Self::TooManyRequests(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests. Please slow down.",
+            ),
             Self::NoFile => (
                 StatusCode::BAD_REQUEST,
                 "No file found in request. Please select an image.",
@@ -113,6 +138,7 @@ impl From<DatabaseError> for AppError {
     fn from(value: DatabaseError) -> Self {
         match value {
             DatabaseError::NotFound => Self::NotFound,
+            DatabaseError::Conflict(msg) => Self::Conflict(msg),
             other => Self::Database(other),
         }
     }
@@ -0,0 +1,137 @@
+use std::{
+    io::{self, ErrorKind},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use async_zip::{Compression, ZipEntryBuilder, tokio::write::ZipFileWriter};
+use axum::{
+    body::Body,
+    http::HeaderValue,
+    response::{IntoResponse, Response},
+};
+use reqwest::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf, duplex},
+    sync::oneshot,
+};
+use tokio_util::io::ReaderStream;
+
+use crate::{
+    config::AppConfig, dto::export::UserExportPayload, error::AppError, image::ProfileImage,
+};
+
+/// name of the json manifest in the archive
+static MANIFEST_NAME: &str = "zpi.json";
+/// size of the buffer between the zip writer and the response body
+static BUFFER_SIZE: usize = 64 * 1024;
+
+/// zip archive with the manifest and the original profile image of a user
+pub struct UserExport {
+    manifest: UserExportPayload,
+    image: Option<(String, Vec<u8>)>,
+}
+
+impl UserExport {
+    /// read the original image, so nothing can fail anymore once the response is streaming
+    pub async fn new(mut manifest: UserExportPayload, config: AppConfig) -> Result<Self, AppError> {
+        let path = ProfileImage::new(manifest.user.id, config).path_orig();
+        let image = match tokio::fs::read(path).await {
+            Ok(data) => Some((format!("image.{}", image_extension(&data)), data)),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        manifest.image = image.as_ref().map(|(name, _)| name.clone());
+        Ok(Self { manifest, image })
+    }
+
+    async fn write(self, writer: impl AsyncWrite + Unpin) -> Result<(), AppError> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|err| AppError::Internal(err.to_string()))?;
+
+        let mut zip = ZipFileWriter::with_tokio(writer);
+        zip.write_entry_whole(
+            ZipEntryBuilder::new(MANIFEST_NAME.into(), Compression::Deflate),
+            &manifest,
+        )
+        .await
+        .map_err(|err| AppError::Internal(err.to_string()))?;
+        if let Some((name, data)) = self.image {
+            // images are compressed already
+            zip.write_entry_whole(
+                ZipEntryBuilder::new(name.into(), Compression::Stored),
+                &data,
+            )
+            .await
+            .map_err(|err| AppError::Internal(err.to_string()))?;
+        }
+        zip.close()
+            .await
+            .map_err(|err| AppError::Internal(err.to_string()))?;
+        Ok(())
+    }
+}
+
+impl IntoResponse for UserExport {
+    fn into_response(self) -> Response {
+        let filename = format!("zpi-{}.zip", self.manifest.user.username);
+        let (writer, reader) = duplex(BUFFER_SIZE);
+        let (failed_tx, failed) = oneshot::channel();
+        tokio::spawn(async move {
+            if let Err(err) = self.write(writer).await {
+                tracing::error!("writing data export failed: {err}");
+                let _ = failed_tx.send(err.to_string());
+            }
+        });
+
+        let reader = ExportReader { reader, failed };
+        let mut response = Body::from_stream(ReaderStream::new(reader)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+        if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename=\"{filename}\"")) {
+            headers.insert(CONTENT_DISPOSITION, value);
+        }
+        response
+    }
+}
+
+/// reads the archive as it's written, ends with an error instead of a truncated archive when
+/// writing failed
+struct ExportReader {
+    reader: DuplexStream,
+    failed: oneshot::Receiver<String>,
+}
+
+impl AsyncRead for ExportReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut self.reader).poll_read(cx, buf))?;
+        if buf.filled().len() > filled || buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+        // the writer is gone, the archive is only complete if it didn't report a failure
+        match ready!(Pin::new(&mut self.failed).poll(cx)) {
+            Ok(err) => Poll::Ready(Err(io::Error::other(err))),
+            Err(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// originals are stored as uploaded, guess the extension from the first bytes
+fn image_extension(data: &[u8]) -> &'static str {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else if data.starts_with(b"\x89PNG") {
+        "png"
+    } else if data.starts_with(b"GIF8") {
+        "gif"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        "webp"
+    } else {
+        "bin"
+    }
+}
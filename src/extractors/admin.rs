@@ -1,17 +1,17 @@
 use axum::{extract::FromRequestParts, http::request::Parts};
 
-use crate::{error::AppError, extractors::authenticated_user::AuthenticatedUser};
+use crate::{AppState, error::AppError, extractors::authenticated_user::AuthenticatedUser};
 
 #[derive(Debug)]
 pub struct Admin(pub AuthenticatedUser);
 
-impl<S> FromRequestParts<S> for Admin
-where
-    S: Send + Sync,
-{
+impl FromRequestParts<AppState> for Admin {
     type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
         let user = AuthenticatedUser::from_request_parts(parts, state).await?;
         if user.admin {
             Ok(Admin(user))
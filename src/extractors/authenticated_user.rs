@@ -1,8 +1,16 @@
-use axum::{extract::FromRequestParts, http::request::Parts};
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::request::Parts,
+};
+use chrono::{DateTime, Duration, Utc};
+use database::models::{impersonation::Impersonation, profile_change::Actor};
 use serde::{Deserialize, Serialize};
 use tower_sessions::Session;
 
-use crate::{error::AppError, handlers::auth::ZauthUser};
+use crate::{AppState, error::AppError, handlers::auth::ZauthUser};
+
+/// how often the activity of a user is written, requests in between only read
+static ACTIVITY_INTERVAL: Duration = Duration::minutes(5);
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct AuthenticatedUser {
@@ -11,20 +19,186 @@ pub struct AuthenticatedUser {
     pub admin: bool,
 }
 
-impl<S> FromRequestParts<S> for AuthenticatedUser
-where
-    S: Send + Sync,
-{
+/// kept in the session while an admin acts as another user
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ImpersonationSession {
+    /// id in the audit log
+    pub id: u32,
+    /// the admin who logged in on this session
+    pub admin: AuthenticatedUser,
+    pub allow_writes: bool,
+    pub started_at: DateTime<Utc>,
+}
+
+impl AuthenticatedUser {
+    /// log a user in on a session, returns when they logged in
+    pub async fn login(self, session: &Session) -> Result<DateTime<Utc>, AppError> {
+        let logged_in_at = Utc::now();
+        session.clear().await;
+        session.insert("user", self).await?;
+        session.insert("logged_in_at", logged_in_at).await?;
+        Ok(logged_in_at)
+    }
+
+    /// log out of a session, returns who was logged in and since when
+    ///
+    /// stop impersonating first, the admin is the one who logged in
+    pub async fn logout(session: &Session) -> Result<Option<(Self, DateTime<Utc>)>, AppError> {
+        let user: Option<AuthenticatedUser> = session.get("user").await?;
+        let logged_in_at: Option<DateTime<Utc>> = session.get("logged_in_at").await?;
+        session.clear().await;
+        Ok(user.zip(logged_in_at))
+    }
+
+    /// let the admin on a session act as this user, returns who they act as now
+    ///
+    /// the user never gets admin rights, so admin routes can't be used while impersonating
+    pub async fn impersonate(
+        self,
+        session: &Session,
+        admin: AuthenticatedUser,
+        impersonation: &Impersonation,
+    ) -> Result<Self, AppError> {
+        let user = Self {
+            admin: false,
+            ..self
+        };
+        session.insert("user", &user).await?;
+        session
+            .insert(
+                "impersonation",
+                ImpersonationSession {
+                    id: impersonation.id,
+                    admin,
+                    allow_writes: impersonation.allow_writes,
+                    started_at: impersonation.started_at,
+                },
+            )
+            .await?;
+        Ok(user)
+    }
+
+    /// the impersonation going on in a session, if any
+    pub async fn impersonation(
+        session: &Session,
+    ) -> Result<Option<ImpersonationSession>, AppError> {
+        Ok(session.get("impersonation").await?)
+    }
+
+    /// who makes the changes of this user, the admin while they are impersonating the user
+    pub async fn actor(&self, session: &Session) -> Result<Actor, AppError> {
+        Ok(match Self::impersonation(session).await? {
+            Some(impersonation) => Actor::User(impersonation.admin.id),
+            None => Actor::User(self.id),
+        })
+    }
+
+    /// switch the session back to the admin, returns the impersonation that ended
+    pub async fn stop_impersonating(
+        session: &Session,
+    ) -> Result<Option<ImpersonationSession>, AppError> {
+        let impersonation: Option<ImpersonationSession> = session.remove("impersonation").await?;
+        if let Some(impersonation) = &impersonation {
+            session.insert("user", &impersonation.admin).await?;
+        }
+        Ok(impersonation)
+    }
+
+    /// when a session expires if it isn't written again
+    pub fn expiry(session: &Session) -> DateTime<Utc> {
+        DateTime::from_timestamp(session.expiry_date().unix_timestamp(), 0).unwrap_or_default()
+    }
+
+    /// remember that the user is using the site, the session keeps track of when it was last
+    /// written so most requests don't write at all
+    ///
+    /// writing the session also pushes back its expiry, so the session row follows
+    async fn touch(
+        session: &Session,
+        state: &AppState,
+        user_id: u32,
+        logged_in_at: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        let touched_at: Option<DateTime<Utc>> = session.get("touched_at").await?;
+        let now = Utc::now();
+        if touched_at.is_some_and(|touched_at| now - touched_at < ACTIVITY_INTERVAL) {
+            return Ok(());
+        }
+        state.db.users().touch(user_id).await?;
+        session.insert("touched_at", now).await?;
+        state
+            .db
+            .users()
+            .extend_session(user_id, logged_in_at, Self::expiry(session))
+            .await?;
+        Ok(())
+    }
+}
+
+impl FromRequestParts<AppState> for AuthenticatedUser {
     type Rejection = AppError;
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let session = Session::from_request_parts(parts, _state)
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let session = Session::from_request_parts(parts, state)
             .await
             .map_err(|(_, msg)| AppError::Internal(msg.into()))?;
 
         let user: Option<AuthenticatedUser> =
             session.get("user").await.map_err(AppError::Session)?;
-        user.ok_or(AppError::NotLoggedIn)
+        let logged_in_at: Option<DateTime<Utc>> = session.get("logged_in_at").await?;
+        let (Some(user), Some(logged_in_at)) = (user, logged_in_at) else {
+            return Err(AppError::NotLoggedIn);
+        };
+
+        // the admin logged in on this session, not the user they act as
+        let impersonation = Self::impersonation(&session).await?;
+        let logged_in_id = impersonation
+            .as_ref()
+            .map_or(user.id, |impersonation| impersonation.admin.id);
+
+        // the session was ended, e.g. because the account was deleted or disabled
+        let session_state = state.db.users().session_state(logged_in_id).await?;
+        if session_state.disabled
+            || session_state
+                .revoked_at
+                .is_some_and(|revoked_at| logged_in_at <= revoked_at)
+        {
+            if let Some(impersonation) = &impersonation {
+                state.db.impersonations().end(impersonation.id).await?;
+            }
+            session.clear().await;
+            return Err(AppError::NotLoggedIn);
+        }
+
+        match impersonation {
+            // looking around is fine, changing things has to be allowed explicitly
+            Some(impersonation) if !impersonation.allow_writes && !parts.method.is_safe() => {
+                return Err(AppError::Forbidden);
+            }
+            // an admin acting as the user doesn't make the user active
+            Some(_) => {}
+            None => Self::touch(&session, state, user.id, logged_in_at).await?,
+        }
+        Ok(user)
+    }
+}
+
+/// `None` for visitors who are not logged in, for routes that are also open to them
+impl OptionalFromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        match <Self as FromRequestParts<AppState>>::from_request_parts(parts, state).await {
+            Ok(user) => Ok(Some(user)),
+            Err(AppError::NotLoggedIn) => Ok(None),
+            Err(err) => Err(err),
+        }
     }
 }
 
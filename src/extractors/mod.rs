@@ -2,6 +2,11 @@ pub mod admin;
 pub mod authenticated_user;
 pub mod config;
 pub mod database;
+pub mod rate_limit;
+pub mod service;
+pub mod usage;
 
 pub use admin::Admin;
 pub use authenticated_user::AuthenticatedUser;
+pub use rate_limit::IpRateLimited;
+pub use service::AuthenticatedService;
@@ -0,0 +1,80 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, State},
+    http::request::Parts,
+};
+
+use crate::{
+    AppState,
+    config::AppConfig,
+    error::AppError,
+    rate_limit::{RateLimitKey, RateLimiter},
+};
+
+/// limits requests per client ip, for routes that don't need authentication
+///
+/// requests without connection info are not limited, requests from a trusted proxy are limited
+/// by the client ip it forwarded
+pub struct IpRateLimited;
+
+impl FromRequestParts<AppState> for IpRateLimited {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        if let Some(ConnectInfo(addr)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() {
+            let ip = client_ip(parts, addr.ip(), &state.config);
+            state
+                .rate_limiter
+                .acquire(RateLimitKey::Ip(ip), state.config.ip_rate_limit)?;
+        }
+        Ok(IpRateLimited)
+    }
+}
+
+impl FromRequestParts<AppState> for RateLimiter {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<RateLimiter, Self::Rejection> {
+        let State(app_state) = State::<AppState>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::Internal("Failed to extract app state".into()))?;
+
+        Ok(app_state.rate_limiter)
+    }
+}
+
+/// the ip of the client, as forwarded by the trusted proxies in front of the app
+///
+/// the forwarded ips are read from the back, the first one that isn't a trusted proxy is the
+/// client. Anything before it could have been sent by the client itself.
+fn client_ip(parts: &Parts, peer: IpAddr, config: &AppConfig) -> IpAddr {
+    if !config.trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    let forwarded: Vec<&str> = parts
+        .headers
+        .get_all(config.client_ip_header.as_str())
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    let mut client = peer;
+    for hop in forwarded.into_iter().rev() {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !config.trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+    client
+}
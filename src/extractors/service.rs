@@ -0,0 +1,70 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+use axum_extra::TypedHeader;
+use database::{
+    Database,
+    error::DatabaseError,
+    models::service::{Namespace, Service},
+};
+use headers::{Authorization, authorization::Bearer};
+
+use crate::{
+    AppState,
+    error::AppError,
+    rate_limit::{RateLimit, RateLimitKey},
+    usage::UsageSlot,
+};
+
+/// a service authenticated with its API key as a bearer token
+///
+/// every extraction takes a token from the service's rate limit bucket
+#[derive(Debug)]
+pub struct AuthenticatedService {
+    pub service: Service,
+    /// `Sandbox` if the service used its test api key
+    pub namespace: Namespace,
+}
+
+impl FromRequestParts<AppState> for AuthenticatedService {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+                .await
+                .map_err(|_| AppError::InvalidApiKey)?;
+
+        let db = Database::from_request_parts(parts, state).await?;
+        let service = match db.services().by_api_key(bearer.token()).await {
+            Err(DatabaseError::NotFound) => return Err(AppError::InvalidApiKey),
+            other => other?,
+        };
+
+        let namespace = if service.test_api_key.as_deref() == Some(bearer.token()) {
+            Namespace::Sandbox
+        } else {
+            Namespace::Production
+        };
+
+        if let Some(slot) = parts.extensions.get::<UsageSlot>() {
+            slot.set(state.usage.clone(), service.id, namespace);
+        }
+
+        state
+            .rate_limiter
+            .acquire(RateLimitKey::Service(service.id), RateLimit::from(&service))?;
+
+        Ok(AuthenticatedService { service, namespace })
+    }
+}
+
+impl From<&Service> for RateLimit {
+    fn from(service: &Service) -> Self {
+        Self {
+            burst: service.rate_limit_burst,
+            per_minute: service.rate_limit_per_minute,
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use axum::{
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+};
+
+use crate::{AppState, error::AppError, usage::UsageRecorder};
+
+impl FromRequestParts<AppState> for UsageRecorder {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<UsageRecorder, Self::Rejection> {
+        let State(app_state) = State::<AppState>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::Internal("Failed to extract app state".into()))?;
+
+        Ok(app_state.usage)
+    }
+}
@@ -4,6 +4,7 @@ use database::Database;
 use crate::{
     dto::achievement::{AchievementCreatePayload, AchievementPayload},
     error::AppError,
+    extractors::AuthenticatedService,
 };
 
 pub struct AchievementHandler;
@@ -18,6 +19,15 @@ impl AchievementHandler {
         ))
     }
 
+    pub async fn get_for_authenticated_service(
+        db: Database,
+        AuthenticatedService { service, .. }: AuthenticatedService,
+    ) -> Result<Json<Vec<AchievementPayload>>, AppError> {
+        Ok(Json(
+            AchievementPayload::for_service(&db, service.id).await?,
+        ))
+    }
+
     pub async fn post_for_service(
         db: Database,
         Path(service_id): Path<u32>,
@@ -1,6 +1,6 @@
 use axum::{
     extract::{Query, rejection::QueryRejection},
-    response::{IntoResponse, Redirect},
+    response::Redirect,
 };
 use database::{Database, models::user::UserCreate};
 use rand::distr::{Alphanumeric, SampleString};
@@ -8,13 +8,19 @@ use serde::{Deserialize, Serialize};
 use tower_sessions::Session;
 
 use crate::{
-    config::AppConfig, error::AppError, extractors::authenticated_user::AuthenticatedUser,
+    config::AppConfig,
+    error::AppError,
+    extractors::{IpRateLimited, authenticated_user::AuthenticatedUser},
 };
 
 pub struct AuthHandler;
 
 impl AuthHandler {
-    pub async fn login(session: Session, config: AppConfig) -> Result<Redirect, AppError> {
+    pub async fn login(
+        _: IpRateLimited,
+        session: Session,
+        config: AppConfig,
+    ) -> Result<Redirect, AppError> {
         let zauth_state = Alphanumeric.sample_string(&mut rand::rng(), 16);
         // insert state so we can check it in the callback
         session.insert("state", zauth_state.clone()).await?;
@@ -27,12 +33,22 @@ impl AuthHandler {
         )))
     }
 
-    pub async fn logout(session: Session, config: AppConfig) -> impl IntoResponse {
-        session.clear().await;
-        Redirect::to(&config.frontend_url)
+    pub async fn logout(
+        session: Session,
+        config: AppConfig,
+        db: Database,
+    ) -> Result<Redirect, AppError> {
+        if let Some(impersonation) = AuthenticatedUser::stop_impersonating(&session).await? {
+            db.impersonations().end(impersonation.id).await?;
+        }
+        if let Some((user, logged_in_at)) = AuthenticatedUser::logout(&session).await? {
+            db.users().remove_session(user.id, logged_in_at).await?;
+        }
+        Ok(Redirect::to(&config.frontend_url))
     }
 
     pub async fn callback(
+        _: IpRateLimited,
         query: Result<Query<Callback>, QueryRejection>,
         session: Session,
         config: AppConfig,
@@ -63,7 +79,7 @@ impl AuthHandler {
         // get token from zauth with code
         let token = client
             .post(format!("{}/oauth/token", config.zauth_url.as_str()))
-            .basic_auth(config.zauth_client_id, Some(config.zauth_client_secret))
+            .basic_auth(&config.zauth_client_id, Some(&config.zauth_client_secret))
             .form(&form)
             .send()
             .await?
@@ -80,11 +96,19 @@ impl AuthHandler {
             .json::<ZauthUser>()
             .await?;
 
+        if db.users().session_state(zauth_user.id).await?.disabled {
+            return Err(AppError::Forbidden);
+        }
+
         db.users().create(zauth_user.clone().into()).await?;
+        db.tags()
+            .sync_zauth(zauth_user.id, &config.tags_for_roles(&zauth_user.roles))
+            .await?;
 
-        session.clear().await;
-        session
-            .insert("user", AuthenticatedUser::from(zauth_user))
+        let user_id = zauth_user.id;
+        let logged_in_at = AuthenticatedUser::from(zauth_user).login(&session).await?;
+        db.users()
+            .add_session(user_id, logged_in_at, AuthenticatedUser::expiry(&session))
             .await?;
         Ok(Redirect::to(&config.frontend_url))
     }
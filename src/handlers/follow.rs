@@ -0,0 +1,79 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+    http::StatusCode,
+};
+use database::Database;
+
+use crate::{
+    config::AppConfig,
+    dto::{
+        follow::{FeedPayload, FeedQuery, FollowList, FollowListPayload, FollowListQuery, follow},
+        user::{UserId, Viewer},
+    },
+    error::AppError,
+    extractors::AuthenticatedUser,
+};
+
+pub struct FollowHandler;
+
+impl FollowHandler {
+    pub async fn follow(
+        Path(user_id): Path<u32>,
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        follow(&db, user.id, user_id, &Viewer::from(&user)).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn unfollow(
+        Path(user_id): Path<u32>,
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        db.follows().unfollow(user.id, user_id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn followers(
+        Path(user_id_or_name): Path<String>,
+        Query(query): Query<FollowListQuery>,
+        user: AuthenticatedUser,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<Json<FollowListPayload>, AppError> {
+        let user_id = UserId::from(user_id_or_name);
+        let viewer = Viewer::from(&user);
+        Ok(Json(
+            query
+                .list(&db, user_id, FollowList::Followers, &viewer, &config)
+                .await?,
+        ))
+    }
+
+    pub async fn following(
+        Path(user_id_or_name): Path<String>,
+        Query(query): Query<FollowListQuery>,
+        user: AuthenticatedUser,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<Json<FollowListPayload>, AppError> {
+        let user_id = UserId::from(user_id_or_name);
+        let viewer = Viewer::from(&user);
+        Ok(Json(
+            query
+                .list(&db, user_id, FollowList::Following, &viewer, &config)
+                .await?,
+        ))
+    }
+
+    /// unlocks and profile updates of the users you follow
+    pub async fn feed(
+        Query(query): Query<FeedQuery>,
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<Json<FeedPayload>, AppError> {
+        Ok(Json(query.feed(&db, user.id).await?))
+    }
+}
@@ -10,64 +10,93 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use axum_extra::TypedHeader;
+use database::{Database, error::DatabaseError, models::profile_change::Actor};
 use headers::{ETag, IfNoneMatch};
 use reqwest::{StatusCode, header::ETAG};
 use serde::Deserialize;
+use tower_sessions::Session;
 
 use crate::{
-    config::AppConfig, error::AppError, extractors::authenticated_user::AuthenticatedUser,
-    image::ProfileImage,
+    config::AppConfig,
+    dto::user::Viewer,
+    error::AppError,
+    extractors::{Admin, IpRateLimited, authenticated_user::AuthenticatedUser},
+    image::{ImageOwner, ProfileImage, ResponseImage},
 };
 
-static SIZES: &[u32] = &[64, 128, 256, 512];
+pub static SIZES: &[u32] = &[64, 128, 256, 512];
 static MAX_SIZE: u32 = 512;
+/// tag and category icons are shown a lot smaller than profile pictures
+pub static ICON_SIZES: &[u32] = &[32, 64, 128];
+static MAX_ICON_SIZE: u32 = 128;
 
 pub struct ImageHandler;
 
 impl ImageHandler {
+    /// profile image, or a placeholder if the viewer is not allowed to see it
     pub async fn get(
+        _: IpRateLimited,
         Query(params): Query<GetImageQuery>,
         Path(user_id): Path<u32>,
         if_none_match: Option<TypedHeader<IfNoneMatch>>,
         config: AppConfig,
+        user: Option<AuthenticatedUser>,
+        db: Database,
     ) -> Result<Response, AppError> {
         // default size
-        let requested_size = params.size.unwrap_or(256);
-        // get next larger size if size is not available, or largest if none are bigger
-        let size = *SIZES
-            .iter()
-            .filter(|x| **x >= requested_size)
-            .min()
-            .unwrap_or(&MAX_SIZE);
+        let size = pick_size(SIZES, MAX_SIZE, params.size.unwrap_or(256));
+        let visible = match db.users().by_id(user_id).await {
+            Ok(owner) => Viewer::from(user).can_see_part(&owner, owner.field_visibility.image),
+            Err(DatabaseError::NotFound) => true,
+            Err(err) => return Err(err.into()),
+        };
+        if !visible {
+            return match params.placeholder {
+                Some(false) => Err(AppError::NotFound),
+                _ => Ok(ResponseImage::Placeholder(user_id).into_response()),
+            };
+        }
 
         let profile = ProfileImage::new(user_id, config);
-        let etag_opt = file_modified_etag(&profile.path(size)).await?;
+        serve(profile, size, params.placeholder, if_none_match).await
+    }
 
-        // return early if etag matches
-        if etag_matches(&if_none_match, &etag_opt) {
-            return Ok(StatusCode::NOT_MODIFIED.into_response());
-        }
+    /// icon of a tag, or of its category if the tag has none
+    pub async fn get_tag_icon(
+        _: IpRateLimited,
+        Query(params): Query<GetImageQuery>,
+        Path(tag_id): Path<u32>,
+        if_none_match: Option<TypedHeader<IfNoneMatch>>,
+        config: AppConfig,
+        db: Database,
+    ) -> Result<Response, AppError> {
+        let size = pick_size(ICON_SIZES, MAX_ICON_SIZE, params.size.unwrap_or(64));
+        let category = db.tags().category_for_tag(tag_id).await?;
 
-        // get image (or placeholder, if requested) from disk
-        let mut resp = match params.placeholder {
-            Some(false) => profile.get(size).await,
-            _ => profile.get_with_placeholder(size).await,
-        }?
-        .into_response();
-
-        // set etag header if possible
-        if let Some(etag_string) = etag_opt
-            && let Ok(etag_header_val) = etag_string.parse()
-        {
-            resp.headers_mut().insert(ETAG, etag_header_val);
+        let mut icon = ProfileImage::for_owner(ImageOwner::Tag(tag_id), config.clone());
+        if !tokio::fs::try_exists(icon.path(size)).await? {
+            icon = ProfileImage::for_owner(ImageOwner::TagCategory(category.id), config);
         }
+        serve(icon, size, Some(false), if_none_match).await
+    }
 
-        Ok(resp)
+    pub async fn get_tag_category_icon(
+        _: IpRateLimited,
+        Query(params): Query<GetImageQuery>,
+        Path(category_id): Path<u32>,
+        if_none_match: Option<TypedHeader<IfNoneMatch>>,
+        config: AppConfig,
+    ) -> Result<Response, AppError> {
+        let size = pick_size(ICON_SIZES, MAX_ICON_SIZE, params.size.unwrap_or(64));
+        let icon = ProfileImage::for_owner(ImageOwner::TagCategory(category_id), config);
+        serve(icon, size, Some(false), if_none_match).await
     }
 
     pub async fn post(
         user: AuthenticatedUser,
+        session: Session,
         config: AppConfig,
+        db: Database,
         body: Body,
     ) -> Result<StatusCode, AppError> {
         let data: Bytes = to_bytes(body, usize::MAX).await?;
@@ -77,27 +106,100 @@ impl ImageHandler {
             .await?
             .save_sizes(SIZES)
             .await?;
+        db.users()
+            .change_image(user.id, true, user.actor(&session).await?)
+            .await?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn post_tag_icon(
+        Path(tag_id): Path<u32>,
+        config: AppConfig,
+        db: Database,
+        body: Body,
+    ) -> Result<StatusCode, AppError> {
+        db.tags().by_id(tag_id).await?;
+        let data: Bytes = to_bytes(body, usize::MAX).await?;
+
+        ProfileImage::for_owner(ImageOwner::Tag(tag_id), config)
+            .with_data(&data)
+            .await?
+            .save_sizes(ICON_SIZES)
+            .await?;
+
+        db.tags().set_icon(tag_id, true).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
 
+    pub async fn delete_tag_icon(
+        Path(tag_id): Path<u32>,
+        config: AppConfig,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        db.tags().set_icon(tag_id, false).await?;
+        ProfileImage::for_owner(ImageOwner::Tag(tag_id), config)
+            .delete(ICON_SIZES)
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn post_tag_category_icon(
+        Path(category_id): Path<u32>,
+        config: AppConfig,
+        db: Database,
+        body: Body,
+    ) -> Result<StatusCode, AppError> {
+        db.tags().category_by_id(category_id).await?;
+        let data: Bytes = to_bytes(body, usize::MAX).await?;
+
+        ProfileImage::for_owner(ImageOwner::TagCategory(category_id), config)
+            .with_data(&data)
+            .await?
+            .save_sizes(ICON_SIZES)
+            .await?;
+
+        db.tags().set_category_icon(category_id, true).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn delete_tag_category_icon(
+        Path(category_id): Path<u32>,
+        config: AppConfig,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        db.tags().set_category_icon(category_id, false).await?;
+        ProfileImage::for_owner(ImageOwner::TagCategory(category_id), config)
+            .delete(ICON_SIZES)
+            .await?;
         Ok(StatusCode::NO_CONTENT)
     }
 
     pub async fn delete(
         user: AuthenticatedUser,
+        session: Session,
         config: AppConfig,
+        db: Database,
     ) -> Result<StatusCode, AppError> {
-        let profile = ProfileImage::new(user.id, config);
-        for size in SIZES {
-            if let Err(e) = tokio::fs::remove_file(profile.path(*size)).await
-                && e.kind() != NotFound
-            {
-                Err(e)?;
-            }
-        }
-        if let Err(e) = tokio::fs::remove_file(profile.path_orig()).await
-            && e.kind() != NotFound
-        {
-            Err(e)?;
-        }
+        ProfileImage::new(user.id, config).delete(SIZES).await?;
+        db.users()
+            .change_image(user.id, false, user.actor(&session).await?)
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// reset the profile image of any user to the placeholder, for admins
+    pub async fn delete_for_user(
+        Path(user_id): Path<u32>,
+        Admin(admin): Admin,
+        config: AppConfig,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        db.users().by_id(user_id).await?;
+        ProfileImage::new(user_id, config).delete(SIZES).await?;
+        db.users()
+            .change_image(user_id, false, Actor::User(admin.id))
+            .await?;
         Ok(StatusCode::NO_CONTENT)
     }
 }
@@ -108,6 +210,45 @@ pub struct GetImageQuery {
     size: Option<u32>,
 }
 
+/// next larger size if size is not available, or largest if none are bigger
+fn pick_size(sizes: &[u32], max_size: u32, requested_size: u32) -> u32 {
+    *sizes
+        .iter()
+        .filter(|x| **x >= requested_size)
+        .min()
+        .unwrap_or(&max_size)
+}
+
+async fn serve(
+    image: ProfileImage,
+    size: u32,
+    placeholder: Option<bool>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+) -> Result<Response, AppError> {
+    let etag_opt = file_modified_etag(&image.path(size)).await?;
+
+    // return early if etag matches
+    if etag_matches(&if_none_match, &etag_opt) {
+        return Ok(StatusCode::NOT_MODIFIED.into_response());
+    }
+
+    // get image (or placeholder, if requested) from disk
+    let mut resp = match placeholder {
+        Some(false) => image.get(size).await,
+        _ => image.get_with_placeholder(size).await,
+    }?
+    .into_response();
+
+    // set etag header if possible
+    if let Some(etag_string) = etag_opt
+        && let Ok(etag_header_val) = etag_string.parse()
+    {
+        resp.headers_mut().insert(ETAG, etag_header_val);
+    }
+
+    Ok(resp)
+}
+
 fn etag_matches(header: &Option<TypedHeader<IfNoneMatch>>, etag_string: &Option<String>) -> bool {
     if let Some(if_none_match) = header
         && let Some(etag_string) = etag_string
@@ -0,0 +1,49 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+    http::StatusCode,
+};
+use database::Database;
+use tower_sessions::Session;
+
+use crate::{
+    dto::impersonation::{
+        CurrentUserPayload, ImpersonationListPayload, ImpersonationQuery, ImpersonationStartPayload,
+    },
+    error::AppError,
+    extractors::{Admin, authenticated_user::AuthenticatedUser},
+};
+
+pub struct ImpersonationHandler;
+
+impl ImpersonationHandler {
+    /// act as another user on the current session, for admins
+    pub async fn start(
+        Path(user_id): Path<u32>,
+        Admin(admin): Admin,
+        session: Session,
+        db: Database,
+        Json(payload): Json<ImpersonationStartPayload>,
+    ) -> Result<Json<CurrentUserPayload>, AppError> {
+        Ok(Json(payload.start(&db, &session, admin, user_id).await?))
+    }
+
+    /// go back to the admin who logged in
+    ///
+    /// not behind the authenticated routes, so it works while writes are blocked
+    pub async fn stop(session: Session, db: Database) -> Result<StatusCode, AppError> {
+        let impersonation = AuthenticatedUser::stop_impersonating(&session)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        db.impersonations().end(impersonation.id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// the audit log of impersonations, for admins
+    pub async fn list(
+        Query(query): Query<ImpersonationQuery>,
+        db: Database,
+    ) -> Result<Json<ImpersonationListPayload>, AppError> {
+        Ok(Json(query.list(&db).await?))
+    }
+}
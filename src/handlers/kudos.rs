@@ -0,0 +1,89 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+    http::StatusCode,
+};
+use database::{Database, models::kudos::Kudos};
+
+use crate::{
+    config::AppConfig,
+    dto::{
+        kudos::{
+            KudosAdminQuery, KudosCreatePayload, KudosHidePayload, KudosListPayload,
+            KudosListQuery, UserKudosPayload,
+        },
+        user::{UserId, Viewer},
+    },
+    error::AppError,
+    extractors::{Admin, AuthenticatedUser},
+    rate_limit::RateLimiter,
+};
+
+pub struct KudosHandler;
+
+impl KudosHandler {
+    pub async fn give(
+        Path(user_id): Path<u32>,
+        user: AuthenticatedUser,
+        db: Database,
+        limiter: RateLimiter,
+        config: AppConfig,
+        Json(payload): Json<KudosCreatePayload>,
+    ) -> Result<(StatusCode, Json<Kudos>), AppError> {
+        let viewer = Viewer::from(&user);
+        let kudos = payload
+            .give(&db, &limiter, &config, &viewer, user.id, user_id)
+            .await?;
+        Ok((StatusCode::CREATED, Json(kudos)))
+    }
+
+    /// take back the kudos you gave a user
+    pub async fn withdraw(
+        Path(user_id): Path<u32>,
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        db.kudos().withdraw(user.id, user_id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn list(
+        Path(user_id_or_name): Path<String>,
+        Query(query): Query<KudosListQuery>,
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<Json<UserKudosPayload>, AppError> {
+        let user_id = UserId::from(user_id_or_name);
+        Ok(Json(query.list(&db, user_id, &Viewer::from(&user)).await?))
+    }
+
+    /// hide or show kudos you got
+    pub async fn hide(
+        Path(kudos_id): Path<u32>,
+        user: AuthenticatedUser,
+        db: Database,
+        Json(payload): Json<KudosHidePayload>,
+    ) -> Result<Json<Kudos>, AppError> {
+        Ok(Json(
+            db.kudos()
+                .set_hidden(kudos_id, user.id, payload.hidden)
+                .await?,
+        ))
+    }
+
+    pub async fn list_all(
+        Query(query): Query<KudosAdminQuery>,
+        db: Database,
+    ) -> Result<Json<KudosListPayload>, AppError> {
+        Ok(Json(query.list(&db).await?))
+    }
+
+    /// remove abusive kudos, the giver can't give them again
+    pub async fn remove(
+        Path(kudos_id): Path<u32>,
+        Admin(admin): Admin,
+        db: Database,
+    ) -> Result<Json<Kudos>, AppError> {
+        Ok(Json(db.kudos().remove(kudos_id, admin.id).await?))
+    }
+}
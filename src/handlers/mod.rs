@@ -1,6 +1,14 @@
 pub mod achievement;
 pub mod auth;
+pub mod follow;
 pub mod image;
+pub mod impersonation;
+pub mod kudos;
+pub mod profile_field;
+pub mod sandbox;
 pub mod service;
+pub mod service_data;
+pub mod tag;
+pub mod unlock;
 pub mod user;
 pub mod version;
@@ -0,0 +1,45 @@
+use axum::{Json, extract::Path};
+use database::{Database, models::profile_change::Actor};
+use reqwest::StatusCode;
+
+use crate::{
+    dto::profile_field::{
+        ProfileFieldCreatePayload, ProfileFieldPatchPayload, ProfileFieldPayload,
+    },
+    error::AppError,
+    extractors::Admin,
+};
+
+pub struct ProfileFieldHandler;
+
+impl ProfileFieldHandler {
+    pub async fn get_all(db: Database) -> Result<Json<Vec<ProfileFieldPayload>>, AppError> {
+        Ok(Json(ProfileFieldPayload::all(&db).await?))
+    }
+
+    pub async fn post(
+        db: Database,
+        Json(payload): Json<ProfileFieldCreatePayload>,
+    ) -> Result<Json<ProfileFieldPayload>, AppError> {
+        Ok(Json(payload.create(&db).await?))
+    }
+
+    pub async fn patch(
+        db: Database,
+        Path(field_id): Path<u32>,
+        Json(payload): Json<ProfileFieldPatchPayload>,
+    ) -> Result<Json<ProfileFieldPayload>, AppError> {
+        Ok(Json(payload.patch(field_id, &db).await?))
+    }
+
+    pub async fn delete(
+        db: Database,
+        Path(field_id): Path<u32>,
+        Admin(admin): Admin,
+    ) -> Result<StatusCode, AppError> {
+        db.profile_fields()
+            .delete(field_id, Actor::User(admin.id))
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
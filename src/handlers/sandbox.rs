@@ -0,0 +1,51 @@
+use axum::{Json, extract::Path};
+use database::{Database, models::service::Namespace};
+use reqwest::StatusCode;
+
+use crate::{
+    dto::sandbox::SandboxPayload,
+    error::AppError,
+    extractors::{AuthenticatedService, AuthenticatedUser},
+};
+
+pub struct SandboxHandler;
+
+impl SandboxHandler {
+    pub async fn get_for_service(
+        user: AuthenticatedUser,
+        db: Database,
+        Path(service_id): Path<u32>,
+    ) -> Result<Json<SandboxPayload>, AppError> {
+        Ok(Json(
+            SandboxPayload::for_service(&db, &user, service_id).await?,
+        ))
+    }
+
+    pub async fn delete_for_service(
+        user: AuthenticatedUser,
+        db: Database,
+        Path(service_id): Path<u32>,
+    ) -> Result<StatusCode, AppError> {
+        SandboxPayload::wipe(&db, &user, service_id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn get_for_current_user(
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<Json<SandboxPayload>, AppError> {
+        Ok(Json(SandboxPayload::for_user(&db, user.id).await?))
+    }
+
+    /// lets a service wipe its own sandbox, only with its test api key
+    pub async fn delete_own(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        if namespace != Namespace::Sandbox {
+            return Err(AppError::Forbidden);
+        }
+        db.services().wipe_sandbox(service.id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
@@ -1,11 +1,18 @@
-use axum::{Json, extract::Path};
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
 use database::Database;
 
 use crate::{
     dto::service::{
         ServiceCreatePayload, ServicePatchPayload, ServicePayloadAdmin, ServicePayloadUser,
+        ServiceRateLimitPatchPayload, ServiceRateLimitPayload, ServiceUsagePayload,
+        ServiceUsageQuery,
     },
     error::AppError,
+    rate_limit::RateLimiter,
+    usage::UsageRecorder,
 };
 
 pub struct ServiceHandler;
@@ -42,4 +49,54 @@ impl ServiceHandler {
             ServicePayloadAdmin::regenerate_api_key(&db, service_id).await?,
         ))
     }
+
+    pub async fn post_test_api_key(
+        db: Database,
+        Path(service_id): Path<u32>,
+    ) -> Result<Json<ServicePayloadAdmin>, AppError> {
+        Ok(Json(
+            ServicePayloadAdmin::regenerate_test_api_key(&db, service_id).await?,
+        ))
+    }
+
+    pub async fn delete_test_api_key(
+        db: Database,
+        Path(service_id): Path<u32>,
+    ) -> Result<Json<ServicePayloadAdmin>, AppError> {
+        Ok(Json(
+            ServicePayloadAdmin::remove_test_api_key(&db, service_id).await?,
+        ))
+    }
+
+    pub async fn get_rate_limit(
+        db: Database,
+        limiter: RateLimiter,
+        Path(service_id): Path<u32>,
+    ) -> Result<Json<ServiceRateLimitPayload>, AppError> {
+        Ok(Json(
+            ServiceRateLimitPayload::get(&db, &limiter, service_id).await?,
+        ))
+    }
+
+    pub async fn put_rate_limit(
+        db: Database,
+        limiter: RateLimiter,
+        Path(service_id): Path<u32>,
+        Json(payload): Json<ServiceRateLimitPatchPayload>,
+    ) -> Result<Json<ServiceRateLimitPayload>, AppError> {
+        Ok(Json(payload.patch(service_id, &db, &limiter).await?))
+    }
+
+    pub async fn usage(
+        db: Database,
+        usage: UsageRecorder,
+        Path(service_id): Path<u32>,
+        Query(query): Query<ServiceUsageQuery>,
+    ) -> Result<Json<ServiceUsagePayload>, AppError> {
+        // include the requests that weren't written yet
+        usage.flush(&db).await?;
+        Ok(Json(
+            ServiceUsagePayload::get(&db, service_id, query).await?,
+        ))
+    }
 }
@@ -0,0 +1,73 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
+use database::{Database, models::service::Namespace};
+use reqwest::StatusCode;
+
+use crate::{
+    dto::service_data::{
+        ServiceDataDeleteQuery, ServiceDataPayload, ServiceDataPutPayload, ServiceDataUserPayload,
+    },
+    error::AppError,
+    extractors::{AuthenticatedService, AuthenticatedUser},
+};
+
+pub struct ServiceDataHandler;
+
+impl ServiceDataHandler {
+    pub async fn get_all(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+        Path(user_id): Path<u32>,
+    ) -> Result<Json<Vec<ServiceDataPayload>>, AppError> {
+        Ok(Json(
+            ServiceDataPayload::for_service_user(&db, namespace, service.id, user_id).await?,
+        ))
+    }
+
+    pub async fn get(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+        Path((user_id, key)): Path<(u32, String)>,
+    ) -> Result<Json<ServiceDataPayload>, AppError> {
+        Ok(Json(
+            ServiceDataPayload::get(&db, namespace, service.id, user_id, &key).await?,
+        ))
+    }
+
+    pub async fn put(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+        Path((user_id, key)): Path<(u32, String)>,
+        Json(payload): Json<ServiceDataPutPayload>,
+    ) -> Result<Json<ServiceDataPayload>, AppError> {
+        Ok(Json(
+            payload
+                .put(&db, namespace, service.id, user_id, key)
+                .await?,
+        ))
+    }
+
+    pub async fn delete(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+        Path((user_id, key)): Path<(u32, String)>,
+        Query(query): Query<ServiceDataDeleteQuery>,
+    ) -> Result<StatusCode, AppError> {
+        db.service_data(namespace)
+            .delete(service.id, user_id, &key, query.version)
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// everything services store about the current user
+    pub async fn get_for_current_user(
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<Json<Vec<ServiceDataUserPayload>>, AppError> {
+        Ok(Json(
+            ServiceDataUserPayload::for_user(&db, Namespace::Production, user.id).await?,
+        ))
+    }
+}
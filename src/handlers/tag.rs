@@ -0,0 +1,216 @@
+use axum::{
+    Json,
+    extract::{Path, Query},
+};
+use database::{
+    Database,
+    models::{
+        profile_change::Actor,
+        tag::{Tag, TagAssignment, TagCategory, TagOverview, TagPolicy},
+    },
+};
+use reqwest::StatusCode;
+
+use crate::{
+    config::AppConfig,
+    dto::{
+        tag::{
+            TagAssignmentCreatePayload, TagAssignmentPatchPayload, TagCategoryCreatePayload,
+            TagCategoryDeleteQuery, TagCategoryPatchPayload, TagCreatePayload, TagMembersPayload,
+            TagMembersQuery, TagPatchPayload, assign_with_policy, check_service_tag,
+            unassign_with_policy,
+        },
+        user::Viewer,
+    },
+    error::AppError,
+    extractors::{Admin, AuthenticatedService, AuthenticatedUser},
+    handlers::image::ICON_SIZES,
+    image::{ImageOwner, ProfileImage},
+};
+
+pub struct TagHandler;
+
+impl TagHandler {
+    pub async fn get_all(db: Database) -> Result<Json<Vec<Tag>>, AppError> {
+        Ok(Json(db.tags().all().await?))
+    }
+
+    pub async fn post(
+        db: Database,
+        Json(payload): Json<TagCreatePayload>,
+    ) -> Result<Json<Tag>, AppError> {
+        Ok(Json(payload.create(&db).await?))
+    }
+
+    pub async fn patch(
+        db: Database,
+        Path(tag_id): Path<u32>,
+        Json(payload): Json<TagPatchPayload>,
+    ) -> Result<Json<Tag>, AppError> {
+        Ok(Json(payload.patch(tag_id, &db).await?))
+    }
+
+    pub async fn delete(
+        db: Database,
+        config: AppConfig,
+        Path(tag_id): Path<u32>,
+        Admin(admin): Admin,
+    ) -> Result<StatusCode, AppError> {
+        db.tags().delete(tag_id, Actor::User(admin.id)).await?;
+        ProfileImage::for_owner(ImageOwner::Tag(tag_id), config)
+            .delete(ICON_SIZES)
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn get_categories(db: Database) -> Result<Json<Vec<TagCategory>>, AppError> {
+        Ok(Json(db.tags().categories().await?))
+    }
+
+    pub async fn post_category(
+        db: Database,
+        Json(payload): Json<TagCategoryCreatePayload>,
+    ) -> Result<Json<TagCategory>, AppError> {
+        Ok(Json(payload.create(&db).await?))
+    }
+
+    pub async fn patch_category(
+        db: Database,
+        Path(category_id): Path<u32>,
+        Json(payload): Json<TagCategoryPatchPayload>,
+    ) -> Result<Json<TagCategory>, AppError> {
+        Ok(Json(payload.patch(category_id, &db).await?))
+    }
+
+    pub async fn delete_category(
+        db: Database,
+        config: AppConfig,
+        Path(category_id): Path<u32>,
+        Query(query): Query<TagCategoryDeleteQuery>,
+        Admin(admin): Admin,
+    ) -> Result<StatusCode, AppError> {
+        let tag_ids = db
+            .tags()
+            .delete_category(category_id, query.cascade, Actor::User(admin.id))
+            .await?;
+
+        let owners = tag_ids.into_iter().map(ImageOwner::Tag);
+        for owner in owners.chain([ImageOwner::TagCategory(category_id)]) {
+            ProfileImage::for_owner(owner, config.clone())
+                .delete(ICON_SIZES)
+                .await?;
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn assign(
+        db: Database,
+        Admin(admin): Admin,
+        Path((user_id, tag_id)): Path<(u32, u32)>,
+    ) -> Result<StatusCode, AppError> {
+        db.users().by_id(user_id).await?;
+        db.tags()
+            .assign(user_id, tag_id, Actor::User(admin.id))
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn unassign(
+        db: Database,
+        Admin(admin): Admin,
+        Path((user_id, tag_id)): Path<(u32, u32)>,
+    ) -> Result<StatusCode, AppError> {
+        db.tags()
+            .unassign(user_id, tag_id, Actor::User(admin.id))
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// every assignment of a user, also past and future ones
+    pub async fn get_assignments(
+        db: Database,
+        Path(user_id): Path<u32>,
+    ) -> Result<Json<Vec<TagAssignment>>, AppError> {
+        db.users().by_id(user_id).await?;
+        Ok(Json(db.tags().assignments(user_id).await?))
+    }
+
+    /// add an assignment for a given period, e.g. to fill in history
+    pub async fn post_assignment(
+        db: Database,
+        Admin(admin): Admin,
+        Path(user_id): Path<u32>,
+        Json(payload): Json<TagAssignmentCreatePayload>,
+    ) -> Result<Json<TagAssignment>, AppError> {
+        Ok(Json(
+            payload.create(user_id, &db, Actor::User(admin.id)).await?,
+        ))
+    }
+
+    pub async fn patch_assignment(
+        db: Database,
+        Admin(admin): Admin,
+        Path(assignment_id): Path<u32>,
+        Json(payload): Json<TagAssignmentPatchPayload>,
+    ) -> Result<Json<TagAssignment>, AppError> {
+        Ok(Json(
+            payload
+                .patch(assignment_id, &db, Actor::User(admin.id))
+                .await?,
+        ))
+    }
+
+    pub async fn delete_assignment(
+        db: Database,
+        Admin(admin): Admin,
+        Path(assignment_id): Path<u32>,
+    ) -> Result<StatusCode, AppError> {
+        db.tags()
+            .delete_assignment(assignment_id, Actor::User(admin.id))
+            .await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// give a user a tag from a service-assignable category
+    pub async fn assign_for_service(
+        service: AuthenticatedService,
+        db: Database,
+        Path((user_id, tag_id)): Path<(u32, u32)>,
+    ) -> Result<StatusCode, AppError> {
+        check_service_tag(&db, &service, tag_id).await?;
+        db.users().by_id(user_id).await?;
+        let actor = Actor::Service(service.service.id);
+        assign_with_policy(&db, user_id, tag_id, TagPolicy::ServiceAssignable, actor).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// remove a tag from a service-assignable category from a user
+    pub async fn unassign_for_service(
+        service: AuthenticatedService,
+        db: Database,
+        Path((user_id, tag_id)): Path<(u32, u32)>,
+    ) -> Result<StatusCode, AppError> {
+        check_service_tag(&db, &service, tag_id).await?;
+        let actor = Actor::Service(service.service.id);
+        unassign_with_policy(&db, user_id, tag_id, TagPolicy::ServiceAssignable, actor).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// all tags with their category and amount of members
+    pub async fn directory(db: Database) -> Result<Json<Vec<TagOverview>>, AppError> {
+        Ok(Json(db.tags().overview().await?))
+    }
+
+    /// paginated profiles of the members holding a tag
+    pub async fn members(
+        db: Database,
+        user: AuthenticatedUser,
+        config: AppConfig,
+        Path(name): Path<String>,
+        Query(query): Query<TagMembersQuery>,
+    ) -> Result<Json<TagMembersPayload>, AppError> {
+        Ok(Json(
+            TagMembersPayload::get(&db, &name, query, &Viewer::from(&user), &config).await?,
+        ))
+    }
+}
@@ -0,0 +1,33 @@
+use axum::{Json, extract::Path};
+use database::Database;
+
+use crate::{
+    dto::unlock::{UnlockCreatePayload, UnlockPayload},
+    error::AppError,
+    extractors::AuthenticatedService,
+};
+
+pub struct UnlockHandler;
+
+impl UnlockHandler {
+    pub async fn get_for_user(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+        Path(user_id): Path<u32>,
+    ) -> Result<Json<Vec<UnlockPayload>>, AppError> {
+        Ok(Json(
+            UnlockPayload::for_service_user(&db, namespace, service.id, user_id).await?,
+        ))
+    }
+
+    pub async fn post_for_user(
+        AuthenticatedService { service, namespace }: AuthenticatedService,
+        db: Database,
+        Path(user_id): Path<u32>,
+        Json(payload): Json<UnlockCreatePayload>,
+    ) -> Result<Json<UnlockPayload>, AppError> {
+        Ok(Json(
+            payload.create(&db, namespace, service.id, user_id).await?,
+        ))
+    }
+}
@@ -1,37 +1,164 @@
-use crate::dto::user::UserProfile;
-use axum::extract::Path;
-use axum::{Json, Router, routing::get};
+use crate::dto::user::{
+    UserId, UserListPayload, UserListQuery, UserLookupQuery, UserProfile, Viewer,
+};
+use axum::extract::{Path, Query};
+use axum::http::StatusCode;
+use axum::http::header::LOCATION;
+use axum::response::{IntoResponse, Response};
+use axum::{
+    Json, Router,
+    routing::{get, patch, put},
+};
 use database::Database;
+use database::error::DatabaseError;
+use database::models::profile_change::Actor;
+use database::models::tag::TagPolicy;
 use database::models::user::User;
+use tower_sessions::Session;
 
 use crate::AppState;
+use crate::config::AppConfig;
+use crate::dto::export::UserExportPayload;
+use crate::dto::impersonation::CurrentUserPayload;
+use crate::dto::profile_change::{ProfileHistoryPayload, ProfileHistoryQuery};
+use crate::dto::tag::{assign_with_policy, unassign_with_policy};
 use crate::dto::user::UserPatchPayload;
+use crate::dto::user_record::{
+    UserModerationPayload, UserRecordListPayload, UserRecordPayload, UserRecordQuery,
+};
 use crate::error::AppError;
+use crate::export::UserExport;
 use crate::extractors::authenticated_user::AuthenticatedUser;
+use crate::extractors::{Admin, AuthenticatedService, IpRateLimited};
+use crate::handlers::follow::FollowHandler;
+use crate::handlers::image::SIZES;
+use crate::handlers::kudos::KudosHandler;
+use crate::handlers::sandbox::SandboxHandler;
+use crate::handlers::service_data::ServiceDataHandler;
+use crate::image::ProfileImage;
 
 pub struct UserHandler;
 
 impl UserHandler {
     pub fn router() -> Router<AppState> {
         Router::new()
-            .route("/me", get(Self::current_user))
-            .route("/{id}", get(Self::profile).patch(Self::patch))
+            .route("/", get(Self::list))
+            .route("/me", get(Self::current_user).delete(Self::delete_current))
+            .route("/me/data", get(ServiceDataHandler::get_for_current_user))
+            .route("/me/sandbox", get(SandboxHandler::get_for_current_user))
+            .route("/me/export", get(Self::export))
+            .route("/me/history", get(Self::current_history))
+            .route("/me/feed", get(FollowHandler::feed))
+            .route("/me/kudos/{kudos_id}", patch(KudosHandler::hide))
+            .route("/{id}", patch(Self::patch))
+            .route(
+                "/{id}/follow",
+                put(FollowHandler::follow).delete(FollowHandler::unfollow),
+            )
+            .route("/{id}/followers", get(FollowHandler::followers))
+            .route("/{id}/following", get(FollowHandler::following))
+            .route(
+                "/{id}/kudos",
+                get(KudosHandler::list)
+                    .post(KudosHandler::give)
+                    .delete(KudosHandler::withdraw),
+            )
+            .route(
+                "/{id}/tags/{tag_id}",
+                put(Self::add_tag).delete(Self::remove_tag),
+            )
     }
 
-    async fn current_user(user: AuthenticatedUser) -> Result<Json<AuthenticatedUser>, AppError> {
-        Ok(Json(user))
+    /// says so when an admin is acting as the user
+    async fn current_user(
+        user: AuthenticatedUser,
+        session: Session,
+    ) -> Result<Json<CurrentUserPayload>, AppError> {
+        Ok(Json(CurrentUserPayload::get(user, &session).await?))
     }
 
-    async fn profile(
+    /// zip archive with everything zpi stores about the current user
+    async fn export(
+        user: AuthenticatedUser,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<UserExport, AppError> {
+        let manifest = UserExportPayload::for_user(&db, user.id).await?;
+        UserExport::new(manifest, config).await
+    }
+
+    /// who changed what on your own profile
+    async fn current_history(
+        Query(query): Query<ProfileHistoryQuery>,
+        user: AuthenticatedUser,
+        db: Database,
+    ) -> Result<Json<ProfileHistoryPayload>, AppError> {
+        Ok(Json(query.list(&db, user.id).await?))
+    }
+
+    /// public profiles can be seen without logging in
+    ///
+    /// an old username redirects to the current one
+    pub async fn profile(
+        _: IpRateLimited,
         Path(user_id_or_name): Path<String>,
+        user: Option<AuthenticatedUser>,
         db: Database,
-    ) -> Result<Json<UserProfile>, AppError> {
-        Ok(Json(UserProfile::get(&db, user_id_or_name.into()).await?))
+        config: AppConfig,
+    ) -> Result<Response, AppError> {
+        let viewer = Viewer::from(user);
+        let user_id = UserId::from(user_id_or_name);
+        let requested_name = match &user_id {
+            UserId::Username(name) => Some(name.clone()),
+            UserId::Id(_) => None,
+        };
+        let user = match user_id.visible_user(&db, &viewer).await {
+            Ok(user) => user,
+            // logging in might help
+            Err(DatabaseError::NotFound) if matches!(viewer, Viewer::Anonymous) => {
+                return Err(AppError::NotLoggedIn);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        if requested_name.is_some_and(|name| name != user.username) {
+            // relative to `/users/`
+            return Ok((
+                StatusCode::MOVED_PERMANENTLY,
+                [(LOCATION, encode_path_segment(&user.username))],
+            )
+                .into_response());
+        }
+        let profile = UserProfile::from_user(&db, user, &viewer, &config).await?;
+        Ok(Json(profile).into_response())
+    }
+
+    /// member directory, optionally searching by username, about and tags
+    async fn list(
+        Query(query): Query<UserListQuery>,
+        user: AuthenticatedUser,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<Json<UserListPayload>, AppError> {
+        Ok(Json(query.list(&db, &Viewer::from(&user), &config).await?))
+    }
+
+    /// look up multiple profiles by id and username at once
+    pub async fn lookup_for_service(
+        _: AuthenticatedService,
+        Query(query): Query<UserLookupQuery>,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<Json<Vec<UserProfile>>, AppError> {
+        let user_ids = query.into_user_ids()?;
+        Ok(Json(
+            UserProfile::get_many(&db, user_ids, &Viewer::Service, &config).await?,
+        ))
     }
 
     async fn patch(
         Path(user_id): Path<u32>,
         authenticated_user: AuthenticatedUser,
+        session: Session,
         db: Database,
         Json(payload): Json<UserPatchPayload>,
     ) -> Result<Json<User>, AppError> {
@@ -39,6 +166,134 @@ impl UserHandler {
             return Err(AppError::Forbidden);
         }
 
-        Ok(Json(db.users().patch(user_id, payload.into()).await?))
+        let actor = authenticated_user.actor(&session).await?;
+        Ok(Json(payload.patch(user_id, &db, actor).await?))
+    }
+
+    /// add a tag from a self-assignable category to your own profile
+    async fn add_tag(
+        Path((user_id, tag_id)): Path<(u32, u32)>,
+        authenticated_user: AuthenticatedUser,
+        session: Session,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        if user_id != authenticated_user.id {
+            return Err(AppError::Forbidden);
+        }
+
+        let actor = authenticated_user.actor(&session).await?;
+        assign_with_policy(&db, user_id, tag_id, TagPolicy::SelfAssignable, actor).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// remove a tag from a self-assignable category from your own profile
+    async fn remove_tag(
+        Path((user_id, tag_id)): Path<(u32, u32)>,
+        authenticated_user: AuthenticatedUser,
+        session: Session,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        if user_id != authenticated_user.id {
+            return Err(AppError::Forbidden);
+        }
+
+        let actor = authenticated_user.actor(&session).await?;
+        unassign_with_policy(&db, user_id, tag_id, TagPolicy::SelfAssignable, actor).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// delete your own account and log out
+    async fn delete_current(
+        user: AuthenticatedUser,
+        session: Session,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<StatusCode, AppError> {
+        delete_account(&db, config, user.id).await?;
+        session.flush().await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// all users, private and disabled ones included, for admins
+    pub async fn list_records(
+        Query(query): Query<UserRecordQuery>,
+        db: Database,
+    ) -> Result<Json<UserRecordListPayload>, AppError> {
+        Ok(Json(query.list(&db).await?))
+    }
+
+    /// everything about a user, for admins
+    pub async fn record(
+        Path(user_id): Path<u32>,
+        db: Database,
+    ) -> Result<Json<UserRecordPayload>, AppError> {
+        Ok(Json(UserRecordPayload::get(&db, user_id).await?))
+    }
+
+    /// who changed what on the profile of any user, for admins
+    pub async fn history(
+        Path(user_id): Path<u32>,
+        Query(query): Query<ProfileHistoryQuery>,
+        db: Database,
+    ) -> Result<Json<ProfileHistoryPayload>, AppError> {
+        Ok(Json(query.list(&db, user_id).await?))
+    }
+
+    /// edit the about of a user or disable them, for admins
+    pub async fn moderate(
+        Path(user_id): Path<u32>,
+        Admin(admin): Admin,
+        db: Database,
+        Json(payload): Json<UserModerationPayload>,
+    ) -> Result<Json<UserRecordPayload>, AppError> {
+        Ok(Json(
+            payload
+                .moderate(&db, user_id, Actor::User(admin.id))
+                .await?,
+        ))
+    }
+
+    /// log a user out everywhere, for admins
+    pub async fn end_sessions(
+        Path(user_id): Path<u32>,
+        db: Database,
+    ) -> Result<StatusCode, AppError> {
+        db.users().by_id(user_id).await?;
+        db.users().revoke_sessions(user_id).await?;
+        Ok(StatusCode::NO_CONTENT)
     }
+
+    /// delete the account of any user, for admins
+    pub async fn delete(
+        Path(user_id): Path<u32>,
+        db: Database,
+        config: AppConfig,
+    ) -> Result<StatusCode, AppError> {
+        delete_account(&db, config, user_id).await?;
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// remove all rows and images of a user, and end their sessions
+///
+/// the account is gone once the rows are, images that can't be removed are only logged
+async fn delete_account(db: &Database, config: AppConfig, user_id: u32) -> Result<(), AppError> {
+    db.users().delete(user_id).await?;
+    if let Err(err) = ProfileImage::new(user_id, config).delete(SIZES).await {
+        tracing::error!("failed to remove images of deleted user {user_id}: {err}");
+    }
+    Ok(())
+}
+
+/// percent-encode everything but unreserved characters, so any username is a valid header value
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
 }
@@ -1,6 +1,8 @@
 use axum::Json;
 use serde::Serialize;
 
+use crate::extractors::IpRateLimited;
+
 pub struct VersionHandler;
 
 #[derive(Serialize)]
@@ -9,7 +11,7 @@ pub struct VersionResponse {
 }
 
 impl VersionHandler {
-    pub async fn get() -> Json<VersionResponse> {
+    pub async fn get(_: IpRateLimited) -> Json<VersionResponse> {
         Json(VersionResponse {
             version: env!("CARGO_PKG_VERSION"),
         })
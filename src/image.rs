@@ -6,6 +6,7 @@ use axum::{
     response::{IntoResponse, Response},
 };
 
+use database::Database;
 use rand::{SeedableRng, rngs::SmallRng, seq::IndexedRandom};
 use reqwest::header::CONTENT_TYPE;
 use svg::{
@@ -18,10 +19,18 @@ use tokio_util::io::ReaderStream;
 use crate::{config::AppConfig, error::AppError};
 
 pub struct ProfileImage {
-    user_id: u32,
+    owner: ImageOwner,
     config: AppConfig,
 }
 
+/// what an image belongs to, decides where it's stored
+#[derive(Debug, Clone, Copy)]
+pub enum ImageOwner {
+    User(u32),
+    Tag(u32),
+    TagCategory(u32),
+}
+
 pub struct DataImage {
     profile: ProfileImage,
 }
@@ -34,7 +43,11 @@ pub enum ResponseImage {
 impl ProfileImage {
     pub fn new(user_id: u32, config: AppConfig) -> Self {
         tracing::debug!("new user with id {user_id}");
-        Self { user_id, config }
+        Self::for_owner(ImageOwner::User(user_id), config)
+    }
+
+    pub fn for_owner(owner: ImageOwner, config: AppConfig) -> Self {
+        Self { owner, config }
     }
 
     pub async fn with_data(self, data: &[u8]) -> Result<DataImage, AppError> {
@@ -66,20 +79,43 @@ impl ProfileImage {
     }
 
     pub async fn get_with_placeholder(&self, size: u32) -> Result<ResponseImage, AppError> {
-        match self.get(size).await {
-            Err(AppError::NotFound) => Ok(ResponseImage::Placeholder(self.user_id)),
-            other => other,
+        match (self.get(size).await, self.owner) {
+            (Err(AppError::NotFound), ImageOwner::User(user_id)) => {
+                Ok(ResponseImage::Placeholder(user_id))
+            }
+            (other, _) => other,
+        }
+    }
+
+    /// remove the original and all given sizes, missing files are ignored
+    pub async fn delete(&self, sizes: &[u32]) -> Result<(), AppError> {
+        let paths = sizes.iter().map(|size| self.path(*size));
+        for path in paths.chain([self.path_orig()]) {
+            if let Err(e) = tokio::fs::remove_file(path).await
+                && e.kind() != ErrorKind::NotFound
+            {
+                Err(e)?;
+            }
         }
+        Ok(())
     }
 
     pub fn path_orig(&self) -> PathBuf {
-        self.config.image_path.join(self.user_id.to_string())
+        self.config.image_path.join(self.file_stem())
     }
 
     pub fn path(&self, size: u32) -> PathBuf {
-        let filename = format!("{}.{}.{}", self.user_id, size, "webp");
+        let filename = format!("{}.{}.{}", self.file_stem(), size, "webp");
         self.config.image_path.join(filename)
     }
+
+    fn file_stem(&self) -> String {
+        match self.owner {
+            ImageOwner::User(user_id) => user_id.to_string(),
+            ImageOwner::Tag(tag_id) => format!("tag-{tag_id}"),
+            ImageOwner::TagCategory(category_id) => format!("tag-category-{category_id}"),
+        }
+    }
 }
 
 impl DataImage {
@@ -164,6 +200,25 @@ impl DataImage {
     }
 }
 
+/// mark the users that uploaded an image before this was stored in the database
+///
+/// originals of profile images are named after the user id, returns how many were found
+pub async fn mark_user_images(db: &Database, config: &AppConfig) -> Result<usize, AppError> {
+    let mut user_ids = Vec::new();
+    let mut entries = tokio::fs::read_dir(&config.image_path).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if let Some(user_id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u32>().ok())
+        {
+            user_ids.push(user_id);
+        }
+    }
+    db.users().set_have_image(&user_ids).await?;
+    Ok(user_ids.len())
+}
+
 impl IntoResponse for ResponseImage {
     fn into_response(self) -> Response {
         match self {
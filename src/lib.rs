@@ -1,14 +1,18 @@
+use std::net::SocketAddr;
+
 use axum::{
     Router,
     extract::DefaultBodyLimit,
-    middleware::from_extractor,
-    routing::{get, patch, post},
+    middleware::{from_extractor_with_state, from_fn},
+    routing::{delete, get, patch, post, put},
 };
 use database::Database;
+use rate_limit::RateLimiter;
 use reqwest::StatusCode;
 use tokio::fs;
 use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
 use tower_sessions::{MemoryStore, SessionManagerLayer, cookie::SameSite};
+use usage::UsageRecorder;
 
 use crate::{
     config::AppConfig,
@@ -16,21 +20,37 @@ use crate::{
     extractors::{Admin, AuthenticatedUser},
     handlers::{
         achievement::AchievementHandler, auth::AuthHandler, image::ImageHandler,
-        service::ServiceHandler, user::UserHandler, version::VersionHandler,
+        impersonation::ImpersonationHandler, kudos::KudosHandler,
+        profile_field::ProfileFieldHandler, sandbox::SandboxHandler, service::ServiceHandler,
+        service_data::ServiceDataHandler, tag::TagHandler, unlock::UnlockHandler,
+        user::UserHandler, version::VersionHandler,
     },
 };
 
 pub mod config;
 pub mod dto;
 pub mod error;
+pub mod export;
 pub mod extractors;
 pub mod handlers;
 pub mod image;
+pub mod rate_limit;
+pub mod usage;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Database,
     pub config: AppConfig,
+    pub rate_limiter: RateLimiter,
+    pub usage: UsageRecorder,
+}
+
+/// one-off for databases from before `has_image` was stored, run with `cargo run -- mark-user-images`
+pub async fn mark_user_images(config: AppConfig) -> Result<(), AppError> {
+    let db = Database::create_connect_migrate(&config.database_url).await?;
+    let count = image::mark_user_images(&db, &config).await?;
+    tracing::info!("marked {count} users with a profile image");
+    Ok(())
 }
 
 pub async fn start_app(config: AppConfig) -> Result<(), AppError> {
@@ -40,14 +60,25 @@ pub async fn start_app(config: AppConfig) -> Result<(), AppError> {
     }
 
     let db = Database::create_connect_migrate(&config.database_url).await?;
+    // sessions are kept in memory and don't survive a restart
+    db.users().clear_sessions().await?;
+    db.impersonations().end_all().await?;
+
+    let usage = UsageRecorder::default();
+    tokio::spawn(usage.clone().flush_every(db.clone(), usage::FLUSH_INTERVAL));
 
-    let state = AppState { db, config };
+    let state = AppState {
+        db: db.clone(),
+        config,
+        rate_limiter: RateLimiter::default(),
+        usage: usage.clone(),
+    };
 
     // setup layers
     let sess_store = MemoryStore::default();
     let sess_mw = SessionManagerLayer::new(sess_store).with_same_site(SameSite::Lax);
     let app = Router::new()
-        .nest("/api", api_router())
+        .nest("/api", api_router(&state))
         .layer(sess_mw)
         .layer(DefaultBodyLimit::max(10_485_760))
         .layer(CompressionLayer::new())
@@ -57,18 +88,26 @@ pub async fn start_app(config: AppConfig) -> Result<(), AppError> {
 
     // start server
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    // requests since the last flush would be lost otherwise
+    usage.flush(&db).await?;
 
     Ok(())
 }
 
-pub fn api_router() -> Router<AppState> {
+/// the state is needed up front to check sessions in the route layers
+pub fn api_router(state: &AppState) -> Router<AppState> {
     Router::new()
         .merge(open_routes())
-        .merge(authenticated_routes())
-        .nest("/admin", admin_routes())
+        .merge(authenticated_routes(state))
+        .nest("/admin", admin_routes(state))
+        .nest("/service", service_routes())
         .fallback(get(|| async { StatusCode::NOT_FOUND }))
 }
 
@@ -76,23 +115,39 @@ fn open_routes() -> Router<AppState> {
     Router::new()
         .route("/login", get(AuthHandler::login))
         .route("/oauth/callback", get(AuthHandler::callback))
+        .route("/users/{id}", get(UserHandler::profile))
+        .route("/impersonation", delete(ImpersonationHandler::stop))
         .route("/image/{id}", get(ImageHandler::get))
+        .route("/image/tags/{id}", get(ImageHandler::get_tag_icon))
+        .route(
+            "/image/tags/categories/{id}",
+            get(ImageHandler::get_tag_category_icon),
+        )
         .route("/version", get(VersionHandler::get))
 }
 
-fn authenticated_routes() -> Router<AppState> {
+fn authenticated_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .nest("/users", UserHandler::router())
         .route("/logout", get(AuthHandler::logout))
+        .route("/tags", get(TagHandler::directory))
+        .route("/tags/{name}/users", get(TagHandler::members))
+        .route("/profile-fields", get(ProfileFieldHandler::get_all))
         .route(
             "/image",
             post(ImageHandler::post).delete(ImageHandler::delete),
         )
-        .route_layer(from_extractor::<AuthenticatedUser>())
+        .route(
+            "/services/{id}/sandbox",
+            get(SandboxHandler::get_for_service).delete(SandboxHandler::delete_for_service),
+        )
+        .route_layer(from_extractor_with_state::<AuthenticatedUser, _>(
+            state.clone(),
+        ))
         .route("/services", get(ServiceHandler::get_user))
 }
 
-fn admin_routes() -> Router<AppState> {
+fn admin_routes(state: &AppState) -> Router<AppState> {
     Router::new()
         .route(
             "/services",
@@ -104,7 +159,99 @@ fn admin_routes() -> Router<AppState> {
             get(AchievementHandler::get_for_service).post(AchievementHandler::post_for_service),
         )
         .route("/services/{id}/apikey", post(ServiceHandler::api_key))
-        .route_layer(from_extractor::<Admin>())
+        .route(
+            "/services/{id}/testkey",
+            post(ServiceHandler::post_test_api_key).delete(ServiceHandler::delete_test_api_key),
+        )
+        .route(
+            "/services/{id}/ratelimit",
+            get(ServiceHandler::get_rate_limit).put(ServiceHandler::put_rate_limit),
+        )
+        .route("/services/{id}/usage", get(ServiceHandler::usage))
+        .route("/tags", get(TagHandler::get_all).post(TagHandler::post))
+        .route(
+            "/tags/{id}",
+            patch(TagHandler::patch).delete(TagHandler::delete),
+        )
+        .route(
+            "/tags/categories",
+            get(TagHandler::get_categories).post(TagHandler::post_category),
+        )
+        .route(
+            "/tags/categories/{id}",
+            patch(TagHandler::patch_category).delete(TagHandler::delete_category),
+        )
+        .route(
+            "/tags/{id}/icon",
+            post(ImageHandler::post_tag_icon).delete(ImageHandler::delete_tag_icon),
+        )
+        .route(
+            "/tags/categories/{id}/icon",
+            post(ImageHandler::post_tag_category_icon)
+                .delete(ImageHandler::delete_tag_category_icon),
+        )
+        .route(
+            "/tags/assignments/{id}",
+            patch(TagHandler::patch_assignment).delete(TagHandler::delete_assignment),
+        )
+        .route(
+            "/profile-fields",
+            get(ProfileFieldHandler::get_all).post(ProfileFieldHandler::post),
+        )
+        .route(
+            "/profile-fields/{id}",
+            patch(ProfileFieldHandler::patch).delete(ProfileFieldHandler::delete),
+        )
+        .route("/users", get(UserHandler::list_records))
+        .route(
+            "/users/{id}",
+            get(UserHandler::record)
+                .patch(UserHandler::moderate)
+                .delete(UserHandler::delete),
+        )
+        .route("/users/{id}/image", delete(ImageHandler::delete_for_user))
+        .route("/users/{id}/sessions", delete(UserHandler::end_sessions))
+        .route("/users/{id}/history", get(UserHandler::history))
+        .route("/users/{id}/impersonate", post(ImpersonationHandler::start))
+        .route("/impersonations", get(ImpersonationHandler::list))
+        .route("/kudos", get(KudosHandler::list_all))
+        .route("/kudos/{id}", delete(KudosHandler::remove))
+        .route(
+            "/users/{id}/tags",
+            get(TagHandler::get_assignments).post(TagHandler::post_assignment),
+        )
+        .route(
+            "/users/{id}/tags/{tag_id}",
+            put(TagHandler::assign).delete(TagHandler::unassign),
+        )
+        .route_layer(from_extractor_with_state::<Admin, _>(state.clone()))
+}
+
+/// routes for external services, every handler takes an `AuthenticatedService`
+fn service_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/achievements",
+            get(AchievementHandler::get_for_authenticated_service),
+        )
+        .route("/users", get(UserHandler::lookup_for_service))
+        .route(
+            "/users/{id}/tags/{tag_id}",
+            put(TagHandler::assign_for_service).delete(TagHandler::unassign_for_service),
+        )
+        .route(
+            "/users/{id}/unlocks",
+            get(UnlockHandler::get_for_user).post(UnlockHandler::post_for_user),
+        )
+        .route("/users/{id}/data", get(ServiceDataHandler::get_all))
+        .route(
+            "/users/{id}/data/{key}",
+            get(ServiceDataHandler::get)
+                .put(ServiceDataHandler::put)
+                .delete(ServiceDataHandler::delete),
+        )
+        .route("/sandbox", delete(SandboxHandler::delete_own))
+        .route_layer(from_fn(usage::record_usage))
 }
 
 #[allow(clippy::expect_used)]
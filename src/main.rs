@@ -1,6 +1,6 @@
 use std::error::Error;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
-use zpi::{config::AppConfig, start_app};
+use zpi::{config::AppConfig, mark_user_images, start_app};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -13,7 +13,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .with(EnvFilter::from_env("LOG_LEVEL"))
         .init();
 
-    start_app(config).await?;
+    match std::env::args().nth(1).as_deref() {
+        Some("mark-user-images") => mark_user_images(config).await?,
+        _ => start_app(config).await?,
+    }
 
     Ok(())
 }
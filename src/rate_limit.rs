@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// idle buckets are dropped once this many are being tracked
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+/// buckets that weren't used for this long are dropped first
+const MAX_IDLE_TIME: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    Service(u32),
+    Ip(IpAddr),
+    /// kudos given by a user
+    Kudos(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// maximum amount of requests that can be made at once
+    pub burst: u32,
+    /// amount of requests that are refilled every minute
+    pub per_minute: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitUsage {
+    /// requests that can still be made right now
+    pub remaining: u32,
+    /// requests that were refused since the bucket was created
+    pub rejected: u64,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    rejected: u64,
+    last_refill: Instant,
+}
+
+/// token bucket rate limiter, shared between all requests
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<RateLimitKey, TokenBucket>>>,
+}
+
+impl RateLimiter {
+    /// take one token from the bucket for this key
+    ///
+    /// returns `AppError::TooManyRequests` with the seconds to wait if the bucket is empty
+    pub fn acquire(&self, key: RateLimitKey, limit: RateLimit) -> Result<(), AppError> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if buckets.len() >= MAX_TRACKED_BUCKETS && !buckets.contains_key(&key) {
+            evict(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry(key).or_insert_with(|| TokenBucket {
+            tokens: limit.burst as f64,
+            rejected: 0,
+            last_refill: now,
+        });
+        bucket.refill(now, limit);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            bucket.rejected += 1;
+            Err(AppError::TooManyRequests(
+                bucket.retry_after(limit).as_secs(),
+            ))
+        }
+    }
+
+    /// current state of the bucket for this key, without taking a token
+    pub fn usage(&self, key: RateLimitKey, limit: RateLimit) -> RateLimitUsage {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        match buckets.get_mut(&key) {
+            Some(bucket) => {
+                bucket.refill(Instant::now(), limit);
+                RateLimitUsage {
+                    remaining: bucket.tokens as u32,
+                    rejected: bucket.rejected,
+                }
+            }
+            None => RateLimitUsage {
+                remaining: limit.burst,
+                rejected: 0,
+            },
+        }
+    }
+}
+
+/// drop buckets by how long they have been idle, service buckets are kept for their usage
+///
+/// if every bucket was used recently, the least recently used half is dropped
+fn evict(buckets: &mut HashMap<RateLimitKey, TokenBucket>, now: Instant) {
+    let evictable = |key: &RateLimitKey| !matches!(key, RateLimitKey::Service(_));
+    buckets.retain(|key, bucket| {
+        !evictable(key) || now.duration_since(bucket.last_refill) < MAX_IDLE_TIME
+    });
+    if buckets.len() < MAX_TRACKED_BUCKETS {
+        return;
+    }
+
+    let mut last_used: Vec<Instant> = buckets
+        .iter()
+        .filter(|(key, _)| evictable(key))
+        .map(|(_, bucket)| bucket.last_refill)
+        .collect();
+    last_used.sort_unstable();
+    if let Some(&cutoff) = last_used.get(last_used.len() / 2) {
+        buckets.retain(|key, bucket| !evictable(key) || bucket.last_refill > cutoff);
+    }
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant, limit: RateLimit) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        let refilled = elapsed * limit.per_minute as f64 / 60.0;
+        self.tokens = (self.tokens + refilled).min(limit.burst as f64);
+        self.last_refill = now;
+    }
+
+    /// time until the next token is available, rounded up to whole seconds
+    fn retry_after(&self, limit: RateLimit) -> Duration {
+        if limit.per_minute == 0 {
+            return Duration::from_secs(60);
+        }
+        let missing = 1.0 - self.tokens;
+        let seconds = (missing * 60.0 / limit.per_minute as f64).ceil().max(1.0);
+        Duration::from_secs(seconds as u64)
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock, PoisonError},
+    time::Duration,
+};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use database::{
+    Database,
+    error::DatabaseError,
+    models::{service::Namespace, service_usage::UsageCount},
+};
+
+/// how often counted requests are written to the database
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// service, namespace, hour, endpoint and status of a request
+type UsageKey = (u32, Namespace, DateTime<Utc>, String, u16);
+/// amount of requests and the last one, per key
+type PendingUsage = HashMap<UsageKey, (u32, DateTime<Utc>)>;
+
+/// counts service requests in memory, they are written to the database in batches
+#[derive(Debug, Clone, Default)]
+pub struct UsageRecorder {
+    pending: Arc<Mutex<PendingUsage>>,
+}
+
+impl UsageRecorder {
+    pub fn record(&self, service_id: u32, namespace: Namespace, endpoint: String, status: u16) {
+        let now = Utc::now();
+        let hour = now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now);
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        let (count, last_called_at) = pending
+            .entry((service_id, namespace, hour, endpoint, status))
+            .or_insert((0, now));
+        *count += 1;
+        *last_called_at = now;
+    }
+
+    /// write everything counted so far, it's counted again if writing fails
+    pub async fn flush(&self, db: &Database) -> Result<(), DatabaseError> {
+        let pending =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(PoisonError::into_inner));
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let counts: Vec<UsageCount> = pending
+            .iter()
+            .map(
+                |((service_id, namespace, hour, endpoint, status), (count, last_called_at))| {
+                    UsageCount {
+                        service_id: *service_id,
+                        namespace: *namespace,
+                        hour: *hour,
+                        endpoint: endpoint.clone(),
+                        status: *status,
+                        count: *count,
+                        last_called_at: *last_called_at,
+                    }
+                },
+            )
+            .collect();
+        if let Err(err) = db.service_usage().record(&counts).await {
+            let mut current = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            for (key, (count, last_called_at)) in pending {
+                let entry = current.entry(key).or_insert((0, last_called_at));
+                entry.0 += count;
+                entry.1 = entry.1.max(last_called_at);
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// keep writing counted requests in the background
+    pub async fn flush_every(self, db: Database, interval: Duration) {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            if let Err(err) = self.flush(&db).await {
+                tracing::error!("failed to record service usage: {err}");
+            }
+        }
+    }
+}
+
+/// filled in by `AuthenticatedService` so `record_usage` knows who made the request
+#[derive(Clone, Default)]
+pub struct UsageSlot(Arc<OnceLock<(UsageRecorder, u32, Namespace)>>);
+
+impl UsageSlot {
+    pub fn set(&self, recorder: UsageRecorder, service_id: u32, namespace: Namespace) {
+        let _ = self.0.set((recorder, service_id, namespace));
+    }
+}
+
+/// middleware that counts every service request per endpoint and status
+pub async fn record_usage(mut request: Request, next: Next) -> Response {
+    let endpoint = format!(
+        "{} {}",
+        request.method(),
+        request
+            .extensions()
+            .get::<MatchedPath>()
+            .map_or(request.uri().path(), MatchedPath::as_str)
+    );
+
+    let slot = UsageSlot::default();
+    request.extensions_mut().insert(slot.clone());
+
+    let response = next.run(request).await;
+
+    if let Some((recorder, service_id, namespace)) = slot.0.get() {
+        recorder.record(
+            *service_id,
+            *namespace,
+            endpoint,
+            response.status().as_u16(),
+        );
+    }
+
+    response
+}
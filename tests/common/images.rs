@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+/// copy of a test image as the original and a size of a user's profile image
+pub fn temp_images(name: &str, user_id: u32) -> PathBuf {
+    temp_dir(name, &[user_id.to_string(), format!("{user_id}.64.webp")])
+}
+
+/// copy of a test image as the original and a size of a tag category's icon
+pub fn temp_category_icon(name: &str, category_id: u32) -> PathBuf {
+    let stem = format!("tag-category-{category_id}");
+    temp_dir(name, &[stem.clone(), format!("{stem}.64.webp")])
+}
+
+fn temp_dir(name: &str, files: &[String]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("zpi-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for file in files {
+        std::fs::copy("tests/test_images/2.256.webp", dir.join(file)).unwrap();
+    }
+    dir
+}
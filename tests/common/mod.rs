@@ -1,5 +1,6 @@
-#![allow(dead_code)]
+#![allow(dead_code, clippy::unwrap_used, clippy::expect_used)]
 
+pub mod images;
 pub mod into_struct;
 pub mod router;
 pub mod test_objects;
@@ -1,8 +1,9 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 
 use axum::{
     Json, Router,
     body::Body,
+    extract::ConnectInfo,
     http::Request,
     response::{IntoResponse, Response},
 };
@@ -13,7 +14,11 @@ use sqlx::SqlitePool;
 use tower::ServiceExt;
 use tower_sessions::{MemoryStore, Session, SessionManagerLayer, session::Id};
 use zpi::{
-    AppState, api_router, config::AppConfig, extractors::authenticated_user::AuthenticatedUser,
+    AppState, api_router,
+    config::AppConfig,
+    extractors::authenticated_user::AuthenticatedUser,
+    rate_limit::{RateLimit, RateLimiter},
+    usage::UsageRecorder,
 };
 
 #[derive(Clone)]
@@ -24,40 +29,33 @@ pub struct AuthenticatedRouter {
 
 impl AuthenticatedRouter {
     pub async fn new(db: SqlitePool) -> Self {
-        let _ = dotenvy::dotenv();
+        Self::with_image_path(db, PathBuf::from("./tests/test_images")).await
+    }
+
+    /// for tests that change images, so the shared test images are left alone
+    pub async fn with_image_path(db: SqlitePool, image_path: PathBuf) -> Self {
         let store = Arc::new(MemoryStore::default());
 
         let session_id = {
             let session = Session::new(Some(Id(1)), store.clone(), None);
-            session
-                .insert(
-                    "user",
-                    AuthenticatedUser {
-                        id: 1,
-                        username: "cheese".to_string(),
-                        admin: true,
-                    },
-                )
-                .await
-                .unwrap();
+            AuthenticatedUser {
+                id: 1,
+                username: "cheese".to_string(),
+                admin: true,
+            }
+            .login(&session)
+            .await
+            .unwrap();
             session.save().await.unwrap();
             session.id().unwrap()
         };
 
-        let session_layer = SessionManagerLayer::new(Arc::into_inner(store).unwrap())
-            .with_secure(false)
-            .with_same_site(tower_sessions::cookie::SameSite::Lax);
-
-        let mut config = AppConfig::load().unwrap();
-        config.image_path = PathBuf::from("./tests/test_images");
-
-        let state = AppState {
-            db: Database::new(db),
-            config,
-        };
+        let mut config = test_config();
+        config.image_path = image_path;
+        let (router, _) = app(db, config, Arc::into_inner(store).unwrap());
 
         Self {
-            router: api_router().layer(session_layer).with_state(state),
+            router,
             cookie: format!("id={}", session_id),
         }
     }
@@ -83,6 +81,20 @@ impl AuthenticatedRouter {
         self.request(Method::POST, path, Some(body)).await
     }
 
+    /// send a put request to an endpoint on this router
+    ///
+    /// must have a leading "/"
+    pub async fn put<T: Serialize>(self, path: &str, body: T) -> Response<Body> {
+        self.request(Method::PUT, path, Some(body)).await
+    }
+
+    /// send a delete request to an endpoint on this router
+    ///
+    /// must have a leading "/"
+    pub async fn delete(self, path: &str) -> Response<Body> {
+        self.request(Method::DELETE, path, None::<()>).await
+    }
+
     /// send a request to an endpoint on this router
     ///
     /// must have a leading "/"
@@ -106,30 +118,34 @@ impl AuthenticatedRouter {
         self.router.oneshot(request.unwrap()).await.unwrap()
     }
 }
+
+#[derive(Clone)]
 pub struct UnauthenticatedRouter {
     router: Router,
 }
 
 impl UnauthenticatedRouter {
     pub async fn new(db: SqlitePool) -> Self {
-        let _ = dotenvy::dotenv();
-        let store = MemoryStore::default();
-
-        let session_layer = SessionManagerLayer::new(store)
-            .with_secure(false)
-            .with_same_site(tower_sessions::cookie::SameSite::Lax);
+        Self::with_config(db, test_config()).await
+    }
 
-        let mut config = AppConfig::load().unwrap();
-        config.image_path = PathBuf::from("./tests/test_images");
+    pub async fn with_ip_rate_limit(db: SqlitePool, ip_rate_limit: RateLimit) -> Self {
+        let mut config = test_config();
+        config.ip_rate_limit = ip_rate_limit;
+        Self::with_config(db, config).await
+    }
 
-        let state = AppState {
-            db: Database::new(db),
-            config,
-        };
+    /// for tests that need images the shared test images don't have
+    pub async fn with_image_path(db: SqlitePool, image_path: PathBuf) -> Self {
+        let mut config = test_config();
+        config.image_path = image_path;
+        Self::with_config(db, config).await
+    }
 
-        Self {
-            router: api_router().layer(session_layer).with_state(state),
-        }
+    /// for tests that need a config the other constructors don't give
+    pub async fn with_config(db: SqlitePool, config: AppConfig) -> Self {
+        let (router, _) = app(db, config, MemoryStore::default());
+        Self { router }
     }
 
     /// send a request to an endpoint on this router
@@ -141,4 +157,131 @@ impl UnauthenticatedRouter {
             .await
             .unwrap()
     }
+
+    /// send a request like it came in over a connection from this address
+    ///
+    /// must have a leading "/"
+    pub async fn get_from(self, path: &str, addr: SocketAddr) -> Response<Body> {
+        let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+        request.extensions_mut().insert(ConnectInfo(addr));
+        self.router.oneshot(request).await.unwrap()
+    }
+
+    /// send a request like it came in through a proxy that forwarded it for these ips
+    ///
+    /// must have a leading "/"
+    pub async fn get_forwarded(
+        self,
+        path: &str,
+        proxy: SocketAddr,
+        forwarded_for: &str,
+    ) -> Response<Body> {
+        let mut request = Request::builder()
+            .uri(path)
+            .header("X-Forwarded-For", forwarded_for)
+            .body(Body::empty())
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(proxy));
+        self.router.oneshot(request).await.unwrap()
+    }
+}
+
+#[derive(Clone)]
+pub struct ServiceRouter {
+    router: Router,
+    api_key: String,
+    db: Database,
+    usage: UsageRecorder,
+}
+
+impl ServiceRouter {
+    pub async fn new(db: SqlitePool, api_key: &str) -> Self {
+        let (router, state) = app(db, test_config(), MemoryStore::default());
+
+        Self {
+            db: state.db,
+            usage: state.usage,
+            router,
+            api_key: api_key.to_string(),
+        }
+    }
+
+    /// write the usage counted by this router, like the app does every so often
+    pub async fn flush_usage(&self) {
+        self.usage.flush(&self.db).await.unwrap();
+    }
+
+    /// send a request to an endpoint on this router, using the api key as bearer token
+    ///
+    /// must have a leading "/"
+    pub async fn get(self, path: &str) -> Response<Body> {
+        self.request(Method::GET, path, None::<()>).await
+    }
+
+    /// send a post request to an endpoint on this router
+    ///
+    /// must have a leading "/"
+    pub async fn post<T: Serialize>(self, path: &str, body: T) -> Response<Body> {
+        self.request(Method::POST, path, Some(body)).await
+    }
+
+    /// send a put request to an endpoint on this router
+    ///
+    /// must have a leading "/"
+    pub async fn put<T: Serialize>(self, path: &str, body: T) -> Response<Body> {
+        self.request(Method::PUT, path, Some(body)).await
+    }
+
+    /// send a delete request to an endpoint on this router
+    ///
+    /// must have a leading "/"
+    pub async fn delete(self, path: &str) -> Response<Body> {
+        self.request(Method::DELETE, path, None::<()>).await
+    }
+
+    async fn request<T: Serialize>(
+        self,
+        method: Method,
+        path: &str,
+        body: Option<T>,
+    ) -> Response<Body> {
+        let request_builder = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.api_key));
+
+        let request = match body {
+            Some(body) => request_builder
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Json(body).into_response().into_body()),
+            None => request_builder.body(Body::empty()),
+        };
+        self.router.oneshot(request.unwrap()).await.unwrap()
+    }
+}
+
+/// the config from the environment, with the shared test images
+fn test_config() -> AppConfig {
+    let _ = dotenvy::dotenv();
+    let mut config = AppConfig::load().unwrap();
+    config.image_path = PathBuf::from("./tests/test_images");
+    config
+}
+
+/// the api with its state, like the app serves it
+fn app(db: SqlitePool, config: AppConfig, store: MemoryStore) -> (Router, AppState) {
+    let session_layer = SessionManagerLayer::new(store)
+        .with_secure(false)
+        .with_same_site(tower_sessions::cookie::SameSite::Lax);
+
+    let state = AppState {
+        db: Database::new(db),
+        config,
+        rate_limiter: RateLimiter::default(),
+        usage: UsageRecorder::default(),
+    };
+    let router = api_router(&state)
+        .layer(session_layer)
+        .with_state(state.clone());
+    (router, state)
 }
@@ -1,4 +1,7 @@
-use database::models::{tag::Tag, user::User};
+use database::models::{
+    tag::UserTag,
+    user::{FieldVisibility, User, Visibility},
+};
 use zpi::{
     dto::{
         achievement::AchievementPayload,
@@ -25,6 +28,8 @@ impl TestObjects {
             id: 1,
             username: "cheese".to_string(),
             about: "Just a test user, doing its job... and fantasizing about a life outside the test environment.".to_string(),
+            visibility: Visibility::Members,
+            field_visibility: FieldVisibility::default(),
         }
     }
 
@@ -33,6 +38,8 @@ impl TestObjects {
             id: 2,
             username: "wafel".into(),
             about: "I like cheese.".into(),
+            visibility: Visibility::Members,
+            field_visibility: FieldVisibility::default(),
         }
     }
 
@@ -41,7 +48,15 @@ impl TestObjects {
             id: 1,
             username: "cheese".into(),
             about: "Just a test user, doing its job... and fantasizing about a life outside the test environment.".to_string(),
+            about_html: "<p>Just a test user, doing its job... and fantasizing about a life outside the test environment.</p>\n".to_string(),
             tags: Vec::new(),
+            former_tags: Vec::new(),
+            links: Vec::new(),
+            fields: Vec::new(),
+            followers: 0,
+            following: 0,
+            kudos: 0,
+            recent_kudos: Vec::new(),
         }
     }
 
@@ -50,29 +65,45 @@ impl TestObjects {
             id: 2,
             username: "wafel".into(),
             about: "I like cheese.".into(),
+            about_html: "<p>I like cheese.</p>\n".into(),
             tags: Self::tags(),
+            former_tags: Vec::new(),
+            links: Vec::new(),
+            fields: Vec::new(),
+            followers: 0,
+            following: 0,
+            kudos: 0,
+            recent_kudos: Vec::new(),
         }
     }
 
-    fn tags() -> Vec<Tag> {
+    fn tags() -> Vec<UserTag> {
         vec![Self::tag_1(), Self::tag_2()]
     }
 
-    fn tag_1() -> Tag {
-        Tag {
-            id: 1,
+    fn tag_1() -> UserTag {
+        UserTag {
+            tag_id: 1,
             name: "bestuur".into(),
             category: "bestuur".into(),
             description: Some("Ik ben huidig bestuur".into()),
+            color: "#A0A0A0".into(),
+            icon: false,
+            valid_from: None,
+            valid_until: None,
         }
     }
 
-    fn tag_2() -> Tag {
-        Tag {
-            id: 2,
+    fn tag_2() -> UserTag {
+        UserTag {
+            tag_id: 2,
             name: "boekentoren".into(),
             category: "toren".into(),
             description: Some("Ik ben een boekentoren".into()),
+            color: "#A0A0A0".into(),
+            icon: false,
+            valid_from: None,
+            valid_until: None,
         }
     }
 
@@ -85,6 +116,8 @@ impl TestObjects {
             id: 1,
             name: "zpi".to_string(),
             api_key: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_string(),
+            test_api_key: None,
+            owner_id: None,
         }
     }
 
@@ -93,6 +126,8 @@ impl TestObjects {
             id: 2,
             name: "zodom".to_string(),
             api_key: "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_string(),
+            test_api_key: None,
+            owner_id: None,
         }
     }
 
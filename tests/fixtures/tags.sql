@@ -1,8 +1,8 @@
 INSERT INTO tag_category
-    (id, name)
+    (id, name, policy, max_per_user)
 VALUES
-    (1, 'bestuur'),
-    (2, 'toren');
+    (1, 'bestuur', 'admin_only', NULL),
+    (2, 'toren', 'self_assignable', 1);
 
 INSERT INTO tag
     (id, name, description, category)
@@ -0,0 +1,121 @@
+use database::{
+    Database,
+    models::{
+        profile_change::Actor,
+        user::{FieldVisibility, UserPatch, Visibility},
+    },
+};
+use reqwest::StatusCode;
+use sqlx::SqlitePool;
+use zpi::dto::{
+    follow::{FeedItem, FeedPayload, FollowListPayload},
+    user::UserProfile,
+};
+
+use crate::common::{into_struct::IntoStruct, router::AuthenticatedRouter};
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn follow_users(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+
+    let response = router.clone().put("/users/1/follow", "").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = router.clone().put("/users/42/follow", "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    for _ in 0..2 {
+        let response = router.clone().put("/users/2/follow", "").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+
+    let response = router.clone().get("/users/wafel").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!((profile.followers, profile.following), (1, 0));
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!((profile.followers, profile.following), (0, 1));
+
+    let response = router.clone().get("/users/wafel/followers").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: FollowListPayload = response.into_struct().await;
+    assert_eq!(page.total, 1);
+    assert_eq!(page.users[0].username, "cheese");
+    let response = router.clone().get("/users/1/following").await;
+    let page: FollowListPayload = response.into_struct().await;
+    assert_eq!(page.users[0].username, "wafel");
+    let response = router.clone().get("/users/2/following").await;
+    let page: FollowListPayload = response.into_struct().await;
+    assert_eq!(page.total, 0);
+
+    // unfollowing twice is fine
+    for _ in 0..2 {
+        let response = router.clone().delete("/users/2/follow").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+    let response = router.get("/users/wafel").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.followers, 0);
+}
+
+#[sqlx::test(fixtures("users", "services", "achievements", "unlocks"))]
+#[test_log::test]
+async fn feed_of_followed_users(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let router = AuthenticatedRouter::new(db_pool).await;
+    router.clone().put("/users/2/follow", "").await;
+
+    let response = router.clone().get("/users/me/feed").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let feed: FeedPayload = response.into_struct().await;
+    assert_eq!(feed.total, 1);
+    assert!(matches!(
+        feed.items[0],
+        FeedItem::Unlock {
+            user_id: 2,
+            goal_id: 3,
+            achievement_id: 2,
+            ..
+        }
+    ));
+
+    let about = |about: &str| UserPatch {
+        about: Some(about.into()),
+        ..Default::default()
+    };
+    db.users()
+        .patch(2, about("Waffles > cheese"), Actor::User(2))
+        .await
+        .unwrap();
+    // changes by admins are not something the user did
+    db.users()
+        .patch(2, about("I like cheese."), Actor::User(1))
+        .await
+        .unwrap();
+    let response = router.clone().get("/users/me/feed").await;
+    let feed: FeedPayload = response.into_struct().await;
+    assert_eq!(feed.total, 2);
+    assert!(matches!(
+        &feed.items[0],
+        FeedItem::ProfileUpdate { username, field, .. } if username == "wafel" && field == "about"
+    ));
+
+    // private parts of a profile stay private
+    let private = UserPatch {
+        field_visibility: Some(FieldVisibility {
+            achievements: Some(Visibility::Private),
+            about: Some(Visibility::Private),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    db.users().patch(2, private, Actor::User(2)).await.unwrap();
+    let response = router.clone().get("/users/me/feed").await;
+    let feed: FeedPayload = response.into_struct().await;
+    assert_eq!(feed.total, 0);
+    assert!(feed.items.is_empty());
+
+    let response = router.get("/users/me/feed?limit=0").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
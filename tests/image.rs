@@ -1,7 +1,10 @@
-use reqwest::StatusCode;
+use reqwest::{StatusCode, header::CONTENT_TYPE};
 use sqlx::SqlitePool;
 
-use crate::common::router::UnauthenticatedRouter;
+use crate::common::{
+    images::temp_category_icon,
+    router::{AuthenticatedRouter, UnauthenticatedRouter},
+};
 
 mod common;
 
@@ -36,3 +39,52 @@ async fn get_image_no_placeholder(db_pool: SqlitePool) {
 
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[sqlx::test(fixtures("users", "tags"))]
+async fn get_tag_icon_from_category(db_pool: SqlitePool) {
+    let images = temp_category_icon("tag-icon-from-category", 2);
+    let router = UnauthenticatedRouter::with_image_path(db_pool, images.clone()).await;
+    let response = router.clone().get("/image/tags/3").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().get("/image/tags/categories/2?size=50").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().get("/image/tags/1").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = router.get("/image/tags/42").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(images).unwrap();
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn get_image_respects_visibility(db_pool: SqlitePool) {
+    let router = UnauthenticatedRouter::new(db_pool.clone()).await;
+
+    // members-only profiles hide their image from visitors
+    let response = router.clone().get("/image/2?placeholder=false").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = router.clone().get("/image/2").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "image/svg+xml");
+
+    sqlx::query("UPDATE user SET visibility = 'public' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let response = router.clone().get("/image/2?placeholder=false").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    sqlx::query("UPDATE user SET image_visibility = 'members' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let response = router.get("/image/2?placeholder=false").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.get("/image/2?placeholder=false").await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
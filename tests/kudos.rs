@@ -0,0 +1,260 @@
+use database::{
+    Database,
+    error::DatabaseError,
+    models::kudos::{Kudos, KudosCreate},
+};
+use reqwest::StatusCode;
+use sqlx::SqlitePool;
+use zpi::dto::{
+    kudos::{KudosCreatePayload, KudosHidePayload, KudosListPayload, UserKudosPayload},
+    user::UserProfile,
+};
+
+use crate::common::{
+    into_struct::IntoStruct,
+    router::{AuthenticatedRouter, UnauthenticatedRouter},
+};
+
+mod common;
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn give_kudos(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let kudos = |tag_id: Option<u32>, skill: Option<&str>| KudosCreatePayload {
+        message: "helped me with Rust".into(),
+        tag_id,
+        skill: skill.map(String::from),
+    };
+
+    let response = router
+        .clone()
+        .post("/users/1/kudos", kudos(None, Some("rust")))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = router
+        .clone()
+        .post("/users/2/kudos", kudos(None, None))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = router
+        .clone()
+        .post("/users/2/kudos", kudos(Some(42), None))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = router
+        .clone()
+        .post("/users/2/kudos", kudos(Some(3), None))
+        .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    // only once per pair of users
+    let response = router
+        .clone()
+        .post("/users/2/kudos", kudos(None, Some("rust")))
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    // kudos that can't be given don't count towards the rate limit
+    for _ in 0..5 {
+        let response = router
+            .clone()
+            .post("/users/2/kudos", kudos(None, Some("rust")))
+            .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    let response = router.clone().get("/users/wafel").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.kudos, 1);
+    assert_eq!(profile.recent_kudos[0].giver_username, "cheese");
+    assert_eq!(profile.recent_kudos[0].tag_name.as_deref(), Some("eiffel"));
+
+    let response = router.clone().get("/users/wafel/kudos").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: UserKudosPayload = response.into_struct().await;
+    assert_eq!(page.total, 1);
+    assert_eq!(page.kudos[0].message, "helped me with Rust");
+
+    let response = router.clone().delete("/users/2/kudos").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.clone().delete("/users/2/kudos").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = router.clone().get("/users/wafel").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.kudos, 0);
+    assert!(profile.recent_kudos.is_empty());
+
+    // withdrawn kudos can be given again, every time counts towards the rate limit
+    for _ in 0..4 {
+        let response = router
+            .clone()
+            .post("/users/2/kudos", kudos(None, Some("rust")))
+            .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let given: Kudos = response.into_struct().await;
+        assert_eq!(given.skill.as_deref(), Some("rust"));
+        assert!(given.tag_id.is_none() && given.withdrawn_at.is_none());
+        let response = router.clone().delete("/users/2/kudos").await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    }
+    let response = router
+        .clone()
+        .post("/users/2/kudos", kudos(None, Some("rust")))
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = router.post("/users/2/kudos", kudos(None, None)).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn hide_and_remove_kudos(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let received = db
+        .kudos()
+        .create(KudosCreate {
+            giver_id: 2,
+            recipient_id: 1,
+            message: "explained borrowing".into(),
+            tag_id: None,
+            skill: Some("rust".into()),
+        })
+        .await
+        .unwrap();
+
+    // only the recipient can hide kudos
+    let response = router
+        .clone()
+        .post(
+            "/users/2/kudos",
+            KudosCreatePayload {
+                message: "great waffles".into(),
+                skill: Some("baking".into()),
+                ..Default::default()
+            },
+        )
+        .await;
+    let given: Kudos = response.into_struct().await;
+    let response = router
+        .clone()
+        .patch(
+            &format!("/users/me/kudos/{}", given.id),
+            KudosHidePayload { hidden: true },
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = router
+        .clone()
+        .patch(
+            &format!("/users/me/kudos/{}", received.id),
+            KudosHidePayload { hidden: true },
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router.clone().get("/users/cheese").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.kudos, 0);
+    // the recipient still sees the kudos they hid
+    let response = router.clone().get("/users/1/kudos").await;
+    let page: UserKudosPayload = response.into_struct().await;
+    assert_eq!(page.total, 1);
+    assert!(page.kudos[0].hidden);
+
+    let response = router
+        .clone()
+        .delete(&format!("/admin/kudos/{}", given.id))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router
+        .clone()
+        .delete(&format!("/admin/kudos/{}", given.id))
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = router.clone().get("/users/wafel").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.kudos, 0);
+
+    // removed kudos can't be taken back and given again
+    let response = router.clone().delete("/users/2/kudos").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let again = db
+        .kudos()
+        .create(KudosCreate {
+            giver_id: 1,
+            recipient_id: 2,
+            message: "great waffles".into(),
+            tag_id: None,
+            skill: Some("baking".into()),
+        })
+        .await;
+    assert!(matches!(again, Err(DatabaseError::Conflict(_))));
+
+    // withdrawn kudos can't be hidden or shown anymore
+    db.kudos().withdraw(2, 1).await.unwrap();
+    let response = router
+        .clone()
+        .patch(
+            &format!("/users/me/kudos/{}", received.id),
+            KudosHidePayload { hidden: false },
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = router.clone().get("/admin/kudos?removed=true").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: KudosListPayload = response.into_struct().await;
+    assert_eq!(page.total, 1);
+    assert_eq!(page.kudos[0].id, given.id);
+    let response = router.get("/admin/kudos?user_id=1").await;
+    let page: KudosListPayload = response.into_struct().await;
+    assert_eq!(page.total, 2);
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn kudos_from_members_only_profiles(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    sqlx::query("UPDATE user SET visibility = 'public' WHERE id = 1")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    db.kudos()
+        .create(KudosCreate {
+            giver_id: 2,
+            recipient_id: 1,
+            message: "explained borrowing".into(),
+            tag_id: None,
+            skill: Some("rust".into()),
+        })
+        .await
+        .unwrap();
+
+    // wafel's profile needs a login, so do the kudos they gave
+    let anonymous = UnauthenticatedRouter::new(db_pool.clone()).await;
+    let response = anonymous.clone().get("/users/cheese").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.kudos, 0);
+    assert!(profile.recent_kudos.is_empty());
+
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let response = router.get("/users/cheese").await;
+    let profile: serde_json::Value = response.into_struct().await;
+    assert_eq!(profile["kudos"], 1);
+    let kudos = &profile["recent_kudos"][0];
+    assert_eq!(kudos["giver_username"], "wafel");
+    // what only admins see is left out
+    assert!(kudos.get("hidden_at").is_none());
+    assert!(kudos.get("removed_at").is_none());
+
+    sqlx::query("UPDATE user SET visibility = 'public' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let response = anonymous.get("/users/cheese").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.kudos, 1);
+    assert_eq!(profile.recent_kudos[0].giver_username, "wafel");
+}
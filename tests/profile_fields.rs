@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+
+use database::models::{profile_field::FieldKind, user::Visibility};
+use reqwest::StatusCode;
+use sqlx::SqlitePool;
+use zpi::dto::{
+    profile_field::{ProfileFieldCreatePayload, ProfileFieldPatchPayload, ProfileFieldPayload},
+    user::{UserPatchPayload, UserProfile},
+};
+
+use crate::common::{
+    into_struct::IntoStruct,
+    router::{AuthenticatedRouter, ServiceRouter},
+    test_objects::TestObjects,
+};
+
+mod common;
+
+fn field(name: &str, kind: FieldKind, options: &[&str]) -> ProfileFieldCreatePayload {
+    ProfileFieldCreatePayload {
+        name: name.into(),
+        label: name.to_uppercase(),
+        kind,
+        options: options.iter().map(|option| option.to_string()).collect(),
+        max_length: None,
+        visibility: None,
+        position: 0,
+    }
+}
+
+fn values(values: &[(&str, Option<&str>)]) -> UserPatchPayload {
+    UserPatchPayload {
+        fields: Some(
+            values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.map(str::to_string)))
+                .collect::<BTreeMap<_, _>>(),
+        ),
+        ..Default::default()
+    }
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn create_profile_fields(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+
+    let response = router
+        .clone()
+        .post(
+            "/admin/profile-fields",
+            field(
+                "pronouns",
+                FieldKind::Enum,
+                &["he/him", "she/her", "they/them"],
+            ),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let created: ProfileFieldPayload = response.into_struct().await;
+    assert_eq!(created.visibility, Visibility::Members);
+    assert_eq!(created.options.len(), 3);
+
+    let invalid = [
+        field("pronouns", FieldKind::Text, &[]),
+        field("Pronouns", FieldKind::Text, &[]),
+        field("year", FieldKind::Enum, &[]),
+        field("birthday", FieldKind::Date, &["2000-01-01"]),
+        field("options", FieldKind::Enum, &["a", "a"]),
+    ];
+    let expected = [
+        StatusCode::CONFLICT,
+        StatusCode::BAD_REQUEST,
+        StatusCode::BAD_REQUEST,
+        StatusCode::BAD_REQUEST,
+        StatusCode::BAD_REQUEST,
+    ];
+    for (payload, status) in invalid.into_iter().zip(expected) {
+        let name = payload.name.clone();
+        let response = router.clone().post("/admin/profile-fields", payload).await;
+        assert_eq!(response.status(), status, "{name}");
+    }
+
+    let body = ProfileFieldPatchPayload {
+        label: Some("Pronouns".into()),
+        max_length: Some(Some(10)),
+        ..Default::default()
+    };
+    let response = router
+        .clone()
+        .patch(&format!("/admin/profile-fields/{}", created.id), body)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = ProfileFieldPatchPayload {
+        label: Some("Pronouns".into()),
+        options: Some(vec!["he/him".into(), "she/her".into()]),
+        ..Default::default()
+    };
+    let response = router
+        .clone()
+        .patch(&format!("/admin/profile-fields/{}", created.id), body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().get("/profile-fields").await;
+    let fields: Vec<ProfileFieldPayload> = response.into_struct().await;
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].label, "Pronouns");
+    assert_eq!(fields[0].options, vec!["he/him", "she/her"]);
+
+    let response = router
+        .clone()
+        .delete(&format!("/admin/profile-fields/{}", created.id))
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.get("/profile-fields").await;
+    let fields: Vec<ProfileFieldPayload> = response.into_struct().await;
+    assert!(fields.is_empty());
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn patch_profile_field_values(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    for payload in [
+        field("pronouns", FieldKind::Enum, &["he/him", "they/them"]),
+        field("birthday", FieldKind::Date, &[]),
+        field("blog", FieldKind::Url, &[]),
+        field("motto", FieldKind::Text, &[]),
+    ] {
+        router.clone().post("/admin/profile-fields", payload).await;
+    }
+
+    let body = values(&[
+        ("pronouns", Some("they/them")),
+        ("birthday", Some("2000-02-29")),
+        ("blog", Some("zeus.gent/blog")),
+        ("motto", Some("  Lekker bezig  ")),
+    ]);
+    let response = router.clone().patch("/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    let fields: Vec<(&str, &str)> = profile
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.value.as_str()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("pronouns", "they/them"),
+            ("birthday", "2000-02-29"),
+            ("blog", "https://zeus.gent/blog"),
+            ("motto", "Lekker bezig"),
+        ]
+    );
+
+    let invalid = [
+        ("pronouns", "it/its"),
+        ("birthday", "2001-02-29"),
+        ("blog", "javascript:alert(1)"),
+        ("motto", "bell\u{7}"),
+        ("unknown", "value"),
+    ];
+    for (name, value) in invalid {
+        let response = router
+            .clone()
+            .patch("/users/1", values(&[(name, Some(value))]))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{name}");
+    }
+
+    let body = values(&[("pronouns", None), ("motto", Some(""))]);
+    router.clone().patch("/users/1", body).await;
+    let response = router.get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    let names: Vec<&str> = profile.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["birthday", "blog"]);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn private_profile_fields_are_hidden(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let mut birthday = field("birthday", FieldKind::Date, &[]);
+    birthday.visibility = Some(Visibility::Private);
+    let mut blog = field("blog", FieldKind::Url, &[]);
+    blog.visibility = Some(Visibility::Public);
+    for payload in [birthday, field("motto", FieldKind::Text, &[]), blog] {
+        router.clone().post("/admin/profile-fields", payload).await;
+    }
+    let body = values(&[
+        ("birthday", Some("2000-01-01")),
+        ("motto", Some("Hi")),
+        ("blog", Some("https://zeus.gent")),
+    ]);
+    router.clone().patch("/users/1", body).await;
+
+    let response = router.get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.fields.len(), 3);
+
+    // services only get the fields shown to everyone
+    sqlx::query("UPDATE user SET visibility = 'public' WHERE id = 1")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let router = ServiceRouter::new(db_pool.clone(), &TestObjects::admin_service_1().api_key).await;
+    let response = router.get("/service/users?ids=1").await;
+    let profiles: Vec<UserProfile> = response.into_struct().await;
+    let names: Vec<&str> = profiles[0].fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["blog"]);
+
+    let result = sqlx::query(
+        "INSERT INTO profile_field (name, label, kind, visibility)
+        VALUES ('secret', 'Secret', 'text', 'friends')",
+    )
+    .execute(&db_pool)
+    .await;
+    assert!(result.is_err());
+}
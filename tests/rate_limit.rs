@@ -0,0 +1,103 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use reqwest::StatusCode;
+use sqlx::SqlitePool;
+use zpi::{
+    config::AppConfig,
+    rate_limit::{RateLimit, RateLimitKey, RateLimiter},
+};
+
+use crate::common::router::UnauthenticatedRouter;
+
+mod common;
+
+#[sqlx::test]
+#[test_log::test]
+async fn ip_rate_limited(db_pool: SqlitePool) {
+    let limit = RateLimit {
+        burst: 2,
+        per_minute: 1,
+    };
+    let router = UnauthenticatedRouter::with_ip_rate_limit(db_pool, limit).await;
+    let client = SocketAddr::from(([10, 0, 0, 1], 4000));
+    for _ in 0..2 {
+        let response = router.clone().get_from("/version", client).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = router.clone().get_from("/version", client).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let other = SocketAddr::from(([10, 0, 0, 2], 4000));
+    let response = router.get_from("/version", other).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test]
+#[test_log::test]
+async fn ip_rate_limited_behind_proxy(db_pool: SqlitePool) {
+    let _ = dotenvy::dotenv();
+    let mut config = AppConfig::load().unwrap();
+    config.ip_rate_limit = RateLimit {
+        burst: 1,
+        per_minute: 1,
+    };
+    config.trusted_proxies = vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))];
+    let router = UnauthenticatedRouter::with_config(db_pool, config).await;
+    let proxy = SocketAddr::from(([10, 0, 0, 1], 4000));
+
+    // every visitor behind the proxy has their own limit
+    let response = router
+        .clone()
+        .get_forwarded("/version", proxy, "203.0.113.1")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router
+        .clone()
+        .get_forwarded("/version", proxy, "203.0.113.2")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    // only the ip the trusted proxy added counts, not what the client sent along
+    let response = router
+        .clone()
+        .get_forwarded("/version", proxy, "198.51.100.7, 203.0.113.1")
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // other clients can't pick their ip
+    let client = SocketAddr::from(([10, 0, 0, 2], 4000));
+    let response = router
+        .clone()
+        .get_forwarded("/version", client, "203.0.113.3")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router
+        .get_forwarded("/version", client, "203.0.113.4")
+        .await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[test]
+fn eviction_keeps_service_usage() {
+    let limiter = RateLimiter::default();
+    let service = RateLimit {
+        burst: 1,
+        per_minute: 0,
+    };
+    for _ in 0..2 {
+        let _ = limiter.acquire(RateLimitKey::Service(1), service);
+    }
+
+    // way more clients than are tracked, all with their own limit
+    let ip_limit = RateLimit {
+        burst: 5,
+        per_minute: 1,
+    };
+    for i in 0..20_000u32 {
+        let ip = IpAddr::V4(Ipv4Addr::from(i));
+        assert!(limiter.acquire(RateLimitKey::Ip(ip), ip_limit).is_ok());
+    }
+
+    let usage = limiter.usage(RateLimitKey::Service(1), service);
+    assert_eq!(usage.remaining, 0);
+    assert_eq!(usage.rejected, 1);
+}
@@ -0,0 +1,176 @@
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::SqlitePool;
+use zpi::dto::{
+    sandbox::SandboxPayload,
+    service::ServicePayloadAdmin,
+    service_data::ServiceDataPutPayload,
+    unlock::{UnlockCreatePayload, UnlockPayload},
+};
+
+use crate::common::{
+    into_struct::IntoStruct,
+    router::{AuthenticatedRouter, ServiceRouter},
+    test_objects::TestObjects,
+};
+
+mod common;
+
+static TEST_API_KEY: &str = "tttttttttttttttttttttttttttttttt";
+
+#[sqlx::test(fixtures("users", "services", "achievements"))]
+#[test_log::test]
+async fn unlock_goal(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router
+        .clone()
+        .post(
+            "/service/users/1/unlocks",
+            UnlockCreatePayload { goal_id: 3 },
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.get("/service/users/1/unlocks").await;
+    let unlocks: Vec<UnlockPayload> = response.into_struct().await;
+    assert_eq!(unlocks.len(), 1);
+    assert_eq!(unlocks[0].goal_id, 3);
+}
+
+#[sqlx::test(fixtures("users", "services", "achievements", "unlocks"))]
+#[test_log::test]
+async fn hidden_achievements_are_shown_to_their_service(db_pool: SqlitePool) {
+    sqlx::query("UPDATE user SET achievements_visibility = 'private' WHERE id = 1")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router.clone().get("/service/users/1/unlocks").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let unlocks: Vec<UnlockPayload> = response.into_struct().await;
+    assert_eq!(unlocks.len(), 1);
+    assert_eq!(unlocks[0].goal_id, 1);
+}
+
+#[sqlx::test(fixtures("users", "services", "achievements"))]
+#[test_log::test]
+async fn unlock_goal_of_other_service(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router
+        .post(
+            "/service/users/1/unlocks",
+            UnlockCreatePayload { goal_id: 4 },
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(fixtures("users", "services", "achievements"))]
+#[test_log::test]
+async fn test_key_writes_to_sandbox(db_pool: SqlitePool) {
+    sqlx::query("UPDATE service SET test_api_key = ? WHERE id = 1")
+        .bind(TEST_API_KEY)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let test_router = ServiceRouter::new(db_pool.clone(), TEST_API_KEY).await;
+    let response = test_router
+        .clone()
+        .post(
+            "/service/users/1/unlocks",
+            UnlockCreatePayload { goal_id: 1 },
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = ServiceDataPutPayload {
+        value: json!(1),
+        version: None,
+    };
+    let response = test_router
+        .clone()
+        .put("/service/users/1/data/votes", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the test key sees its own writes
+    let response = test_router.get("/service/users/1/unlocks").await;
+    let unlocks: Vec<UnlockPayload> = response.into_struct().await;
+    assert_eq!(unlocks.len(), 1);
+
+    // the production key doesn't
+    let router = ServiceRouter::new(db_pool.clone(), &TestObjects::admin_service_1().api_key).await;
+    let response = router.clone().get("/service/users/1/unlocks").await;
+    let unlocks: Vec<UnlockPayload> = response.into_struct().await;
+    assert!(unlocks.is_empty());
+    let response = router.get("/service/users/1/data/votes").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // neither does the production data of the user
+    let user_router = AuthenticatedRouter::new(db_pool).await;
+    let response = user_router.clone().get("/users/me/data").await;
+    let data: Vec<serde_json::Value> = response.into_struct().await;
+    assert!(data.is_empty());
+
+    // but the user can see their sandbox
+    let response = user_router.get("/users/me/sandbox").await;
+    let sandbox: SandboxPayload = response.into_struct().await;
+    assert_eq!(sandbox.unlocks.len(), 1);
+    assert_eq!(sandbox.data.len(), 1);
+}
+
+#[sqlx::test(fixtures("users", "services", "achievements"))]
+#[test_log::test]
+async fn wipe_sandbox(db_pool: SqlitePool) {
+    sqlx::query("UPDATE service SET test_api_key = ? WHERE id = 1")
+        .bind(TEST_API_KEY)
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let test_router = ServiceRouter::new(db_pool.clone(), TEST_API_KEY).await;
+    test_router
+        .post(
+            "/service/users/1/unlocks",
+            UnlockCreatePayload { goal_id: 1 },
+        )
+        .await;
+
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.clone().get("/services/1/sandbox").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let sandbox: SandboxPayload = response.into_struct().await;
+    assert_eq!(sandbox.unlocks.len(), 1);
+
+    let response = router.clone().delete("/services/1/sandbox").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/services/1/sandbox").await;
+    let sandbox: SandboxPayload = response.into_struct().await;
+    assert!(sandbox.unlocks.is_empty());
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn production_key_cant_wipe_sandbox(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router.delete("/service/sandbox").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn create_test_api_key(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.clone().post("/admin/services/1/testkey", "").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let service: ServicePayloadAdmin = response.into_struct().await;
+    assert!(service.test_api_key.is_some());
+    assert_ne!(service.test_api_key, Some(service.api_key));
+
+    let response = router.delete("/admin/services/1/testkey").await;
+    let service: ServicePayloadAdmin = response.into_struct().await;
+    assert_eq!(service.test_api_key, None);
+}
@@ -1,12 +1,19 @@
-use reqwest::StatusCode;
+use reqwest::{StatusCode, header};
 use serde::Deserialize;
+use serde_json::json;
 use sqlx::SqlitePool;
-use zpi::dto::service::{
-    ServiceCreatePayload, ServicePatchPayload, ServicePayloadAdmin, ServicePayloadUser,
+use zpi::dto::{
+    achievement::AchievementPayload,
+    service::{
+        ServiceCreatePayload, ServicePatchPayload, ServicePayloadAdmin, ServicePayloadUser,
+        ServiceRateLimitPatchPayload, ServiceRateLimitPayload, ServiceUsagePayload,
+    },
 };
 
 use crate::common::{
-    into_struct::IntoStruct, router::AuthenticatedRouter, test_objects::TestObjects,
+    into_struct::IntoStruct,
+    router::{AuthenticatedRouter, ServiceRouter},
+    test_objects::TestObjects,
 };
 
 mod common;
@@ -73,15 +80,16 @@ async fn create_service(db_pool: SqlitePool) {
     assert_eq!(service_response.api_key.len(), 44);
 }
 
-#[sqlx::test(fixtures("services"))]
+#[sqlx::test(fixtures("users", "services"))]
 #[test_log::test]
 async fn patch_service(db_pool: SqlitePool) {
     let new_name = "gamification2";
     let router = AuthenticatedRouter::new(db_pool).await;
     let body = ServicePatchPayload {
         name: new_name.to_string(),
+        owner_id: None,
     };
-    let response = router.patch("/admin/services/1", body).await;
+    let response = router.clone().patch("/admin/services/1", body).await;
 
     assert_eq!(response.status(), StatusCode::OK);
 
@@ -90,6 +98,29 @@ async fn patch_service(db_pool: SqlitePool) {
     let mut expected_service = TestObjects::admin_service_1();
     expected_service.name = new_name.to_string();
     assert_eq!(service_response, expected_service);
+
+    let body = ServicePatchPayload {
+        name: new_name.to_string(),
+        owner_id: Some(Some(2)),
+    };
+    let response = router.clone().patch("/admin/services/1", body).await;
+    let service_response: ServicePayloadAdmin = response.into_struct().await;
+    assert_eq!(service_response.owner_id, Some(2));
+    // a missing owner keeps it, null removes it
+    let response = router
+        .clone()
+        .patch("/admin/services/1", json!({ "name": new_name }))
+        .await;
+    let service_response: ServicePayloadAdmin = response.into_struct().await;
+    assert_eq!(service_response.owner_id, Some(2));
+    let response = router
+        .patch(
+            "/admin/services/1",
+            json!({ "name": new_name, "owner_id": null }),
+        )
+        .await;
+    let service_response: ServicePayloadAdmin = response.into_struct().await;
+    assert_eq!(service_response.owner_id, None);
 }
 
 #[sqlx::test(fixtures("services"))]
@@ -104,3 +135,146 @@ async fn regenerate_api_key(db_pool: SqlitePool) {
 
     assert_ne!(data.api_key, TestObjects::admin_service_1().api_key)
 }
+
+#[sqlx::test(fixtures("services", "achievements"))]
+#[test_log::test]
+async fn service_gets_own_achievements(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router.get("/service/achievements").await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data: Vec<AchievementPayload> = response.into_struct().await;
+
+    assert_eq!(
+        data,
+        vec![TestObjects::achievement_1(), TestObjects::achievement_2()]
+    );
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn service_invalid_api_key(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, "not a key").await;
+    let response = router.get("/service/achievements").await;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn service_rate_limited(db_pool: SqlitePool) {
+    sqlx::query("UPDATE service SET rate_limit_burst = 2, rate_limit_per_minute = 1 WHERE id = 1")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    for _ in 0..2 {
+        let response = router.clone().get("/service/achievements").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = router.get("/service/achievements").await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn patch_rate_limit(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let body = ServiceRateLimitPatchPayload {
+        burst: 10,
+        per_minute: 5,
+    };
+    let response = router
+        .clone()
+        .put("/admin/services/1/ratelimit", body)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.get("/admin/services/1/ratelimit").await;
+    let data: ServiceRateLimitPayload = response.into_struct().await;
+
+    assert_eq!(
+        data,
+        ServiceRateLimitPayload {
+            burst: 10,
+            per_minute: 5,
+            remaining: 10,
+            rejected: 0,
+        }
+    );
+}
+
+#[sqlx::test(fixtures("services", "achievements"))]
+#[test_log::test]
+async fn service_usage(db_pool: SqlitePool) {
+    let service_router =
+        ServiceRouter::new(db_pool.clone(), &TestObjects::admin_service_1().api_key).await;
+    for _ in 0..2 {
+        let response = service_router.clone().get("/service/achievements").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = service_router
+        .clone()
+        .get("/service/users/1/data/missing")
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    service_router.flush_usage().await;
+
+    // requests with the test api key are counted apart
+    sqlx::query(
+        "UPDATE service SET test_api_key = 'tttttttttttttttttttttttttttttttt' WHERE id = 1",
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+    let test_router = ServiceRouter::new(db_pool.clone(), "tttttttttttttttttttttttttttttttt").await;
+    let response = test_router.clone().get("/service/achievements").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    test_router.flush_usage().await;
+
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router
+        .clone()
+        .get("/admin/services/1/usage?namespace=sandbox")
+        .await;
+    let usage: ServiceUsagePayload = response.into_struct().await;
+    assert_eq!(usage.requests, 1);
+
+    let response = router.get("/admin/services/1/usage").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let usage: ServiceUsagePayload = response.into_struct().await;
+    assert_eq!(usage.requests, 3);
+    assert_eq!(usage.errors, 1);
+    assert!(usage.last_called_at.is_some());
+
+    let endpoints: Vec<(&str, u16, u32)> = usage
+        .endpoints
+        .iter()
+        .map(|usage| (usage.endpoint.as_str(), usage.status, usage.count))
+        .collect();
+    assert_eq!(
+        endpoints,
+        vec![
+            ("GET /service/achievements", 200, 2),
+            ("GET /service/users/{id}/data/{key}", 404, 1),
+        ]
+    );
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn service_usage_unused(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.get("/admin/services/2/usage").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let usage: ServiceUsagePayload = response.into_struct().await;
+    assert_eq!(usage.requests, 0);
+    assert_eq!(usage.last_called_at, None);
+}
@@ -0,0 +1,197 @@
+use database::{
+    Database,
+    error::DatabaseError,
+    models::{service::Namespace, service_data::ServiceDataPut},
+};
+use reqwest::StatusCode;
+use serde_json::json;
+use sqlx::SqlitePool;
+use tokio::task::JoinSet;
+use zpi::dto::service_data::{ServiceDataPayload, ServiceDataPutPayload, ServiceDataUserPayload};
+
+use crate::common::{
+    into_struct::IntoStruct,
+    router::{AuthenticatedRouter, ServiceRouter},
+    test_objects::TestObjects,
+};
+
+mod common;
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn put_and_get_data(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let body = ServiceDataPutPayload {
+        value: json!({ "votes": 3 }),
+        version: None,
+    };
+    let response = router
+        .clone()
+        .put("/service/users/1/data/votes", body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let created: ServiceDataPayload = response.into_struct().await;
+    assert_eq!(created.version, 1);
+
+    let response = router.get("/service/users/1/data/votes").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data: ServiceDataPayload = response.into_struct().await;
+    assert_eq!(data, created);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn put_data_version_conflict(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let body = ServiceDataPutPayload {
+        value: json!(1),
+        version: None,
+    };
+    let response = router
+        .clone()
+        .put("/service/users/1/data/votes", &body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // creating the same key again conflicts
+    let response = router
+        .clone()
+        .put("/service/users/1/data/votes", &body)
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let body = ServiceDataPutPayload {
+        value: json!(2),
+        version: Some(1),
+    };
+    let response = router
+        .clone()
+        .put("/service/users/1/data/votes", &body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let data: ServiceDataPayload = response.into_struct().await;
+    assert_eq!(data.version, 2);
+
+    // version 1 is outdated now
+    let response = router.put("/service/users/1/data/votes", &body).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn put_data_too_large(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let body = ServiceDataPutPayload {
+        value: json!("a".repeat(20_000)),
+        version: None,
+    };
+    let response = router.put("/service/users/1/data/big", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn key_limit_holds_for_concurrent_puts(db_pool: SqlitePool) {
+    let db = Database::new(db_pool);
+    let mut puts = JoinSet::new();
+    for i in 0..10 {
+        let db = db.clone();
+        puts.spawn(async move {
+            db.service_data(Namespace::Production)
+                .put(
+                    1,
+                    1,
+                    ServiceDataPut {
+                        key: format!("key-{i}"),
+                        value: "1".into(),
+                        version: None,
+                        max_keys: 3,
+                    },
+                )
+                .await
+        });
+    }
+    let mut stored = 0;
+    while let Some(result) = puts.join_next().await {
+        match result.unwrap() {
+            Ok(_) => stored += 1,
+            Err(err) => assert!(matches!(err, DatabaseError::Conflict(_)), "{err}"),
+        }
+    }
+    assert_eq!(stored, 3);
+    let count = db
+        .service_data(Namespace::Production)
+        .count_for_service_user(1, 1)
+        .await
+        .unwrap();
+    assert_eq!(count, 3);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn services_dont_see_each_others_data(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool.clone(), &TestObjects::admin_service_1().api_key).await;
+    let body = ServiceDataPutPayload {
+        value: json!(true),
+        version: None,
+    };
+    router.put("/service/users/1/data/visited", body).await;
+
+    let router = ServiceRouter::new(db_pool, "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb").await;
+    let response = router.get("/service/users/1/data/visited").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn delete_data(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let body = ServiceDataPutPayload {
+        value: json!(true),
+        version: None,
+    };
+    router
+        .clone()
+        .put("/service/users/1/data/visited", body)
+        .await;
+
+    let response = router
+        .clone()
+        .delete("/service/users/1/data/visited?version=2")
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = router
+        .clone()
+        .delete("/service/users/1/data/visited?version=1")
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/service/users/1/data/visited").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn user_sees_stored_data(db_pool: SqlitePool) {
+    let service_router =
+        ServiceRouter::new(db_pool.clone(), &TestObjects::admin_service_1().api_key).await;
+    let body = ServiceDataPutPayload {
+        value: json!({ "last_visit": "2025-10-01" }),
+        version: None,
+    };
+    service_router
+        .put("/service/users/1/data/visit", body)
+        .await;
+
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.get("/users/me/data").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let data: Vec<ServiceDataUserPayload> = response.into_struct().await;
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].service, TestObjects::service_1());
+    assert_eq!(data[0].data[0].value, json!({ "last_visit": "2025-10-01" }));
+}
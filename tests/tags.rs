@@ -1,9 +1,21 @@
+use chrono::{DateTime, Utc};
+use database::Database;
+use database::models::tag::{Tag, TagAssignment, TagCategory, TagOverview, TagPolicy, UserTag};
 use reqwest::StatusCode;
 use sqlx::SqlitePool;
-use zpi::dto::user::UserProfile;
+use zpi::dto::{
+    tag::{
+        TagAssignmentCreatePayload, TagAssignmentPatchPayload, TagCategoryCreatePayload,
+        TagCategoryPatchPayload, TagCreatePayload, TagMembersPayload, TagPatchPayload,
+    },
+    user::UserProfile,
+};
 
 use crate::common::{
-    into_struct::IntoStruct, router::AuthenticatedRouter, test_objects::TestObjects,
+    images::temp_category_icon,
+    into_struct::IntoStruct,
+    router::{AuthenticatedRouter, ServiceRouter},
+    test_objects::TestObjects,
 };
 
 mod common;
@@ -20,3 +32,527 @@ async fn get_user_with_tags(db_pool: SqlitePool) {
 
     assert_eq!(data, TestObjects::user_profile_2())
 }
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn create_tag(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let body = TagCreatePayload {
+        name: "vooruit".into(),
+        description: Some("Ik ben de vooruit".into()),
+        category_id: 2,
+        color: Some("#ff8000".into()),
+    };
+    let response = router.post("/admin/tags", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tag: Tag = response.into_struct().await;
+    assert_eq!(
+        tag,
+        Tag {
+            id: 4,
+            name: "vooruit".into(),
+            category: "toren".into(),
+            description: Some("Ik ben de vooruit".into()),
+            color: "#FF8000".into(),
+            icon: false,
+        }
+    );
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn create_tag_duplicate_name(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let body = TagCreatePayload {
+        name: "eiffel".into(),
+        description: None,
+        category_id: 2,
+        color: None,
+    };
+    let response = router.post("/admin/tags", body).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn rename_tag(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let body = TagPatchPayload {
+        name: Some("belfort".into()),
+        ..Default::default()
+    };
+    let response = router.patch("/admin/tags/3", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tag: Tag = response.into_struct().await;
+    assert_eq!(tag.name, "belfort");
+    assert_eq!(tag.description, Some("Ik ben een eiffeltoren".into()));
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn create_category(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let body = TagCategoryCreatePayload {
+        name: "werkgroep".into(),
+        description: None,
+        policy: TagPolicy::SelfAssignable,
+        max_per_user: Some(3),
+        service_id: None,
+        color: None,
+    };
+    let response = router.clone().post("/admin/tags/categories", &body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let category: TagCategory = response.into_struct().await;
+    assert_eq!(category.id, 3);
+    assert_eq!(category.policy, TagPolicy::SelfAssignable);
+    assert_eq!(category.max_per_user, Some(3));
+    assert_eq!(category.color, "#A0A0A0");
+
+    let response = router.post("/admin/tags/categories", &body).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn delete_category_with_tags(db_pool: SqlitePool) {
+    let images = temp_category_icon("delete-category-with-tags", 2);
+    let router = AuthenticatedRouter::with_image_path(db_pool, images.clone()).await;
+    let response = router.clone().delete("/admin/tags/categories/2").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = router
+        .clone()
+        .delete("/admin/tags/categories/2?cascade=true")
+        .await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/users/2").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.tags.len(), 1);
+
+    // the icon of the category is gone with it
+    assert!(!images.join("tag-category-2").exists());
+    std::fs::remove_dir_all(images).unwrap();
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn assign_and_remove_tag(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.clone().put("/admin/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().get("/users/1").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.tags.len(), 1);
+
+    let response = router.clone().delete("/admin/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/users/1").await;
+    let data: UserProfile = response.into_struct().await;
+    assert!(data.tags.is_empty());
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn self_assign_tag(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.clone().put("/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // toren allows only one tag per user
+    let response = router.clone().put("/users/1/tags/2", "").await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // assigning a tag you already have is fine
+    let response = router.clone().put("/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().delete("/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().put("/users/1/tags/2", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/users/1").await;
+    let data: serde_json::Value = response.into_struct().await;
+    let tags = data["tags"].as_array().unwrap();
+    assert_eq!(tags.len(), 1);
+    // profile tags are shown by the id of the tag
+    assert_eq!(tags[0]["id"], 2);
+    assert!(tags[0].get("tag_id").is_none());
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn self_unassign_keeps_history(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let date = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    let body = TagAssignmentCreatePayload {
+        tag_id: 3,
+        valid_from: Some(date("2023-09-01T00:00:00Z")),
+        valid_until: Some(date("2024-07-01T00:00:00Z")),
+    };
+    let response = router.clone().post("/admin/users/1/tags", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // an assignment the user made themselves leaves no trace
+    let response = router.clone().put("/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.clone().delete("/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().get("/admin/users/1/tags").await;
+    let assignments: Vec<TagAssignment> = response.into_struct().await;
+    assert_eq!(assignments.len(), 1);
+
+    // one made by someone else is ended instead
+    sqlx::query("INSERT INTO user_tag (user_id, tag_id) VALUES (1, 3);")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let response = router.clone().delete("/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().get("/admin/users/1/tags").await;
+    let assignments: Vec<TagAssignment> = response.into_struct().await;
+    assert_eq!(assignments.len(), 2);
+    assert!(assignments.iter().all(|a| a.valid_until.is_some()));
+
+    let response = router.clone().delete("/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = router.get("/users/1").await;
+    let data: UserProfile = response.into_struct().await;
+    assert!(data.tags.is_empty());
+    assert_eq!(data.former_tags.len(), 2);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn self_assign_tag_not_allowed(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    // bestuur is admin only
+    let response = router.clone().put("/users/1/tags/1", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // other users' profiles
+    let response = router.clone().put("/users/2/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = router.clone().delete("/users/2/tags/2").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = router.put("/users/1/tags/42", "").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(fixtures("users", "tags", "services"))]
+#[test_log::test]
+async fn service_assign_tag(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let service = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+
+    let response = service.clone().put("/service/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let body = TagCategoryPatchPayload {
+        policy: Some(TagPolicy::ServiceAssignable),
+        max_per_user: Some(None),
+        service_id: Some(Some(1)),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/categories/2", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let category: TagCategory = response.into_struct().await;
+    assert_eq!(category.max_per_user, None);
+    assert_eq!(category.service_id, Some(1));
+
+    let response = service.clone().put("/service/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = service.clone().put("/service/users/1/tags/2", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // users can no longer manage these tags themselves
+    let response = router.clone().delete("/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = service.delete("/service/users/1/tags/3").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/users/1").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.tags.len(), 1);
+}
+
+#[sqlx::test(fixtures("users", "tags", "services"))]
+#[test_log::test]
+async fn service_assign_tag_of_other_service(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let body = TagCategoryPatchPayload {
+        policy: Some(TagPolicy::ServiceAssignable),
+        service_id: Some(Some(2)),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/categories/2", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let service = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = service.clone().put("/service/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = service.delete("/service/users/2/tags/2").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = router.get("/users/2").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.tags.len(), 2);
+}
+
+#[sqlx::test(fixtures("users", "tags", "services"))]
+#[test_log::test]
+async fn service_assign_tag_with_test_key(db_pool: SqlitePool) {
+    sqlx::query(
+        "UPDATE service SET test_api_key = 'tttttttttttttttttttttttttttttttt' WHERE id = 1",
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let body = TagCategoryPatchPayload {
+        policy: Some(TagPolicy::ServiceAssignable),
+        service_id: Some(Some(1)),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/categories/2", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // test keys never touch real tags
+    let service = ServiceRouter::new(db_pool, "tttttttttttttttttttttttttttttttt").await;
+    let response = service.clone().put("/service/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = service.delete("/service/users/2/tags/2").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = router.get("/users/1").await;
+    let data: UserProfile = response.into_struct().await;
+    assert!(data.tags.is_empty());
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn tag_directory(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let response = router.clone().get("/tags").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let tags: Vec<TagOverview> = response.into_struct().await;
+    let members: Vec<(&str, u32)> = tags.iter().map(|t| (t.name.as_str(), t.members)).collect();
+    assert_eq!(
+        members,
+        vec![("bestuur", 1), ("boekentoren", 1), ("eiffel", 0)]
+    );
+
+    sqlx::query("UPDATE user SET visibility = 'private' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let response = router.get("/tags").await;
+    let tags: Vec<TagOverview> = response.into_struct().await;
+    assert!(tags.iter().all(|t| t.members == 0));
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn tag_members(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let response = router.clone().put("/admin/users/1/tags/1", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().get("/tags/bestuur/users?limit=1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: TagMembersPayload = response.into_struct().await;
+    assert_eq!(page.total, 2);
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].username, "cheese");
+
+    let response = router
+        .clone()
+        .get("/tags/bestuur/users?limit=1&offset=1")
+        .await;
+    let page: TagMembersPayload = response.into_struct().await;
+    assert_eq!(page.users, vec![TestObjects::user_profile_2()]);
+
+    sqlx::query("UPDATE user SET visibility = 'private' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let response = router.clone().get("/tags/bestuur/users").await;
+    let page: TagMembersPayload = response.into_struct().await;
+    assert_eq!(page.total, 1);
+    assert_eq!(page.users.len(), 1);
+
+    let response = router.clone().get("/tags/bestuur/users?limit=0").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = router.get("/tags/vooruit/users").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn sync_zauth_tags(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let names = |tags: Vec<UserTag>| tags.into_iter().map(|t| t.name).collect::<Vec<_>>();
+
+    db.tags()
+        .sync_zauth(2, &["eiffel".into(), "unknown".into()])
+        .await
+        .unwrap();
+    let tags = db.tags().for_user(2).await.unwrap();
+    assert_eq!(names(tags), vec!["bestuur", "boekentoren", "eiffel"]);
+
+    // manually granted tags stay when the role is gone
+    db.tags().sync_zauth(2, &[]).await.unwrap();
+    let tags = db.tags().for_user(2).await.unwrap();
+    assert_eq!(names(tags), vec!["bestuur", "boekentoren"]);
+    let former = db.tags().former_for_user(2).await.unwrap();
+    assert_eq!(names(former), vec!["eiffel"]);
+
+    // a synced tag granted by hand afterwards is no longer managed by the sync
+    db.tags().sync_zauth(1, &["bestuur".into()]).await.unwrap();
+    let response = router.put("/admin/users/1/tags/1", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    db.tags().sync_zauth(1, &[]).await.unwrap();
+    let tags = db.tags().for_user(1).await.unwrap();
+    assert_eq!(names(tags), vec!["bestuur"]);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn tag_history(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let date = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+
+    let body = TagAssignmentCreatePayload {
+        tag_id: 1,
+        valid_from: Some(date("2023-09-01T00:00:00Z")),
+        valid_until: Some(date("2025-07-01T00:00:00Z")),
+    };
+    let response = router.clone().post("/admin/users/2/tags", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let assignment: TagAssignment = response.into_struct().await;
+    assert_eq!(assignment.valid_until, Some(date("2025-07-01T00:00:00Z")));
+
+    let response = router.clone().get("/users/2").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.tags.len(), 2);
+    assert_eq!(data.former_tags.len(), 1);
+    assert_eq!(data.former_tags[0].name, "bestuur");
+    assert_eq!(
+        data.former_tags[0].valid_from,
+        Some(date("2023-09-01T00:00:00Z"))
+    );
+
+    // a second open ended bestuur assignment
+    let body = TagAssignmentPatchPayload {
+        valid_until: Some(None),
+        ..Default::default()
+    };
+    let path = format!("/admin/tags/assignments/{}", assignment.id);
+    let response = router.clone().patch(&path, body).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let body = TagAssignmentPatchPayload {
+        valid_from: Some(Some(date("2026-01-01T00:00:00Z"))),
+        ..Default::default()
+    };
+    let response = router.clone().patch(&path, body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // future assignments are not shown yet
+    let body = TagAssignmentCreatePayload {
+        tag_id: 3,
+        valid_from: Some(date("2099-09-01T00:00:00Z")),
+        valid_until: None,
+    };
+    let response = router.clone().post("/admin/users/2/tags", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // ending the current assignment keeps it as history
+    let response = router.clone().delete("/admin/users/2/tags/1").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.clone().get("/users/2").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.tags.len(), 1);
+    assert_eq!(data.former_tags.len(), 2);
+
+    let response = router.clone().get("/admin/users/2/tags").await;
+    let assignments: Vec<TagAssignment> = response.into_struct().await;
+    assert_eq!(assignments.len(), 4);
+
+    let response = router.clone().delete(&path).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = router.get("/users/2").await;
+    let data: UserProfile = response.into_struct().await;
+    assert_eq!(data.former_tags.len(), 1);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn tag_colors(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let body = TagCategoryPatchPayload {
+        color: Some(Some("#00b400".into())),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/categories/2", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = TagPatchPayload {
+        color: Some(Some("#FF0000".into())),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/2", body).await;
+    let tag: Tag = response.into_struct().await;
+    assert_eq!(tag.color, "#FF0000");
+
+    let response = router.clone().get("/users/2").await;
+    let data: UserProfile = response.into_struct().await;
+    let colors: Vec<&str> = data.tags.iter().map(|t| t.color.as_str()).collect();
+    assert_eq!(colors, vec!["#A0A0A0", "#FF0000"]);
+
+    // back to the color of the category
+    let body = TagPatchPayload {
+        color: Some(None),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/2", body).await;
+    let tag: Tag = response.into_struct().await;
+    assert_eq!(tag.color, "#00B400");
+
+    let body = TagPatchPayload {
+        color: Some(Some("orange".into())),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/tags/2", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // a category without a color is grey again
+    let body = TagCategoryPatchPayload {
+        color: Some(None),
+        ..Default::default()
+    };
+    let response = router.patch("/admin/tags/categories/2", body).await;
+    let category: TagCategory = response.into_struct().await;
+    assert_eq!(category.color, "#A0A0A0");
+}
@@ -1,11 +1,37 @@
-use database::models::user::{User, UserPatch};
-use reqwest::StatusCode;
+use async_zip::base::read::mem::ZipFileReader;
+use axum::body::to_bytes;
+use chrono::{Duration, Utc};
+use database::{
+    Database,
+    models::{
+        profile_change::Actor,
+        user::{
+            FieldVisibility, LinkKind, SocialLink, User, UserCreate, UserModeration, UserPatch,
+            Visibility,
+        },
+    },
+};
+use reqwest::{
+    StatusCode,
+    header::{CONTENT_TYPE, LOCATION},
+};
 use sqlx::SqlitePool;
-use zpi::{dto::user::UserProfile, extractors::AuthenticatedUser};
+use zpi::{
+    dto::{
+        export::UserExportPayload,
+        impersonation::{CurrentUserPayload, ImpersonationListPayload, ImpersonationStartPayload},
+        profile_change::ProfileHistoryPayload,
+        tag::{TagMembersPayload, TagPatchPayload},
+        user::{UserListPayload, UserPatchPayload, UserProfile},
+        user_record::{UserModerationPayload, UserRecordListPayload, UserRecordPayload},
+    },
+    extractors::AuthenticatedUser,
+};
 
 use crate::common::{
+    images::temp_images,
     into_struct::IntoStruct,
-    router::{AuthenticatedRouter, UnauthenticatedRouter},
+    router::{AuthenticatedRouter, ServiceRouter, UnauthenticatedRouter},
     test_objects::TestObjects,
 };
 
@@ -35,7 +61,8 @@ async fn get_users_me_unauthenticated(db_pool: SqlitePool) {
 async fn patch_user(db_pool: SqlitePool) {
     let router = AuthenticatedRouter::new(db_pool).await;
     let body = UserPatch {
-        about: "Changed about".to_string(),
+        about: Some("Changed about".to_string()),
+        ..Default::default()
     };
     let response = router.patch("/users/1", body).await;
 
@@ -92,3 +119,1036 @@ async fn get_profile_by_name(db_pool: SqlitePool) {
     let user_response: UserProfile = response.into_struct().await;
     assert_eq!(user_response, TestObjects::user_profile_1());
 }
+
+#[sqlx::test(fixtures("users", "tags", "services"))]
+#[test_log::test]
+async fn service_lookup_users(db_pool: SqlitePool) {
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router
+        .get("/service/users?ids=1,3&usernames=wafel,cheese")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let profiles: Vec<UserProfile> = response.into_struct().await;
+    assert_eq!(
+        profiles,
+        vec![TestObjects::user_profile_1(), TestObjects::user_profile_2()]
+    );
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn service_lookup_skips_private_users(db_pool: SqlitePool) {
+    sqlx::query("UPDATE user SET visibility = 'private' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router.get("/service/users?ids=1,2").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let profiles: Vec<UserProfile> = response.into_struct().await;
+    assert_eq!(profiles, vec![TestObjects::user_profile_1()]);
+}
+
+#[sqlx::test(fixtures("users", "services", "tags"))]
+#[test_log::test]
+async fn service_lookup_former_username(db_pool: SqlitePool) {
+    sqlx::query("INSERT INTO username_history (username, user_id) VALUES ('waffle', 2)")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router
+        .get("/service/users?usernames=waffle,nobody,wafel&ids=2")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let profiles: Vec<UserProfile> = response.into_struct().await;
+    assert_eq!(profiles, vec![TestObjects::user_profile_2()]);
+}
+
+#[sqlx::test(fixtures("services"))]
+#[test_log::test]
+async fn service_lookup_batch_too_large(db_pool: SqlitePool) {
+    let ids = (1..=101)
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let router = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = router.get(&format!("/service/users?ids={ids}")).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn patch_social_links(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let link = |kind, value: &str| SocialLink {
+        kind,
+        value: value.into(),
+    };
+    let body = UserPatch {
+        links: Some(vec![
+            link(LinkKind::Website, "zeus.gent/about/"),
+            link(LinkKind::Github, "https://github.com/ZeusWPI/"),
+            link(LinkKind::Mattermost, "@Cheese"),
+            link(LinkKind::Matrix, "@Cheese:Zeus.gent"),
+            link(LinkKind::Email, "cheese@ZEUS.gent"),
+        ]),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    let links: Vec<(&str, &str)> = profile
+        .links
+        .iter()
+        .map(|l| (l.value.as_str(), l.url.as_str()))
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            ("https://zeus.gent/about/", "https://zeus.gent/about/"),
+            ("ZeusWPI", "https://github.com/ZeusWPI"),
+            (
+                "cheese",
+                "https://mattermost.zeus.gent/zeus/messages/@cheese"
+            ),
+            ("@cheese:zeus.gent", "https://matrix.to/#/@cheese:zeus.gent"),
+            ("cheese@zeus.gent", "mailto:cheese@zeus.gent"),
+        ]
+    );
+
+    // patching something else keeps the links
+    let body = UserPatch {
+        about: Some("Changed about".into()),
+        ..Default::default()
+    };
+    router.clone().patch("/users/1", body).await;
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.links.len(), 5);
+
+    // the address can't add headers to the mail
+    let body = UserPatch {
+        links: Some(vec![link(LinkKind::Email, "a?cc=x&body=y@evil.com")]),
+        ..Default::default()
+    };
+    router.clone().patch("/users/1", body).await;
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(
+        profile.links[0].url,
+        "mailto:a%3Fcc%3Dx%26body%3Dy@evil.com"
+    );
+
+    let body = UserPatch {
+        links: Some(Vec::new()),
+        ..Default::default()
+    };
+    router.clone().patch("/users/1", body).await;
+    let response = router.get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert!(profile.links.is_empty());
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn patch_invalid_social_links(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let invalid = [
+        (LinkKind::Github, "-cheese"),
+        (LinkKind::Matrix, "cheese"),
+        (LinkKind::Email, "cheese"),
+        (LinkKind::Email, "chee se@zeus.gent"),
+        (LinkKind::Email, "cheese.@zeus.gent"),
+        (LinkKind::Email, "(cheese)@zeus.gent"),
+        (LinkKind::Website, "javascript:alert(1)"),
+        (LinkKind::Website, "ftp://zeus.gent"),
+        (LinkKind::Mattermost, "cheese!"),
+    ];
+    for (kind, value) in invalid {
+        let body = UserPatch {
+            links: Some(vec![SocialLink {
+                kind,
+                value: value.into(),
+            }]),
+            ..Default::default()
+        };
+        let response = router.clone().patch("/users/1", body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{value}");
+    }
+
+    let body = UserPatch {
+        links: Some(vec![
+            SocialLink {
+                kind: LinkKind::Github,
+                value: "cheese".into(),
+            },
+            SocialLink {
+                kind: LinkKind::Github,
+                value: "@cheese".into(),
+            },
+        ]),
+        ..Default::default()
+    };
+    let response = router.patch("/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn search_users(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let search = |query: &str| {
+        let router = router.clone();
+        let path = format!("/users?q={query}");
+        async move {
+            let response = router.get(&path).await;
+            assert_eq!(response.status(), StatusCode::OK, "{path}");
+            let page: UserListPayload = response.into_struct().await;
+            page.users
+                .into_iter()
+                .map(|p| p.username)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // a username match ranks above a match in the about
+    assert_eq!(search("cheese").await, vec!["cheese", "wafel"]);
+    // prefixes for autocomplete
+    assert_eq!(search("waf").await, vec!["wafel"]);
+    assert_eq!(search("fantas").await, vec!["cheese"]);
+    // tag names
+    assert_eq!(search("boekentoren").await, vec!["wafel"]);
+    // every word has to match
+    assert_eq!(search("cheese%20bestuur").await, vec!["wafel"]);
+    // fts5 syntax is not interpreted
+    assert_eq!(search("chee*%22(").await, vec!["cheese", "wafel"]);
+    assert!(search("cheese%20OR%20stroopwafel").await.is_empty());
+    // without words it's the whole directory
+    assert_eq!(search("").await, vec!["cheese", "wafel"]);
+    assert!(search("stroopwafel").await.is_empty());
+
+    let response = router.clone().get("/users?q=cheese&limit=0").await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn search_index_follows_changes(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let search = |query: &str| {
+        let router = router.clone();
+        let path = format!("/users?q={query}");
+        async move {
+            let page: UserListPayload = router.get(&path).await.into_struct().await;
+            page.users
+                .into_iter()
+                .map(|p| p.username)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let body = UserPatch {
+        about: Some("Ik eet graag crème brûlée".into()),
+        ..Default::default()
+    };
+    router.clone().patch("/users/1", body).await;
+    assert_eq!(search("creme").await, vec!["cheese"]);
+    assert!(search("fantasizing").await.is_empty());
+
+    router.clone().put("/admin/users/1/tags/3", ()).await;
+    assert_eq!(search("eiffel").await, vec!["cheese"]);
+    router.clone().delete("/admin/users/1/tags/3").await;
+    assert!(search("eiffel").await.is_empty());
+
+    let body = TagPatchPayload {
+        name: Some("praeses".into()),
+        ..Default::default()
+    };
+    router.clone().patch("/admin/tags/1", body).await;
+    assert_eq!(search("praeses").await, vec!["wafel"]);
+    assert!(search("bestuur").await.is_empty());
+
+    sqlx::query("UPDATE user SET username = 'gouda' WHERE id = 1")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    assert_eq!(search("gou").await, vec!["gouda"]);
+
+    // private profiles are never found
+    sqlx::query("UPDATE user SET visibility = 'private' WHERE id = 2")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    assert!(search("wafel").await.is_empty());
+}
+
+#[sqlx::test(fixtures("users", "tags", "services", "achievements", "unlocks"))]
+#[test_log::test]
+async fn member_directory(db_pool: SqlitePool) {
+    sqlx::query(
+        "INSERT INTO user (id, username, created_at, last_active_at) VALUES
+            (3, 'stroop', '2025-01-01 00:00:00', '2025-10-01 00:00:00');
+        UPDATE user SET created_at = '2024-01-01 00:00:00', last_active_at = '2025-08-01 00:00:00'
+        WHERE id = 1;
+        UPDATE user SET created_at = '2023-01-01 00:00:00', last_active_at = '2025-09-01 00:00:00',
+            has_image = TRUE
+        WHERE id = 2;",
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let list = |query: &str| {
+        let router = router.clone();
+        let path = format!("/users?{query}");
+        async move {
+            let response = router.get(&path).await;
+            assert_eq!(response.status(), StatusCode::OK, "{path}");
+            let page: UserListPayload = response.into_struct().await;
+            let names: Vec<String> = page.users.into_iter().map(|p| p.username).collect();
+            (names, page.next_cursor)
+        }
+    };
+
+    assert_eq!(
+        list("").await,
+        (vec!["cheese".into(), "stroop".into(), "wafel".into()], None)
+    );
+    assert_eq!(
+        list("sort=joined").await.0,
+        vec!["stroop", "cheese", "wafel"]
+    );
+    // browsing the directory makes cheese active
+    assert_eq!(
+        list("sort=active").await.0,
+        vec!["cheese", "stroop", "wafel"]
+    );
+
+    // walk through the pages
+    for (sort, expected) in [
+        ("username", ["cheese", "stroop", "wafel"]),
+        ("joined", ["stroop", "cheese", "wafel"]),
+    ] {
+        let mut names = Vec::new();
+        let mut query = format!("sort={sort}&limit=1");
+        loop {
+            let (page, next) = list(&query).await;
+            names.extend(page);
+            match next {
+                Some(next) => query = format!("sort={sort}&limit=1&cursor={next}"),
+                None => break,
+            }
+        }
+        assert_eq!(names, expected);
+    }
+
+    // search results are paged too
+    let (first, next) = list("q=cheese&limit=1").await;
+    assert_eq!(first, vec!["cheese"]);
+    let (second, next) = list(&format!("q=cheese&limit=1&cursor={}", next.unwrap())).await;
+    assert_eq!((second, next), (vec!["wafel".into()], None));
+    assert_eq!(
+        list("q=cheese&sort=joined").await.0,
+        vec!["cheese", "wafel"]
+    );
+
+    assert_eq!(list("tag=boekentoren").await.0, vec!["wafel"]);
+    assert_eq!(list("achievement=1").await.0, vec!["cheese"]);
+    assert_eq!(list("has_image=true").await.0, vec!["wafel"]);
+    assert_eq!(
+        list("has_image=false&sort=active").await.0,
+        vec!["cheese", "stroop"]
+    );
+
+    let (_, cursor) = list("limit=1").await;
+    for query in [
+        format!("sort=joined&cursor={}", cursor.unwrap()),
+        "cursor=zz".into(),
+        "cursor=616263".into(),
+        "limit=101".into(),
+    ] {
+        let response = router.clone().get(&format!("/users?{query}")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+    }
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn get_public_profile_unauthenticated(db_pool: SqlitePool) {
+    sqlx::query("UPDATE user SET visibility = 'public' WHERE id = 1")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let router = UnauthenticatedRouter::new(db_pool).await;
+    let response = router.clone().get("/users/cheese").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile, TestObjects::user_profile_1());
+
+    // members-only profiles still need a login
+    let response = router.get("/users/2").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test(fixtures("users", "tags", "services"))]
+#[test_log::test]
+async fn field_visibility(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let body = UserPatch {
+        visibility: Some(Visibility::Public),
+        field_visibility: Some(FieldVisibility {
+            about: Some(Visibility::Members),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user: User = response.into_struct().await;
+    assert_eq!(user.field_visibility.about, Some(Visibility::Members));
+
+    // the owner sees everything, visitors don't see the about
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile, TestObjects::user_profile_1());
+    let response = UnauthenticatedRouter::new(db_pool.clone())
+        .await
+        .get("/users/1")
+        .await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.about, "");
+    assert_eq!(profile.about_html, "");
+
+    // an override can't make something more visible than the profile
+    sqlx::query(
+        "UPDATE user SET visibility = 'members', about_visibility = 'public',
+            tags_visibility = 'private'
+        WHERE id = 2",
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+    let response = UnauthenticatedRouter::new(db_pool.clone())
+        .await
+        .get("/users/2")
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let service = ServiceRouter::new(db_pool, &TestObjects::admin_service_1().api_key).await;
+    let response = service.get("/service/users?ids=2").await;
+    let profiles: Vec<UserProfile> = response.into_struct().await;
+    let mut expected = TestObjects::user_profile_2();
+    expected.tags = Vec::new();
+    assert_eq!(profiles, vec![expected]);
+
+    // hidden tags are left out of listings
+    let response = router.clone().get("/tags/boekentoren/users").await;
+    let members: TagMembersPayload = response.into_struct().await;
+    assert_eq!(members.total, 0);
+    let response = router.clone().get("/users?tag=boekentoren").await;
+    let page: UserListPayload = response.into_struct().await;
+    assert!(page.users.is_empty());
+    let response = router.get("/users?q=boekentoren").await;
+    let page: UserListPayload = response.into_struct().await;
+    assert!(page.users.is_empty());
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn about_markdown(db_pool: SqlitePool) {
+    sqlx::query("INSERT INTO username_history (username, user_id) VALUES ('waffle', 2)")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let about = "# Hi\n\n**Bold** <script>alert(1)</script> \
+        [site](https://zeus.gent) [bad](javascript:alert(1)) ![img](https://zeus.gent/x.png)\n\n\
+        Friends with @wafel, @waffle and @nobody, mail cheese@zeus.gent\n\n`@wafel`";
+    let body = UserPatch {
+        about: Some(about.into()),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().get("/users/1").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.about, about);
+    assert_eq!(
+        profile.about_html,
+        "<p>Hi</p>\n\
+        <p><strong>Bold</strong> &lt;script&gt;alert(1)&lt;/script&gt; \
+        <a href=\"https://zeus.gent\">site</a> bad img</p>\n\
+        <p>Friends with <a href=\"http://localhost:5173/profile/wafel\">@wafel</a>, \
+        <a href=\"http://localhost:5173/profile/wafel\">@waffle</a> and @nobody, \
+        mail cheese@zeus.gent</p>\n\
+        <p><code>@wafel</code></p>\n"
+    );
+
+    for about in [
+        "a".repeat(2001),
+        "null\0byte".into(),
+        "right\u{202e}txt.exe".into(),
+        "zero\u{200b}width".into(),
+    ] {
+        let body = UserPatch {
+            about: Some(about),
+            ..Default::default()
+        };
+        let response = router.clone().patch("/users/1", body).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[sqlx::test(fixtures("users", "tags", "services", "achievements", "unlocks"))]
+#[test_log::test]
+async fn export_user_data(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.get("/users/me/export").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_TYPE], "application/zip");
+
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let zip = ZipFileReader::new(bytes.to_vec()).await.unwrap();
+    let names: Vec<&str> = zip
+        .file()
+        .entries()
+        .iter()
+        .map(|entry| entry.filename().as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["zpi.json", "image.webp"]);
+
+    let mut manifest = String::new();
+    let mut reader = zip.reader_with_entry(0).await.unwrap();
+    reader.read_to_string_checked(&mut manifest).await.unwrap();
+    let manifest: UserExportPayload = serde_json::from_str(&manifest).unwrap();
+    assert_eq!(manifest.user, TestObjects::user_1());
+    assert_eq!(manifest.image.as_deref(), Some("image.webp"));
+    assert_eq!(
+        manifest
+            .unlocks
+            .iter()
+            .map(|unlock| unlock.goal_id)
+            .collect::<Vec<_>>(),
+        vec![1, 4]
+    );
+
+    let mut image = Vec::new();
+    let mut reader = zip.reader_with_entry(1).await.unwrap();
+    reader.read_to_end_checked(&mut image).await.unwrap();
+    assert_eq!(image, std::fs::read("tests/test_images/1").unwrap());
+}
+
+#[sqlx::test(fixtures("users", "tags", "services", "achievements", "unlocks"))]
+#[test_log::test]
+async fn delete_own_account(db_pool: SqlitePool) {
+    let images = temp_images("delete-own-account", 1);
+    let other_session = AuthenticatedRouter::new(db_pool.clone()).await;
+    let router = AuthenticatedRouter::with_image_path(db_pool.clone(), images.clone()).await;
+
+    let response = router.clone().delete("/users/me").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!images.join("1").exists());
+    assert!(!images.join("1.64.webp").exists());
+
+    // every session of the user ended
+    let response = router.get("/users/me").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = other_session.get("/users/me").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    for table in ["user", "user_tag", "unlock"] {
+        let column = if table == "user" { "id" } else { "user_id" };
+        let count: u32 =
+            sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table} WHERE {column} = 1"))
+                .fetch_one(&db_pool)
+                .await
+                .unwrap();
+        assert_eq!(count, 0, "{table} still has rows of the user");
+    }
+
+    // logging in again starts from a clean profile
+    Database::new(db_pool.clone())
+        .users()
+        .create(UserCreate {
+            id: 1,
+            username: "cheese".into(),
+        })
+        .await
+        .unwrap();
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.get("/users/1").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.about, "");
+    assert!(profile.tags.is_empty());
+    std::fs::remove_dir_all(images).unwrap();
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn admin_delete_account(db_pool: SqlitePool) {
+    let images = temp_images("admin-delete-account", 2);
+    let router = AuthenticatedRouter::with_image_path(db_pool, images.clone()).await;
+
+    let response = router.clone().delete("/admin/users/2").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!images.join("2").exists());
+
+    let response = router.clone().get("/users/2").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = router.clone().delete("/admin/users/2").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // the admin is still logged in
+    let response = router.get("/users/me").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    std::fs::remove_dir_all(images).unwrap();
+}
+
+#[sqlx::test(fixtures("users", "services"))]
+#[test_log::test]
+async fn renamed_user(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let rename = async |id: u32, username: &str| {
+        db.users()
+            .create(UserCreate {
+                id,
+                username: username.into(),
+            })
+            .await
+            .unwrap();
+    };
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+
+    rename(1, "kaas").await;
+    let response = router.clone().get("/users/cheese").await;
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()[LOCATION], "kaas");
+    let response = router.clone().get("/users/kaas").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // services get the current profile for an old name
+    let service =
+        ServiceRouter::new(db_pool.clone(), &TestObjects::admin_service_1().api_key).await;
+    let response = service.get("/service/users?usernames=cheese").await;
+    let profiles: Vec<UserProfile> = response.into_struct().await;
+    assert_eq!(profiles.len(), 1);
+    assert_eq!(profiles[0].username, "kaas");
+
+    // a name in use belongs to its current owner
+    rename(2, "cheese").await;
+    let response = router.clone().get("/users/cheese").await;
+    let profile: UserProfile = response.into_struct().await;
+    assert_eq!(profile.id, 2);
+
+    // visitors don't learn the new name of a profile they can't see
+    let response = UnauthenticatedRouter::new(db_pool.clone())
+        .await
+        .get("/users/wafel")
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let former = db.users().former_usernames(2).await.unwrap();
+    assert_eq!(
+        former
+            .iter()
+            .map(|former| former.username.as_str())
+            .collect::<Vec<_>>(),
+        vec!["wafel"]
+    );
+    // getting a name back removes it from the history
+    rename(1, "cheese2").await;
+    rename(1, "kaas").await;
+    let former = db.users().former_usernames(1).await.unwrap();
+    assert_eq!(former.len(), 1);
+    assert_eq!(former[0].username, "cheese2");
+
+    // the new name is encoded in the redirect
+    rename(1, "kaas\nbal").await;
+    let response = router.get("/users/kaas").await;
+    assert_eq!(response.status(), StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(response.headers()[LOCATION], "kaas%0Abal");
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn admin_manage_users(db_pool: SqlitePool) {
+    let images = temp_images("admin-manage-users", 2);
+    let router = AuthenticatedRouter::with_image_path(db_pool, images.clone()).await;
+
+    let response = router.clone().get("/admin/users").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: UserRecordListPayload = response.into_struct().await;
+    assert_eq!(page.total, 2);
+    assert_eq!(page.users[0].user, TestObjects::user_1());
+    for q in ["waf", "2"] {
+        let response = router.clone().get(&format!("/admin/users?q={q}")).await;
+        let page: UserRecordListPayload = response.into_struct().await;
+        assert_eq!(page.total, 1);
+        assert_eq!(page.users[0].user.username, "wafel");
+    }
+
+    let body = UserModerationPayload {
+        about: Some("Removed by an admin".into()),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/users/2", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let record: UserRecordPayload = response.into_struct().await;
+    assert_eq!(record.record.user.about, "Removed by an admin");
+    assert!(record.record.disabled_at.is_none());
+
+    let body = UserModerationPayload {
+        disabled_reason: Some("spam".into()),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/users/2", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = UserModerationPayload {
+        disabled: Some(true),
+        disabled_reason: Some("spam".into()),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/users/2", body).await;
+    let record: UserRecordPayload = response.into_struct().await;
+    assert!(record.record.disabled_at.is_some());
+    assert_eq!(record.record.disabled_reason.as_deref(), Some("spam"));
+    let response = router.clone().get("/admin/users?disabled=true").await;
+    let page: UserRecordListPayload = response.into_struct().await;
+    assert_eq!(page.total, 1);
+
+    // disabling again keeps when and why
+    let disabled_at = record.record.disabled_at;
+    let body = UserModerationPayload {
+        disabled: Some(true),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/users/2", body).await;
+    let record: UserRecordPayload = response.into_struct().await;
+    assert_eq!(record.record.disabled_at, disabled_at);
+    assert_eq!(record.record.disabled_reason.as_deref(), Some("spam"));
+
+    let body = UserModerationPayload {
+        disabled: Some(false),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/users/2", body).await;
+    let record: UserRecordPayload = response.into_struct().await;
+    assert!(record.record.disabled_at.is_none());
+    assert!(record.record.disabled_reason.is_none());
+
+    let response = router.clone().delete("/admin/users/2/image").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert!(!images.join("2").exists());
+    assert!(!images.join("2.64.webp").exists());
+
+    let response = router.get("/admin/users/42").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    std::fs::remove_dir_all(images).unwrap();
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn disabled_user_is_logged_out(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let now = Utc::now();
+    db.users()
+        .add_session(1, now, now + Duration::days(14))
+        .await
+        .unwrap();
+    // expired sessions can't be used anymore, so they aren't shown
+    db.users()
+        .add_session(1, now - Duration::days(15), now - Duration::days(1))
+        .await
+        .unwrap();
+
+    let response = router.clone().get("/admin/users/1").await;
+    let record: UserRecordPayload = response.into_struct().await;
+    assert_eq!(record.sessions.len(), 1);
+    assert!(record.record.last_login_at.is_none());
+
+    let body = UserModerationPayload {
+        disabled: Some(true),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/admin/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    db.users()
+        .moderate(
+            1,
+            UserModeration {
+                disabled: Some(true),
+                ..Default::default()
+            },
+            Actor::System,
+        )
+        .await
+        .unwrap();
+    assert!(db.users().sessions(1).await.unwrap().is_empty());
+
+    // disabled users can't use new sessions either
+    for router in [router, AuthenticatedRouter::new(db_pool.clone()).await] {
+        let response = router.get("/users/me").await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    db.users()
+        .moderate(
+            1,
+            UserModeration {
+                disabled: Some(false),
+                ..Default::default()
+            },
+            Actor::System,
+        )
+        .await
+        .unwrap();
+    let router = AuthenticatedRouter::new(db_pool).await;
+    let response = router.clone().get("/users/me").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = router.clone().delete("/admin/users/1/sessions").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.get("/users/me").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test(fixtures("users"))]
+#[test_log::test]
+async fn impersonate_user(db_pool: SqlitePool) {
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+
+    let body = ImpersonationStartPayload::default();
+    let response = router
+        .clone()
+        .post("/admin/users/1/impersonate", &body)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = router
+        .clone()
+        .post("/admin/users/42/impersonate", &body)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = router
+        .clone()
+        .post("/admin/users/2/impersonate", &body)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router.clone().get("/users/me").await;
+    let me: CurrentUserPayload = response.into_struct().await;
+    assert_eq!(me.user.id, 2);
+    assert!(!me.user.admin);
+    let impersonation = me.impersonation.unwrap();
+    assert_eq!(impersonation.admin_id, 1);
+    assert!(!impersonation.allow_writes);
+    assert!(impersonation.banner.contains("wafel"));
+
+    // writes and admin routes are off limits
+    let body = UserPatchPayload {
+        about: Some("Hacked".into()),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/users/2", &body).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = router.clone().get("/admin/impersonations").await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = router.clone().delete("/impersonation").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.clone().delete("/impersonation").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = router.clone().get("/users/me").await;
+    assert_eq!(
+        response.into_struct::<AuthenticatedUser>().await,
+        TestObjects::authenticated_user_1()
+    );
+
+    let start = ImpersonationStartPayload { allow_writes: true };
+    let response = router
+        .clone()
+        .post("/admin/users/2/impersonate", start)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router.clone().patch("/users/2", &body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the change is made by the admin, not by the user
+    let response = router.clone().get("/users/me/history").await;
+    let history: ProfileHistoryPayload = response.into_struct().await;
+    assert_eq!(history.total, 1);
+    assert_eq!(history.changes[0].actor_user_id, Some(1));
+    assert_eq!(history.changes[0].actor_username.as_deref(), Some("cheese"));
+
+    // logging out ends the impersonation too
+    let response = router.clone().get("/logout").await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    let response = router.clone().get("/admin/impersonations?user_id=2").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let page: ImpersonationListPayload = response.into_struct().await;
+    assert_eq!(page.total, 2);
+    assert!(page.impersonations[0].allow_writes);
+    assert!(page.impersonations.iter().all(|imp| imp.ended_at.is_some()
+        && imp.admin_username == "cheese"
+        && imp.username == "wafel"));
+
+    // sessions that expired while impersonating end their impersonation
+    sqlx::query(
+        "INSERT INTO impersonation
+            (admin_id, admin_username, user_id, username, started_at, expires_at)
+        VALUES (1, 'cheese', 2, 'wafel', '2024-12-20 00:00:00', '2025-01-01T00:00:00+00:00');",
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+    let response = router.clone().get("/admin/impersonations").await;
+    let page: ImpersonationListPayload = response.into_struct().await;
+    assert_eq!(page.total, 3);
+    assert!(page.impersonations.iter().all(|imp| imp.ended_at.is_some()));
+
+    // disabled users can't be impersonated
+    sqlx::query("UPDATE user SET disabled_at = CURRENT_TIMESTAMP WHERE id = 2;")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+    let response = router
+        .post(
+            "/admin/users/2/impersonate",
+            ImpersonationStartPayload::default(),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn profile_history(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let router = AuthenticatedRouter::new(db_pool).await;
+
+    let body = UserPatchPayload {
+        about: Some("Cheese is life".into()),
+        links: Some(vec![SocialLink {
+            kind: LinkKind::Github,
+            value: "cheese".into(),
+        }]),
+        ..Default::default()
+    };
+    let response = router.clone().patch("/users/1", body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = router.clone().put("/users/1/tags/3", "").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    db.users()
+        .change_image(1, true, Actor::User(1))
+        .await
+        .unwrap();
+
+    let response = router.clone().get("/users/me/history").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let history: ProfileHistoryPayload = response.into_struct().await;
+    assert_eq!(history.total, 4);
+    let fields: Vec<&str> = history.changes.iter().map(|c| c.field.as_str()).collect();
+    assert_eq!(fields, ["image", "tags", "links", "about"]);
+    let about = &history.changes[3];
+    assert_eq!(about.old_value, Some(TestObjects::user_1().about));
+    assert_eq!(about.new_value.as_deref(), Some("Cheese is life"));
+    assert_eq!(about.actor_username.as_deref(), Some("cheese"));
+    assert_eq!(
+        history.changes[1].new_value.as_deref(),
+        Some(r#"["eiffel"]"#)
+    );
+
+    // unchanged fields are left out
+    let body = UserPatchPayload {
+        about: Some("Cheese is life".into()),
+        ..Default::default()
+    };
+    router.clone().patch("/users/1", body).await;
+    let response = router.clone().get("/users/me/history?limit=1").await;
+    let history: ProfileHistoryPayload = response.into_struct().await;
+    assert_eq!(history.total, 4);
+    assert_eq!(history.changes.len(), 1);
+
+    // admins see who changed what on any profile
+    let body = UserModerationPayload {
+        about: Some("Removed by an admin".into()),
+        ..Default::default()
+    };
+    router.clone().patch("/admin/users/2", body).await;
+    let response = router.clone().delete("/admin/users/2/tags/1").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.get("/admin/users/2/history").await;
+    let history: ProfileHistoryPayload = response.into_struct().await;
+    assert_eq!(history.total, 2);
+    assert!(history.changes.iter().all(|c| c.actor_user_id == Some(1)));
+    assert_eq!(
+        history.changes[0].old_value.as_deref(),
+        Some(r#"["bestuur","boekentoren"]"#)
+    );
+    assert_eq!(
+        history.changes[0].new_value.as_deref(),
+        Some(r#"["boekentoren"]"#)
+    );
+}
+
+#[sqlx::test(fixtures("users", "tags"))]
+#[test_log::test]
+async fn shared_changes_are_in_history(db_pool: SqlitePool) {
+    let db = Database::new(db_pool.clone());
+    let router = AuthenticatedRouter::new(db_pool.clone()).await;
+    sqlx::query(
+        "INSERT INTO profile_field (id, name, label, kind) VALUES (1, 'motto', 'Motto', 'text');
+        INSERT INTO user_field (user_id, field_id, value) VALUES (2, 1, 'Waffles');",
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+
+    // deleting a tag or field changes the profiles that had it
+    let response = router.clone().delete("/admin/tags/1").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = router.clone().delete("/admin/profile-fields/1").await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // a new username from zauth
+    db.users()
+        .create(UserCreate {
+            id: 2,
+            username: "wafeltje".into(),
+        })
+        .await
+        .unwrap();
+
+    let response = router.get("/admin/users/2/history").await;
+    let history: ProfileHistoryPayload = response.into_struct().await;
+    let fields: Vec<&str> = history.changes.iter().map(|c| c.field.as_str()).collect();
+    assert_eq!(fields, ["username", "fields.motto", "tags"]);
+    let username = &history.changes[0];
+    assert_eq!(username.old_value.as_deref(), Some("wafel"));
+    assert_eq!(username.new_value.as_deref(), Some("wafeltje"));
+    assert_eq!(username.actor_user_id, None);
+    let field = &history.changes[1];
+    assert_eq!(field.old_value.as_deref(), Some("Waffles"));
+    assert_eq!(field.new_value, None);
+    assert_eq!(field.actor_user_id, Some(1));
+    assert_eq!(
+        history.changes[2].new_value.as_deref(),
+        Some(r#"["boekentoren"]"#)
+    );
+}
@@ -3,18 +3,63 @@ import { env } from '$env/dynamic/public';
 const BACKEND_URL = env.PUBLIC_BACKEND_URL;
 
 export type Tag = {
+	id: number;
 	name: string;
 	category: string;
+	color: string;
+	icon: boolean;
+};
+export type SocialLink = {
+	kind: 'github' | 'gitlab' | 'mattermost' | 'matrix' | 'website' | 'email';
+	value: string;
+	url: string;
+};
+export type ProfileFieldValue = {
+	name: string;
+	label: string;
+	kind: 'text' | 'enum' | 'date' | 'url';
+	value: string;
 };
 export type ProfileData = {
 	id: number;
 	username: string;
 	about: string;
+	about_html: string;
 	tags: Tag[];
+	links: SocialLink[];
+	fields: ProfileFieldValue[];
+	followers: number;
+	following: number;
+	kudos: number;
+	recent_kudos: Kudos[];
+};
+// a short public endorsement, for a tag or a free skill
+export type Kudos = {
+	id: number;
+	giver_id: number;
+	giver_username: string;
+	recipient_id: number;
+	message: string;
+	tag_id?: number;
+	tag_name?: string;
+	skill?: string;
+	created_at: string;
+	// only the recipient sees the kudos they hid
+	hidden: boolean;
 };
 export type CurrentUser = {
 	id: number;
 	username: string;
+	impersonation?: Impersonation;
+};
+
+// only set while an admin acts as the current user
+export type Impersonation = {
+	admin_id: number;
+	admin_username: string;
+	allow_writes: boolean;
+	started_at: string;
+	banner: string;
 };
 
 export function toTitleCase(str: string) {
@@ -24,6 +69,10 @@ export function toTitleCase(str: string) {
 	return str.toLowerCase().replace(/\b\w/g, (s) => s.toUpperCase());
 }
 
+export function tagIconUrl(tag: Tag, size: number = 32): string {
+	return `${BACKEND_URL}/api/image/tags/${tag.id}?size=${size}`;
+}
+
 // API Query Functions //
 export async function getCurrentUser(): Promise<CurrentUser> {
 	const response = await fetch(BACKEND_URL + '/api/users/me', {